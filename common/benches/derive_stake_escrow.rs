@@ -0,0 +1,51 @@
+use common::pda::{derive_stake_escrow_addresses, StakeEscrowAddresses, VaultOwner};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use solana_sdk::pubkey::Pubkey;
+
+fn vault_owner_pairs(vault_count: usize, owner_count: usize) -> Vec<VaultOwner> {
+    let vaults: Vec<(Pubkey, Pubkey)> = (0..vault_count)
+        .map(|_| (Pubkey::new_unique(), Pubkey::new_unique()))
+        .collect();
+
+    (0..owner_count)
+        .map(|i| {
+            let (vault, stake_mint) = vaults[i % vault_count];
+            VaultOwner {
+                vault,
+                stake_mint,
+                owner: Pubkey::new_unique(),
+            }
+        })
+        .collect()
+}
+
+fn bench_derive_stake_escrow(c: &mut Criterion) {
+    let mut group = c.benchmark_group("derive_stake_escrow");
+    group.sample_size(10);
+
+    for owner_count in [1_000, 10_000] {
+        let pairs = vault_owner_pairs(8, owner_count);
+
+        group.bench_with_input(
+            BenchmarkId::new("serial", owner_count),
+            &pairs,
+            |b, pairs| {
+                b.iter(|| {
+                    pairs
+                        .iter()
+                        .map(|pair| StakeEscrowAddresses::from(*pair))
+                        .collect::<Vec<_>>()
+                })
+            },
+        );
+
+        group.bench_with_input(BenchmarkId::new("bulk", owner_count), &pairs, |b, pairs| {
+            b.iter(|| derive_stake_escrow_addresses(black_box(pairs)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_derive_stake_escrow);
+criterion_main!(benches);
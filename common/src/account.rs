@@ -0,0 +1,24 @@
+use borsh::BorshDeserialize;
+
+/// Deserialize an Anchor account of a foreign program after checking its discriminator. Only the
+/// leading fields declared in `T` are read, so `T` may describe a prefix of the account.
+pub(crate) fn deserialize_account<T: BorshDeserialize>(
+    data: &[u8],
+    discm: [u8; 8],
+) -> std::io::Result<T> {
+    let maybe_discm = data.get(..8).ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "account data too short")
+    })?;
+
+    if maybe_discm != discm {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "discm does not match. Expected: {:?}. Received: {:?}",
+                discm, maybe_discm
+            ),
+        ));
+    }
+
+    T::deserialize(&mut &data[8..])
+}
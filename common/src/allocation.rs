@@ -0,0 +1,348 @@
+use crate::{decoder::TopStakerListState, top_list::get_top_staker_list_entry_stake_amount};
+use m3m3::{StakeForFeeError, TopStakerInfo};
+use solana_sdk::pubkey::Pubkey;
+use std::{cmp::Reverse, collections::HashMap};
+
+const BISECTION_ITERATIONS: usize = 200;
+
+/// A fee vault the budget of `stake_mint` can be staked into.
+#[derive(Clone, Copy, Debug)]
+pub struct VaultCandidate<'a> {
+    pub vault: Pubkey,
+    pub stake_mint: Pubkey,
+    pub top_staker_info: &'a TopStakerInfo,
+    pub top_staker_list: &'a TopStakerListState,
+    /// Quote fee released to the top list per day, e.g. from
+    /// [`get_fee_emission_per_day`](crate::performance::get_fee_emission_per_day).
+    pub quote_fee_per_day: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VaultAllocation {
+    pub vault: Pubkey,
+    pub stake_mint: Pubkey,
+    pub stake_amount: u64,
+    pub projected_quote_fee_per_day: u64,
+    /// `effective_stake_amount` once staked, net of the displaced top staker.
+    pub effective_stake_amount: u64,
+    /// Owner of the top staker pushed out of a full top list.
+    pub displaced_owner: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AllocationPlan {
+    /// Sorted by projected quote fee income, highest first.
+    pub allocations: Vec<VaultAllocation>,
+    /// Budget left per stake mint, when no vault earns more from it.
+    pub unallocated: HashMap<Pubkey, u64>,
+}
+
+impl AllocationPlan {
+    pub fn projected_quote_fee_per_day(&self) -> u128 {
+        self.allocations
+            .iter()
+            .map(|allocation| u128::from(allocation.projected_quote_fee_per_day))
+            .sum()
+    }
+}
+
+/// Income of a new staker in one vault: `stake * fee / (base_stake + stake)` for any stake of at
+/// least `entry_stake_amount`, nothing below.
+#[derive(Clone, Copy, Debug)]
+struct IncomeCurve {
+    entry_stake_amount: u64,
+    base_stake_amount: u64,
+    fee: u64,
+    displaced_owner: Option<Pubkey>,
+}
+
+impl IncomeCurve {
+    fn new(candidate: &VaultCandidate) -> Option<Self> {
+        let top_staker_info = candidate.top_staker_info;
+        if top_staker_info.top_list_length == 0 || candidate.quote_fee_per_day == 0 {
+            return None;
+        }
+
+        if top_staker_info.current_length < top_staker_info.top_list_length {
+            return Some(Self {
+                entry_stake_amount: 1,
+                base_stake_amount: top_staker_info.effective_stake_amount,
+                fee: candidate.quote_fee_per_day,
+                displaced_owner: None,
+            });
+        }
+
+        // Entering a full list replaces its smallest staker, the latest joiner on ties.
+        let smallest = candidate
+            .top_staker_list
+            .stakers
+            .iter()
+            .filter(|staker| staker.full_balance_index >= 0)
+            .min_by_key(|staker| (staker.stake_amount, Reverse(staker.full_balance_index)))?;
+
+        Some(Self {
+            entry_stake_amount: get_top_staker_list_entry_stake_amount(candidate.top_staker_list),
+            base_stake_amount: top_staker_info
+                .effective_stake_amount
+                .saturating_sub(smallest.stake_amount),
+            fee: candidate.quote_fee_per_day,
+            displaced_owner: Some(smallest.owner),
+        })
+    }
+
+    fn income(&self, stake_amount: u64) -> Result<u64, StakeForFeeError> {
+        if stake_amount < self.entry_stake_amount {
+            return Ok(0);
+        }
+
+        let effective_stake_amount = u128::from(self.base_stake_amount) + u128::from(stake_amount);
+        let income = u128::from(stake_amount) * u128::from(self.fee) / effective_stake_amount;
+        u64::try_from(income).map_err(|_| StakeForFeeError::TypeCastFailed)
+    }
+
+    fn marginal_income(&self, stake_amount: f64) -> f64 {
+        let base_stake_amount = self.base_stake_amount as f64;
+        self.fee as f64 * base_stake_amount / (base_stake_amount + stake_amount).powi(2)
+    }
+
+    /// Stake at which the marginal income drops to `marginal_income`, at least the entry stake.
+    fn stake_at_marginal_income(&self, marginal_income: f64) -> f64 {
+        let base_stake_amount = self.base_stake_amount as f64;
+        let stake_amount =
+            (self.fee as f64 * base_stake_amount / marginal_income).sqrt() - base_stake_amount;
+        stake_amount.max(self.entry_stake_amount as f64)
+    }
+}
+
+/// Split each stake mint's budget across its vaults to maximize the total projected quote fee
+/// income of new stake escrows.
+///
+/// A vault only pays once the stake reaches its top list entry threshold, and a stake entering a
+/// full top list displaces the smallest top staker, whose stake no longer dilutes the income.
+/// Income is concave above the threshold, so the budget is spread across a set of vaults by
+/// equalizing marginal income, and the set is improved by adding or dropping one vault at a time.
+pub fn optimize_stake_allocation(
+    candidates: &[VaultCandidate],
+    budgets: &HashMap<Pubkey, u64>,
+) -> Result<AllocationPlan, StakeForFeeError> {
+    let mut plan = AllocationPlan::default();
+
+    for (stake_mint, budget) in budgets {
+        let (vaults, curves): (Vec<_>, Vec<_>) = candidates
+            .iter()
+            .filter(|candidate| candidate.stake_mint == *stake_mint)
+            .filter_map(|candidate| Some((candidate.vault, IncomeCurve::new(candidate)?)))
+            .unzip();
+
+        let stake_amounts = allocate_budget(&curves, *budget);
+
+        let mut allocated = 0u64;
+        for ((vault, curve), stake_amount) in vaults.iter().zip(&curves).zip(stake_amounts) {
+            if stake_amount == 0 {
+                continue;
+            }
+            allocated = allocated
+                .checked_add(stake_amount)
+                .ok_or(StakeForFeeError::MathOverflow)?;
+
+            plan.allocations.push(VaultAllocation {
+                vault: *vault,
+                stake_mint: *stake_mint,
+                stake_amount,
+                projected_quote_fee_per_day: curve.income(stake_amount)?,
+                effective_stake_amount: curve
+                    .base_stake_amount
+                    .checked_add(stake_amount)
+                    .ok_or(StakeForFeeError::MathOverflow)?,
+                displaced_owner: curve.displaced_owner,
+            });
+        }
+
+        let unallocated = budget
+            .checked_sub(allocated)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        if unallocated > 0 {
+            plan.unallocated.insert(*stake_mint, unallocated);
+        }
+    }
+
+    plan.allocations.sort_by_key(|allocation| {
+        (
+            Reverse(allocation.projected_quote_fee_per_day),
+            allocation.vault,
+        )
+    });
+
+    Ok(plan)
+}
+
+/// Stake per curve, zero for the vaults left out.
+fn allocate_budget(curves: &[IncomeCurve], budget: u64) -> Vec<u64> {
+    // Start from every affordable vault, dropping the costliest entries until all fit.
+    let mut selected: Vec<bool> = curves
+        .iter()
+        .map(|curve| curve.entry_stake_amount <= budget)
+        .collect();
+    while entry_cost(curves, &selected) > u128::from(budget) {
+        let costliest = (0..curves.len())
+            .filter(|idx| selected[*idx])
+            .max_by_key(|idx| curves[*idx].entry_stake_amount);
+        match costliest {
+            Some(idx) => selected[idx] = false,
+            None => break,
+        }
+    }
+
+    let mut best = fill(curves, &selected, budget);
+    let mut best_income = total_income(curves, &best);
+
+    loop {
+        let mut improved = false;
+
+        for idx in 0..curves.len() {
+            let mut candidate = selected.clone();
+            candidate[idx] = !candidate[idx];
+            if entry_cost(curves, &candidate) > u128::from(budget) {
+                continue;
+            }
+
+            let stake_amounts = fill(curves, &candidate, budget);
+            let income = total_income(curves, &stake_amounts);
+            if income > best_income {
+                selected = candidate;
+                best = stake_amounts;
+                best_income = income;
+                improved = true;
+            }
+        }
+
+        if !improved {
+            return best;
+        }
+    }
+}
+
+fn entry_cost(curves: &[IncomeCurve], selected: &[bool]) -> u128 {
+    curves
+        .iter()
+        .zip(selected)
+        .filter(|(_, selected)| **selected)
+        .map(|(curve, _)| u128::from(curve.entry_stake_amount))
+        .sum()
+}
+
+fn total_income(curves: &[IncomeCurve], stake_amounts: &[u64]) -> u128 {
+    curves
+        .iter()
+        .zip(stake_amounts)
+        .map(|(curve, stake_amount)| u128::from(curve.income(*stake_amount).unwrap_or_default()))
+        .sum()
+}
+
+/// Spread `budget` over the selected curves so their marginal incomes are equal, with each at
+/// least at its entry stake.
+fn fill(curves: &[IncomeCurve], selected: &[bool], budget: u64) -> Vec<u64> {
+    let selected_curves: Vec<&IncomeCurve> = curves
+        .iter()
+        .zip(selected)
+        .filter(|(_, selected)| **selected)
+        .map(|(curve, _)| curve)
+        .collect();
+
+    let mut stake_amounts = vec![0u64; curves.len()];
+    if selected_curves.is_empty() {
+        return stake_amounts;
+    }
+
+    // A vault with no other stake pays its whole fee to any stake, so extra stake earns nothing.
+    let dilutable: Vec<&IncomeCurve> = selected_curves
+        .iter()
+        .copied()
+        .filter(|curve| curve.base_stake_amount > 0)
+        .collect();
+    let budget_f64 = budget as f64;
+    let stake_sum = |marginal_income: f64| -> f64 {
+        selected_curves
+            .iter()
+            .map(|curve| {
+                if curve.base_stake_amount > 0 {
+                    curve.stake_at_marginal_income(marginal_income)
+                } else {
+                    curve.entry_stake_amount as f64
+                }
+            })
+            .sum()
+    };
+
+    let mut marginal_income = f64::INFINITY;
+    if !dilutable.is_empty() {
+        // Every selected curve sits at its entry stake at `high`, and the sum only grows as the
+        // marginal income decreases.
+        let mut high = dilutable
+            .iter()
+            .map(|curve| curve.marginal_income(curve.entry_stake_amount as f64))
+            .fold(0.0, f64::max);
+        let mut low = high;
+        while low > f64::MIN_POSITIVE && stake_sum(low) < budget_f64 {
+            low /= 2.0;
+        }
+        for _ in 0..BISECTION_ITERATIONS {
+            let mid = (low + high) / 2.0;
+            if stake_sum(mid) > budget_f64 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        marginal_income = high;
+    }
+
+    let mut allocated = 0u128;
+    for (idx, (curve, selected)) in curves.iter().zip(selected).enumerate() {
+        if !selected {
+            continue;
+        }
+        let stake_amount = if curve.base_stake_amount > 0 {
+            curve.stake_at_marginal_income(marginal_income)
+        } else {
+            curve.entry_stake_amount as f64
+        };
+        stake_amounts[idx] = (stake_amount.floor() as u64).max(curve.entry_stake_amount);
+        allocated += u128::from(stake_amounts[idx]);
+    }
+
+    // Above 2^53 the float stakes can round past the budget. The excess is taken back from the
+    // vaults paying the least for it, down to their entry stake, which fit the budget together.
+    let budget_u128 = u128::from(budget);
+    while allocated > budget_u128 {
+        let worst = (0..curves.len())
+            .filter(|idx| selected[*idx] && stake_amounts[*idx] > curves[*idx].entry_stake_amount)
+            .min_by(|a, b| {
+                let marginal = |idx: usize| curves[idx].marginal_income(stake_amounts[idx] as f64);
+                marginal(*a).total_cmp(&marginal(*b))
+            });
+        let Some(idx) = worst else {
+            break;
+        };
+        let excess = u64::try_from(allocated - budget_u128).unwrap_or(u64::MAX);
+        let cut = excess.min(stake_amounts[idx] - curves[idx].entry_stake_amount);
+        stake_amounts[idx] -= cut;
+        allocated -= u128::from(cut);
+    }
+
+    // Rounding leftovers go to the vault paying the most for them.
+    if allocated < budget_u128 {
+        let best = (0..curves.len())
+            .filter(|idx| selected[*idx] && curves[*idx].base_stake_amount > 0)
+            .max_by(|a, b| {
+                let marginal = |idx: usize| curves[idx].marginal_income(stake_amounts[idx] as f64);
+                marginal(*a).total_cmp(&marginal(*b))
+            });
+        if let Some(idx) = best {
+            // Below the budget, so the difference and the new stake fit a u64.
+            stake_amounts[idx] += (budget_u128 - allocated) as u64;
+        }
+    }
+
+    stake_amounts
+}
@@ -0,0 +1,60 @@
+use crate::{constants::*, pda::*};
+use m3m3::{
+    initialize_vault_with_config_ix, Config, ConfigAccount, InitializeVaultWithConfigIxArgs,
+    InitializeVaultWithConfigKeys, InitializeVaultWithConfigParams,
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_program};
+
+pub fn decode_config(data: &[u8]) -> std::io::Result<Config> {
+    Ok(ConfigAccount::deserialize(data)?.0)
+}
+
+/// Keys for `initialize_vault_with_config`. The lock escrow must already exist and be owned by
+/// the fee vault derived from `pool`.
+pub fn initialize_vault_with_config_keys(
+    pool: Pubkey,
+    stake_mint: Pubkey,
+    quote_mint: Pubkey,
+    config_index: u64,
+    payer: Pubkey,
+) -> InitializeVaultWithConfigKeys {
+    let vault = derive_m3m3_vault_key(pool);
+
+    InitializeVaultWithConfigKeys {
+        vault,
+        stake_token_vault: derive_associated_token_key(vault, stake_mint),
+        quote_token_vault: derive_associated_token_key(vault, quote_mint),
+        top_staker_list: derive_top_staker_list_key(vault),
+        full_balance_list: derive_full_balance_list_key(vault),
+        pool,
+        stake_mint,
+        quote_mint,
+        lock_escrow: derive_lock_escrow_key(pool, vault),
+        config: derive_config_key(config_index),
+        payer,
+        system_program: system_program::ID,
+        token_program: TOKEN_PROGRAM_ID,
+        associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+        event_authority: derive_m3m3_event_authority_key(),
+        program: m3m3::ID,
+    }
+}
+
+pub fn create_vault_from_config_ix(
+    pool: Pubkey,
+    stake_mint: Pubkey,
+    quote_mint: Pubkey,
+    config_index: u64,
+    payer: Pubkey,
+    start_fee_distribute_timestamp: Option<u64>,
+) -> std::io::Result<Instruction> {
+    initialize_vault_with_config_ix(
+        initialize_vault_with_config_keys(pool, stake_mint, quote_mint, config_index, payer),
+        InitializeVaultWithConfigIxArgs {
+            params: InitializeVaultWithConfigParams {
+                start_fee_distribute_timestamp,
+                padding: [0u8; 64],
+            },
+        },
+    )
+}
@@ -0,0 +1,19 @@
+use solana_sdk::{pubkey, pubkey::Pubkey};
+
+pub const DYNAMIC_AMM_PROGRAM_ID: Pubkey = pubkey!("Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB");
+pub const DYNAMIC_VAULT_PROGRAM_ID: Pubkey =
+    pubkey!("24Uqj9JCLxUeoC3hGfh5W3s9FM9uCHDS2SG3LYwBpyTi");
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Minimum seconds between two fee claims from the lock escrow.
+pub const MIN_LOCK_ESCROW_CLAIM_FEE_DURATION: i64 = 300;
+
+/// `StakeEscrow::full_balance_index` of an escrow without a full balance list slot, because the
+/// list was full when it was created or a larger staker reclaimed its slot. It reclaims one the
+/// next time its stake grows.
+pub const NO_FULL_BALANCE_INDEX: u64 = u64::MAX;
+
+/// Maximum number of entries in the full balance list.
+pub const FULL_BALANCE_LIST_HARD_LIMIT: u64 = 10_000;
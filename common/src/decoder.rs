@@ -0,0 +1,55 @@
+use borsh::BorshDeserialize;
+use m3m3::{
+    FeeVault, FullBalanceListMetadata, FullBalanceListMetadataAccount, StakerBalance,
+    StakerMetadata, TopListMetadata, TopListMetadataAccount,
+};
+
+const TOP_LIST_METADATA_SIZE: usize = 8 + 32;
+const FULL_BALANCE_LIST_METADATA_SIZE: usize = 8 + 40;
+
+/// Top staker list account with its trailing `StakerMetadata` entries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopStakerListState {
+    pub metadata: TopListMetadata,
+    pub stakers: Vec<StakerMetadata>,
+}
+
+/// Full balance list account with its trailing `StakerBalance` entries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FullBalanceListState {
+    pub metadata: FullBalanceListMetadata,
+    pub stakers: Vec<StakerBalance>,
+}
+
+/// Decode the top staker list. The account is sized for `top_list_length` entries, of which only
+/// the first `current_length` are read.
+pub fn decode_top_staker_list_state(
+    fee_vault: &FeeVault,
+    data: &[u8],
+) -> std::io::Result<TopStakerListState> {
+    let metadata = TopListMetadataAccount::deserialize(data)?.0;
+    let stakers = decode_entries(
+        data,
+        TOP_LIST_METADATA_SIZE,
+        fee_vault.top_staker_info.current_length,
+    )?;
+
+    Ok(TopStakerListState { metadata, stakers })
+}
+
+/// Decode the full balance list and its first `metadata.length` entries.
+pub fn decode_full_balance_list_state(data: &[u8]) -> std::io::Result<FullBalanceListState> {
+    let metadata = FullBalanceListMetadataAccount::deserialize(data)?.0;
+    let stakers = decode_entries(data, FULL_BALANCE_LIST_METADATA_SIZE, metadata.length)?;
+
+    Ok(FullBalanceListState { metadata, stakers })
+}
+
+fn decode_entries<T: BorshDeserialize>(
+    data: &[u8],
+    offset: usize,
+    length: u64,
+) -> std::io::Result<Vec<T>> {
+    let mut reader = data.get(offset..).unwrap_or_default();
+    (0..length).map(|_| T::deserialize(&mut reader)).collect()
+}
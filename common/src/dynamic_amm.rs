@@ -0,0 +1,179 @@
+use crate::{
+    account::deserialize_account,
+    constants::MIN_LOCK_ESCROW_CLAIM_FEE_DURATION,
+    dynamic_vault::{get_amount_by_share, Vault},
+    math::{q64::SCALE_OFFSET, u128x128_math::shl_div, utils_math::safe_mul_div_cast, U256},
+};
+use borsh::BorshDeserialize;
+use m3m3::{FeeVault, Rounding, StakeForFeeError};
+use solana_sdk::pubkey::Pubkey;
+
+pub const POOL_ACCOUNT_DISCM: [u8; 8] = [241, 154, 109, 4, 17, 177, 109, 188];
+pub const LOCK_ESCROW_ACCOUNT_DISCM: [u8; 8] = [190, 106, 121, 6, 200, 182, 21, 75];
+
+/// Leading fields of the dynamic AMM pool account. The remaining fields are not needed to price
+/// the pool LP.
+#[derive(Clone, Debug, BorshDeserialize, PartialEq)]
+pub struct Pool {
+    pub lp_mint: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub a_vault: Pubkey,
+    pub b_vault: Pubkey,
+    pub a_vault_lp: Pubkey,
+    pub b_vault_lp: Pubkey,
+    pub a_vault_lp_bump: u8,
+    pub enabled: bool,
+}
+
+pub fn decode_pool(data: &[u8]) -> std::io::Result<Pool> {
+    deserialize_account(data, POOL_ACCOUNT_DISCM)
+}
+
+/// Dynamic AMM lock escrow account.
+#[derive(Clone, Debug, BorshDeserialize, PartialEq)]
+pub struct LockEscrow {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub escrow_vault: Pubkey,
+    pub bump: u8,
+    pub total_locked_amount: u64,
+    pub lp_per_token: u128,
+    pub unclaimed_fee_pending: u64,
+    pub a_fee: u64,
+    pub b_fee: u64,
+}
+
+pub fn decode_lock_escrow(data: &[u8]) -> std::io::Result<LockEscrow> {
+    deserialize_account(data, LOCK_ESCROW_ACCOUNT_DISCM)
+}
+
+/// Token account balances and mint supplies backing a pool, read from `Pool::a_vault_lp`,
+/// `Pool::b_vault_lp`, the vaults' LP mints and `Pool::lp_mint`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolLpAmounts {
+    pub a_vault_lp_amount: u64,
+    pub b_vault_lp_amount: u64,
+    pub a_vault_lp_supply: u64,
+    pub b_vault_lp_supply: u64,
+    pub pool_lp_supply: u64,
+}
+
+/// Token A/B amounts held by the pool through its vault LP, net of the vaults' locked profit.
+pub fn get_token_balances(
+    current_time: i64,
+    a_vault: &Vault,
+    b_vault: &Vault,
+    lp_amounts: &PoolLpAmounts,
+) -> Result<(u64, u64), StakeForFeeError> {
+    let token_a_amount = get_amount_by_share(
+        a_vault,
+        current_time,
+        lp_amounts.a_vault_lp_amount,
+        lp_amounts.a_vault_lp_supply,
+    )?;
+    let token_b_amount = get_amount_by_share(
+        b_vault,
+        current_time,
+        lp_amounts.b_vault_lp_amount,
+        lp_amounts.b_vault_lp_supply,
+    )?;
+
+    Ok((token_a_amount, token_b_amount))
+}
+
+/// Virtual price of the pool LP as `sqrt(k) / lp_supply` in Q64.64. Constant product pools only.
+pub fn get_virtual_price(
+    token_a_amount: u64,
+    token_b_amount: u64,
+    lp_supply: u64,
+) -> Result<u128, StakeForFeeError> {
+    if lp_supply == 0 {
+        return Ok(0);
+    }
+
+    let k = U256::from(token_a_amount) * U256::from(token_b_amount);
+    // At most `u64::MAX`.
+    let d = k.integer_sqrt().as_u128();
+    shl_div(d, lp_supply.into(), SCALE_OFFSET, Rounding::Down)
+}
+
+/// Fee A/B the fee vault would claim from its lock escrow at `current_time`.
+///
+/// The claim is rate limited to once per `MIN_LOCK_ESCROW_CLAIM_FEE_DURATION`, so nothing is
+/// claimable before that.
+pub fn get_locked_escrow_pending_fee(
+    current_time: i64,
+    fee_vault: &FeeVault,
+    lock_escrow: &LockEscrow,
+    a_vault: &Vault,
+    b_vault: &Vault,
+    lp_amounts: &PoolLpAmounts,
+) -> Result<(u64, u64), StakeForFeeError> {
+    let last_claim_fee_at = fee_vault.top_staker_info.last_claim_fee_at;
+    if current_time <= last_claim_fee_at
+        || current_time - last_claim_fee_at < MIN_LOCK_ESCROW_CLAIM_FEE_DURATION
+    {
+        return Ok((0, 0));
+    }
+
+    let (token_a_amount, token_b_amount) =
+        get_token_balances(current_time, a_vault, b_vault, lp_amounts)?;
+
+    let current_lp_per_token =
+        get_virtual_price(token_a_amount, token_b_amount, lp_amounts.pool_lp_supply)?;
+
+    if current_lp_per_token <= lock_escrow.lp_per_token {
+        return Ok((0, 0));
+    }
+
+    let new_fee: u64 = safe_mul_div_cast(
+        lock_escrow.total_locked_amount.into(),
+        current_lp_per_token - lock_escrow.lp_per_token,
+        current_lp_per_token,
+        Rounding::Down,
+    )?;
+
+    if new_fee == 0 {
+        return Ok((0, 0));
+    }
+
+    let a_vault_lp_to_burn = get_vault_lp_to_burn(
+        new_fee,
+        lp_amounts.a_vault_lp_amount,
+        lp_amounts.pool_lp_supply,
+    )?;
+    let b_vault_lp_to_burn = get_vault_lp_to_burn(
+        new_fee,
+        lp_amounts.b_vault_lp_amount,
+        lp_amounts.pool_lp_supply,
+    )?;
+
+    let fee_a = get_amount_by_share(
+        a_vault,
+        current_time,
+        a_vault_lp_to_burn,
+        lp_amounts.a_vault_lp_supply,
+    )?;
+    let fee_b = get_amount_by_share(
+        b_vault,
+        current_time,
+        b_vault_lp_to_burn,
+        lp_amounts.b_vault_lp_supply,
+    )?;
+
+    Ok((fee_a, fee_b))
+}
+
+fn get_vault_lp_to_burn(
+    pool_lp_amount: u64,
+    vault_lp_amount: u64,
+    pool_lp_supply: u64,
+) -> Result<u64, StakeForFeeError> {
+    safe_mul_div_cast(
+        pool_lp_amount.into(),
+        vault_lp_amount.into(),
+        pool_lp_supply.into(),
+        Rounding::Down,
+    )
+}
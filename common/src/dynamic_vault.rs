@@ -0,0 +1,87 @@
+use crate::account::deserialize_account;
+use borsh::BorshDeserialize;
+use m3m3::StakeForFeeError;
+use solana_sdk::pubkey::Pubkey;
+
+pub const VAULT_ACCOUNT_DISCM: [u8; 8] = [211, 8, 232, 43, 2, 152, 117, 119];
+
+pub const LOCKED_PROFIT_DEGRADATION_DENOMINATOR: u128 = 1_000_000_000_000;
+
+#[derive(Clone, Debug, BorshDeserialize, PartialEq)]
+pub struct VaultBumps {
+    pub vault_bump: u8,
+    pub token_vault_bump: u8,
+}
+
+#[derive(Clone, Debug, BorshDeserialize, PartialEq)]
+pub struct LockedProfitTracker {
+    pub last_updated_locked_profit: u64,
+    pub last_report: u64,
+    pub locked_profit_degradation: u64,
+}
+
+/// Dynamic vault account.
+#[derive(Clone, Debug, BorshDeserialize, PartialEq)]
+pub struct Vault {
+    pub enabled: u8,
+    pub bumps: VaultBumps,
+    pub total_amount: u64,
+    pub token_vault: Pubkey,
+    pub fee_vault: Pubkey,
+    pub token_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub strategies: [Pubkey; 30],
+    pub base: Pubkey,
+    pub admin: Pubkey,
+    pub operator: Pubkey,
+    pub locked_profit_tracker: LockedProfitTracker,
+}
+
+pub fn decode_vault(data: &[u8]) -> std::io::Result<Vault> {
+    deserialize_account(data, VAULT_ACCOUNT_DISCM)
+}
+
+/// Profit from the last rebalance that is still locked at `current_time`. It degrades linearly at
+/// `locked_profit_degradation / LOCKED_PROFIT_DEGRADATION_DENOMINATOR` per second.
+pub fn calculate_locked_profit(vault: &Vault, current_time: i64) -> u64 {
+    let tracker = &vault.locked_profit_tracker;
+
+    let duration = u64::try_from(current_time)
+        .unwrap_or_default()
+        .saturating_sub(tracker.last_report);
+    let locked_fund_ratio = u128::from(duration) * u128::from(tracker.locked_profit_degradation);
+
+    if locked_fund_ratio > LOCKED_PROFIT_DEGRADATION_DENOMINATOR {
+        return 0;
+    }
+
+    let locked_profit = u128::from(tracker.last_updated_locked_profit)
+        * (LOCKED_PROFIT_DEGRADATION_DENOMINATOR - locked_fund_ratio)
+        / LOCKED_PROFIT_DEGRADATION_DENOMINATOR;
+
+    // Bounded by `last_updated_locked_profit`.
+    locked_profit as u64
+}
+
+/// Vault liquidity withdrawable at `current_time`, i.e. `total_amount` minus the locked profit.
+pub fn get_unlocked_amount(vault: &Vault, current_time: i64) -> Result<u64, StakeForFeeError> {
+    vault
+        .total_amount
+        .checked_sub(calculate_locked_profit(vault, current_time))
+        .ok_or(StakeForFeeError::MathOverflow)
+}
+
+/// Underlying token amount of `share` vault LP out of `total_supply`, rounded down.
+pub fn get_amount_by_share(
+    vault: &Vault,
+    current_time: i64,
+    share: u64,
+    total_supply: u64,
+) -> Result<u64, StakeForFeeError> {
+    let unlocked_amount = get_unlocked_amount(vault, current_time)?;
+
+    let amount = (u128::from(unlocked_amount) * u128::from(share))
+        .checked_div(u128::from(total_supply))
+        .ok_or(StakeForFeeError::MathOverflow)?;
+    u64::try_from(amount).map_err(|_| StakeForFeeError::TypeCastFailed)
+}
@@ -0,0 +1,144 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use m3m3::{
+    AddNewUserToTopHolder, CancelUnstakeSucceed, ClaimFeeSucceed, FeeEmission, ReclaimIndex,
+    RemoveUserFromTopHolder, StakeEscrowCreated, UnstakeCreated, UpdateUnstakeLockDuration,
+    UserStake, WithdrawSucceed,
+};
+use solana_sdk::pubkey::Pubkey;
+
+/// Declares a struct with the public fields of a program event, whose generated fields are
+/// private, converting from and into the event through their shared borsh layout.
+macro_rules! event_fields {
+    ($($event:ident => $fields:ident { $($field:ident: $ty:ty,)* })*) => {$(
+        #[doc = concat!("Fields of [`", stringify!($event), "`].")]
+        #[derive(Clone, Debug, PartialEq, Eq, BorshDeserialize, BorshSerialize)]
+        pub struct $fields {
+            $(pub $field: $ty,)*
+        }
+
+        impl From<$fields> for $event {
+            fn from(fields: $fields) -> Self {
+                let data = fields.try_to_vec().expect("fields serialize");
+                Self::try_from_slice(&data).expect("same layout as the event")
+            }
+        }
+
+        impl From<&$event> for $fields {
+            fn from(event: &$event) -> Self {
+                let data = event.try_to_vec().expect("event serializes");
+                Self::try_from_slice(&data).expect("same layout as the event")
+            }
+        }
+    )*};
+}
+
+event_fields! {
+    StakeEscrowCreated => StakeEscrowCreatedFields {
+        pool: Pubkey,
+        vault: Pubkey,
+        escrow: Pubkey,
+        owner: Pubkey,
+        full_balance_index: u64,
+    }
+    UserStake => UserStakeFields {
+        pool: Pubkey,
+        vault: Pubkey,
+        owner: Pubkey,
+        stake_amount: u64,
+        total_stake_amount: u64,
+        fee_a_pending: u64,
+        fee_b_pending: u64,
+        fee_a_per_liquidity_checkpoint: u128,
+        fee_b_per_liquidity_checkpoint: u128,
+    }
+    UnstakeCreated => UnstakeCreatedFields {
+        unstake: Pubkey,
+        pool: Pubkey,
+        vault: Pubkey,
+        owner: Pubkey,
+        amount: u64,
+        new_stake_escrow_amount: u64,
+        new_stake_escrow_ongoing_total_unstake_amount: u64,
+        fee_a_pending: u64,
+        fee_b_pending: u64,
+        fee_a_per_liquidity_checkpoint: u128,
+        fee_b_per_liquidity_checkpoint: u128,
+        start_at: i64,
+        end_at: i64,
+    }
+    CancelUnstakeSucceed => CancelUnstakeSucceedFields {
+        unstake: Pubkey,
+        pool: Pubkey,
+        vault: Pubkey,
+        owner: Pubkey,
+        amount: u64,
+        new_stake_escrow_amount: u64,
+        new_stake_escrow_ongoing_total_unstake_amount: u64,
+        fee_a_pending: u64,
+        fee_b_pending: u64,
+        fee_a_per_liquidity_checkpoint: u128,
+        fee_b_per_liquidity_checkpoint: u128,
+    }
+    WithdrawSucceed => WithdrawSucceedFields {
+        unstake: Pubkey,
+        pool: Pubkey,
+        vault: Pubkey,
+        owner: Pubkey,
+        amount: u64,
+        new_stake_escrow_ongoing_total_unstake_amount: u64,
+    }
+    ClaimFeeSucceed => ClaimFeeSucceedFields {
+        stake_escrow: Pubkey,
+        pool: Pubkey,
+        vault: Pubkey,
+        owner: Pubkey,
+        fee_a_amount: u64,
+        fee_b_amount: u64,
+        total_fee_a_amount: u128,
+        total_fee_b_amount: u128,
+    }
+    FeeEmission => FeeEmissionFields {
+        pool: Pubkey,
+        vault: Pubkey,
+        token_a_claimed: u64,
+        token_b_claimed: u64,
+        token_a_released: u64,
+        token_b_released: u64,
+        cumulative_fee_a_per_liquidity: u128,
+        cumulative_fee_b_per_liquidity: u128,
+        effective_stake_amount: u64,
+    }
+    AddNewUserToTopHolder => AddNewUserToTopHolderFields {
+        pool: Pubkey,
+        vault: Pubkey,
+        owner: Pubkey,
+        stake_amount: u64,
+        fee_a_pending: u64,
+        fee_b_pending: u64,
+        fee_a_per_liquidity_checkpoint: u128,
+        fee_b_per_liquidity_checkpoint: u128,
+    }
+    RemoveUserFromTopHolder => RemoveUserFromTopHolderFields {
+        pool: Pubkey,
+        vault: Pubkey,
+        owner: Pubkey,
+        stake_amount: u64,
+        fee_a_pending: u64,
+        fee_b_pending: u64,
+        fee_a_per_liquidity_checkpoint: u128,
+        fee_b_per_liquidity_checkpoint: u128,
+    }
+    ReclaimIndex => ReclaimIndexFields {
+        vault: Pubkey,
+        in_owner: Pubkey,
+        in_owner_balance: u64,
+        out_owner: Pubkey,
+        out_owner_balance: u64,
+        reclaim_index: u64,
+    }
+    UpdateUnstakeLockDuration => UpdateUnstakeLockDurationFields {
+        vault: Pubkey,
+        old_value: u64,
+        new_value: u64,
+    }
+}
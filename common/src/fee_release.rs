@@ -0,0 +1,163 @@
+use m3m3::{FeeVault, StakeForFeeError};
+use std::num::NonZeroU64;
+
+/// Split of the vault's locked fees at a point in time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeeRelease {
+    pub released_fee_a: u64,
+    pub released_fee_b: u64,
+    pub locked_fee_a: u64,
+    pub locked_fee_b: u64,
+}
+
+/// Linear release of `locked_fee` over `seconds_to_full_unlock` since `last_updated_at`.
+pub fn get_released_fee(
+    locked_fee: u64,
+    last_updated_at: i64,
+    seconds_to_full_unlock: u64,
+    current_time: i64,
+) -> Result<u64, StakeForFeeError> {
+    let seconds_elapsed = current_time.saturating_sub(last_updated_at).max(0) as u64;
+
+    if seconds_elapsed >= seconds_to_full_unlock {
+        return Ok(locked_fee);
+    }
+
+    let released_fee =
+        u128::from(locked_fee) * u128::from(seconds_elapsed) / u128::from(seconds_to_full_unlock);
+    u64::try_from(released_fee).map_err(|_| StakeForFeeError::TypeCastFailed)
+}
+
+/// Fees released to the top list at `current_time`, assuming no new fee is claimed from the lock
+/// escrow in between.
+pub fn get_released_fees(
+    fee_vault: &FeeVault,
+    current_time: i64,
+) -> Result<FeeRelease, StakeForFeeError> {
+    get_released_fees_with_pending_claim(fee_vault, current_time, 0, 0)
+}
+
+/// Same as [`get_released_fees`] but with `pending_fee_a/b`, claimable from the lock escrow, added
+/// to the locked fees first. This matches the TS `getFarmReleasedFees`.
+pub fn get_released_fees_with_pending_claim(
+    fee_vault: &FeeVault,
+    current_time: i64,
+    pending_fee_a: u64,
+    pending_fee_b: u64,
+) -> Result<FeeRelease, StakeForFeeError> {
+    let top_staker_info = &fee_vault.top_staker_info;
+    let seconds_to_full_unlock = fee_vault.configuration.seconds_to_full_unlock;
+
+    let locked_fee_a = top_staker_info
+        .locked_fee_a
+        .checked_add(pending_fee_a)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+    let locked_fee_b = top_staker_info
+        .locked_fee_b
+        .checked_add(pending_fee_b)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+
+    let released_fee_a = get_released_fee(
+        locked_fee_a,
+        top_staker_info.last_updated_at,
+        seconds_to_full_unlock,
+        current_time,
+    )?;
+    let released_fee_b = get_released_fee(
+        locked_fee_b,
+        top_staker_info.last_updated_at,
+        seconds_to_full_unlock,
+        current_time,
+    )?;
+
+    Ok(FeeRelease {
+        released_fee_a,
+        released_fee_b,
+        locked_fee_a: locked_fee_a - released_fee_a,
+        locked_fee_b: locked_fee_b - released_fee_b,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeReleaseInterval {
+    pub start: i64,
+    pub end: i64,
+    pub released_fee_a: u64,
+    pub released_fee_b: u64,
+}
+
+/// Iterator over the fees released per `interval` seconds, from `start_time` until the currently
+/// locked fees are fully unlocked.
+#[derive(Clone, Debug)]
+pub struct FeeReleaseSchedule {
+    locked_fee_a: u64,
+    locked_fee_b: u64,
+    last_updated_at: i64,
+    seconds_to_full_unlock: u64,
+    full_unlock_at: i64,
+    interval: i64,
+    next_start: i64,
+}
+
+impl FeeReleaseSchedule {
+    pub fn new(fee_vault: &FeeVault, start_time: i64, interval: NonZeroU64) -> Self {
+        let top_staker_info = &fee_vault.top_staker_info;
+        let seconds_to_full_unlock = fee_vault.configuration.seconds_to_full_unlock;
+
+        Self {
+            locked_fee_a: top_staker_info.locked_fee_a,
+            locked_fee_b: top_staker_info.locked_fee_b,
+            last_updated_at: top_staker_info.last_updated_at,
+            seconds_to_full_unlock,
+            full_unlock_at: top_staker_info
+                .last_updated_at
+                .saturating_add(i64::try_from(seconds_to_full_unlock).unwrap_or(i64::MAX)),
+            interval: i64::try_from(interval.get()).unwrap_or(i64::MAX),
+            next_start: start_time,
+        }
+    }
+
+    fn released_at(&self, time: i64) -> (u64, u64) {
+        // Both amounts are bounded by the locked fees, so the cast cannot fail.
+        let released_fee_a = get_released_fee(
+            self.locked_fee_a,
+            self.last_updated_at,
+            self.seconds_to_full_unlock,
+            time,
+        )
+        .unwrap_or(self.locked_fee_a);
+        let released_fee_b = get_released_fee(
+            self.locked_fee_b,
+            self.last_updated_at,
+            self.seconds_to_full_unlock,
+            time,
+        )
+        .unwrap_or(self.locked_fee_b);
+
+        (released_fee_a, released_fee_b)
+    }
+}
+
+impl Iterator for FeeReleaseSchedule {
+    type Item = FeeReleaseInterval;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_start >= self.full_unlock_at {
+            return None;
+        }
+
+        let start = self.next_start;
+        let end = start.saturating_add(self.interval);
+        self.next_start = end;
+
+        let (start_fee_a, start_fee_b) = self.released_at(start);
+        let (end_fee_a, end_fee_b) = self.released_at(end);
+
+        Some(FeeReleaseInterval {
+            start,
+            end,
+            released_fee_a: end_fee_a - start_fee_a,
+            released_fee_b: end_fee_b - start_fee_b,
+        })
+    }
+}
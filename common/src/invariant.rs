@@ -0,0 +1,309 @@
+use crate::{
+    constants::NO_FULL_BALANCE_INDEX,
+    decoder::{FullBalanceListState, TopStakerListState},
+    math::fee::get_stake_escrow_pending_fees,
+    pda::derive_stake_escrow_key,
+};
+use m3m3::{FeeVault, StakeEscrow, StakeForFeeError, Unstake};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+
+/// Every account of a vault, and the balances of its stake and quote token vaults.
+#[derive(Clone, Copy, Debug)]
+pub struct VaultAccounts<'a> {
+    pub fee_vault: &'a FeeVault,
+    pub top_staker_list: &'a TopStakerListState,
+    pub full_balance_list: &'a FullBalanceListState,
+    pub stake_escrows: &'a [StakeEscrow],
+    pub unstakes: &'a [Unstake],
+    pub stake_token_balance: u64,
+    pub quote_token_balance: u64,
+    /// Whether the stake token is the pool's token A, so fee A is paid in the stake token.
+    pub is_stake_token_a: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    StakeEscrowCountMismatch {
+        metrics: u64,
+        stake_escrows: u64,
+    },
+    TotalStakedAmountMismatch {
+        metrics: u64,
+        stake_escrows: u128,
+    },
+    OngoingUnstakeAmountMismatch {
+        metrics: u64,
+        unstakes: u128,
+    },
+    EffectiveStakeAmountMismatch {
+        top_staker_info: u64,
+        top_staker_list: u128,
+    },
+    TopListLengthMismatch {
+        top_staker_info: u64,
+        top_staker_list: u64,
+    },
+    /// A list entry or unstake refers to an owner or stake escrow that was not provided.
+    MissingStakeEscrow {
+        owner: Option<Pubkey>,
+        stake_escrow: Pubkey,
+    },
+    /// `StakeEscrow::in_top_list` disagrees with the top staker list or the full balance list
+    /// entry.
+    TopListFlagMismatch {
+        owner: Pubkey,
+        stake_escrow: bool,
+        top_staker_list: bool,
+        full_balance_list: Option<bool>,
+    },
+    TopListStakeAmountMismatch {
+        owner: Pubkey,
+        stake_escrow: u64,
+        top_staker_list: u64,
+    },
+    /// `StakeEscrow::full_balance_index` is neither `NO_FULL_BALANCE_INDEX` nor points to an
+    /// entry of the same owner. `full_balance_list_owner` is `None` past the end of the list.
+    FullBalanceIndexMismatch {
+        owner: Pubkey,
+        full_balance_index: u64,
+        full_balance_list_owner: Option<Pubkey>,
+    },
+    FullBalanceMismatch {
+        owner: Pubkey,
+        stake_escrow: u64,
+        full_balance_list: u64,
+    },
+    EscrowUnstakeAmountMismatch {
+        owner: Pubkey,
+        stake_escrow: u64,
+        unstakes: u128,
+    },
+    InsufficientStakeTokenBalance {
+        balance: u64,
+        required: u128,
+    },
+    InsufficientQuoteTokenBalance {
+        balance: u64,
+        required: u128,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InvariantReport {
+    pub violations: Vec<InvariantViolation>,
+}
+
+impl InvariantReport {
+    pub fn is_consistent(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Check the bookkeeping of a vault against its list entries, stake escrows and unstakes, and
+/// that its token vaults cover the staked, unstaking, unclaimed and locked amounts.
+///
+/// Unclaimed fees are computed up to the cumulative fee per liquidity stored in the vault, as the
+/// fees released after that are still counted in `locked_fee_a/b`.
+pub fn check_vault_invariants(
+    accounts: &VaultAccounts,
+) -> Result<InvariantReport, StakeForFeeError> {
+    let VaultAccounts {
+        fee_vault,
+        top_staker_list,
+        full_balance_list,
+        stake_escrows,
+        unstakes,
+        ..
+    } = *accounts;
+    let metrics = &fee_vault.metrics;
+    let top_staker_info = &fee_vault.top_staker_info;
+
+    let mut violations = vec![];
+
+    if metrics.total_stake_escrow_count != stake_escrows.len() as u64 {
+        violations.push(InvariantViolation::StakeEscrowCountMismatch {
+            metrics: metrics.total_stake_escrow_count,
+            stake_escrows: stake_escrows.len() as u64,
+        });
+    }
+
+    let total_staked_amount: u128 = stake_escrows
+        .iter()
+        .map(|stake_escrow| u128::from(stake_escrow.stake_amount))
+        .sum();
+    if u128::from(metrics.total_staked_amount) != total_staked_amount {
+        violations.push(InvariantViolation::TotalStakedAmountMismatch {
+            metrics: metrics.total_staked_amount,
+            stake_escrows: total_staked_amount,
+        });
+    }
+
+    let ongoing_unstake_amount: u128 = unstakes
+        .iter()
+        .map(|unstake| u128::from(unstake.unstake_amount))
+        .sum();
+    if u128::from(metrics.ongoing_total_partial_unstake_amount) != ongoing_unstake_amount {
+        violations.push(InvariantViolation::OngoingUnstakeAmountMismatch {
+            metrics: metrics.ongoing_total_partial_unstake_amount,
+            unstakes: ongoing_unstake_amount,
+        });
+    }
+
+    let top_stakers: HashMap<Pubkey, u64> = top_staker_list
+        .stakers
+        .iter()
+        .filter(|staker| staker.full_balance_index >= 0)
+        .map(|staker| (staker.owner, staker.stake_amount))
+        .collect();
+
+    let effective_stake_amount: u128 = top_stakers.values().copied().map(u128::from).sum();
+    if u128::from(top_staker_info.effective_stake_amount) != effective_stake_amount {
+        violations.push(InvariantViolation::EffectiveStakeAmountMismatch {
+            top_staker_info: top_staker_info.effective_stake_amount,
+            top_staker_list: effective_stake_amount,
+        });
+    }
+    if top_staker_info.current_length != top_stakers.len() as u64 {
+        violations.push(InvariantViolation::TopListLengthMismatch {
+            top_staker_info: top_staker_info.current_length,
+            top_staker_list: top_stakers.len() as u64,
+        });
+    }
+
+    let escrows_by_owner: HashMap<Pubkey, &StakeEscrow> = stake_escrows
+        .iter()
+        .map(|stake_escrow| (stake_escrow.owner, stake_escrow))
+        .collect();
+
+    let listed_owners = top_staker_list
+        .stakers
+        .iter()
+        .filter(|staker| staker.full_balance_index >= 0)
+        .map(|staker| staker.owner)
+        .chain(full_balance_list.stakers.iter().map(|staker| staker.owner))
+        .filter(|owner| *owner != Pubkey::default());
+    let mut missing_owners = HashSet::new();
+    for owner in listed_owners {
+        if !escrows_by_owner.contains_key(&owner) && missing_owners.insert(owner) {
+            violations.push(InvariantViolation::MissingStakeEscrow {
+                owner: Some(owner),
+                stake_escrow: derive_stake_escrow_key(full_balance_list.metadata.vault, owner),
+            });
+        }
+    }
+
+    let mut unstake_amounts: HashMap<Pubkey, u128> = HashMap::new();
+    for unstake in unstakes {
+        *unstake_amounts.entry(unstake.stake_escrow).or_default() +=
+            u128::from(unstake.unstake_amount);
+    }
+
+    let mut unclaimed_fee_a = 0u128;
+    let mut unclaimed_fee_b = 0u128;
+
+    for stake_escrow in stake_escrows {
+        let owner = stake_escrow.owner;
+        // Without a slot, because the list was full or a larger staker reclaimed it.
+        let has_full_balance_index = stake_escrow.full_balance_index != NO_FULL_BALANCE_INDEX;
+        let full_balance_staker = full_balance_list
+            .stakers
+            .get(stake_escrow.full_balance_index as usize)
+            .filter(|_| has_full_balance_index);
+        match full_balance_staker {
+            Some(staker) if staker.owner != owner => {
+                violations.push(InvariantViolation::FullBalanceIndexMismatch {
+                    owner,
+                    full_balance_index: stake_escrow.full_balance_index,
+                    full_balance_list_owner: Some(staker.owner),
+                });
+            }
+            Some(staker) if staker.balance != stake_escrow.stake_amount => {
+                violations.push(InvariantViolation::FullBalanceMismatch {
+                    owner,
+                    stake_escrow: stake_escrow.stake_amount,
+                    full_balance_list: staker.balance,
+                });
+            }
+            None if has_full_balance_index => {
+                violations.push(InvariantViolation::FullBalanceIndexMismatch {
+                    owner,
+                    full_balance_index: stake_escrow.full_balance_index,
+                    full_balance_list_owner: None,
+                });
+            }
+            _ => {}
+        }
+        let full_balance_entry = full_balance_staker.filter(|staker| staker.owner == owner);
+
+        let in_top_list = stake_escrow.in_top_list != 0;
+        let top_stake_amount = top_stakers.get(&owner).copied();
+        let full_balance_in_top_list = full_balance_entry.map(|staker| staker.is_in_top_list != 0);
+        if in_top_list != top_stake_amount.is_some()
+            || full_balance_in_top_list.is_some_and(|flag| flag != in_top_list)
+        {
+            violations.push(InvariantViolation::TopListFlagMismatch {
+                owner,
+                stake_escrow: in_top_list,
+                top_staker_list: top_stake_amount.is_some(),
+                full_balance_list: full_balance_in_top_list,
+            });
+        }
+        if let Some(top_stake_amount) = top_stake_amount {
+            if top_stake_amount != stake_escrow.stake_amount {
+                violations.push(InvariantViolation::TopListStakeAmountMismatch {
+                    owner,
+                    stake_escrow: stake_escrow.stake_amount,
+                    top_staker_list: top_stake_amount,
+                });
+            }
+        }
+
+        let key = derive_stake_escrow_key(stake_escrow.vault, owner);
+        let escrow_unstake_amount = unstake_amounts.remove(&key).unwrap_or_default();
+        if u128::from(stake_escrow.ongoing_total_partial_unstake_amount) != escrow_unstake_amount {
+            violations.push(InvariantViolation::EscrowUnstakeAmountMismatch {
+                owner,
+                stake_escrow: stake_escrow.ongoing_total_partial_unstake_amount,
+                unstakes: escrow_unstake_amount,
+            });
+        }
+
+        let (fee_a, fee_b) = get_stake_escrow_pending_fees(top_staker_info, stake_escrow, 0, 0)?;
+        unclaimed_fee_a += u128::from(fee_a);
+        unclaimed_fee_b += u128::from(fee_b);
+    }
+
+    let mut orphan_unstakes: Vec<_> = unstake_amounts.into_keys().collect();
+    orphan_unstakes.sort();
+    for stake_escrow in orphan_unstakes {
+        violations.push(InvariantViolation::MissingStakeEscrow {
+            owner: None,
+            stake_escrow,
+        });
+    }
+
+    let fee_a = unclaimed_fee_a + u128::from(top_staker_info.locked_fee_a);
+    let fee_b = unclaimed_fee_b + u128::from(top_staker_info.locked_fee_b);
+    let (stake_token_fee, quote_token_fee) = if accounts.is_stake_token_a {
+        (fee_a, fee_b)
+    } else {
+        (fee_b, fee_a)
+    };
+
+    let required_stake_token = total_staked_amount + ongoing_unstake_amount + stake_token_fee;
+    if u128::from(accounts.stake_token_balance) < required_stake_token {
+        violations.push(InvariantViolation::InsufficientStakeTokenBalance {
+            balance: accounts.stake_token_balance,
+            required: required_stake_token,
+        });
+    }
+    if u128::from(accounts.quote_token_balance) < quote_token_fee {
+        violations.push(InvariantViolation::InsufficientQuoteTokenBalance {
+            balance: accounts.quote_token_balance,
+            required: quote_token_fee,
+        });
+    }
+
+    Ok(InvariantReport { violations })
+}
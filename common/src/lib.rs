@@ -1 +1,16 @@
+mod account;
+pub mod allocation;
+pub mod config;
+pub mod constants;
+pub mod decoder;
+pub mod dynamic_amm;
+pub mod dynamic_vault;
+pub mod event;
+pub mod fee_release;
+pub mod invariant;
+pub mod math;
 pub mod pda;
+pub mod performance;
+pub mod preview;
+pub mod top_list;
+pub mod unstake;
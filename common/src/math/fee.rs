@@ -0,0 +1,83 @@
+use super::q64;
+use m3m3::{Rounding, StakeEscrow, StakeForFeeError, TopStakerInfo};
+
+pub use super::q64::SCALE_OFFSET;
+
+/// Fee per unit of effective stake (Q64.64) contributed by `released_fee`. Nothing is distributed
+/// while no stake is in the top list.
+pub fn get_fee_per_liquidity(
+    released_fee: u64,
+    effective_stake_amount: u64,
+) -> Result<u128, StakeForFeeError> {
+    if effective_stake_amount == 0 {
+        return Ok(0);
+    }
+
+    q64::from_ratio(released_fee, effective_stake_amount, Rounding::Down)
+}
+
+/// Fee earned by `stake_amount` between `checkpoint` and `cumulative_fee_per_liquidity`, rounded
+/// down.
+pub fn get_new_fee(
+    cumulative_fee_per_liquidity: u128,
+    checkpoint: u128,
+    stake_amount: u64,
+) -> Result<u64, StakeForFeeError> {
+    let delta = cumulative_fee_per_liquidity
+        .checked_sub(checkpoint)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+
+    q64::mul_u64(delta, stake_amount, Rounding::Down)
+}
+
+/// Unclaimed fee A/B of `stake_escrow` once `released_fee_a/b` are distributed to the top list.
+///
+/// Escrows outside the top list do not accrue fees, so only their checkpointed pending fees are
+/// returned.
+pub fn get_stake_escrow_pending_fees(
+    top_staker_info: &TopStakerInfo,
+    stake_escrow: &StakeEscrow,
+    released_fee_a: u64,
+    released_fee_b: u64,
+) -> Result<(u64, u64), StakeForFeeError> {
+    if stake_escrow.in_top_list == 0 {
+        return Ok((stake_escrow.fee_a_pending, stake_escrow.fee_b_pending));
+    }
+
+    let effective_stake_amount = top_staker_info.effective_stake_amount;
+
+    let cumulative_fee_a_per_liquidity = top_staker_info
+        .cumulative_fee_a_per_liquidity
+        .checked_add(get_fee_per_liquidity(
+            released_fee_a,
+            effective_stake_amount,
+        )?)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+    let cumulative_fee_b_per_liquidity = top_staker_info
+        .cumulative_fee_b_per_liquidity
+        .checked_add(get_fee_per_liquidity(
+            released_fee_b,
+            effective_stake_amount,
+        )?)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+
+    let new_fee_a = get_new_fee(
+        cumulative_fee_a_per_liquidity,
+        stake_escrow.fee_a_per_liquidity_checkpoint,
+        stake_escrow.stake_amount,
+    )?;
+    let new_fee_b = get_new_fee(
+        cumulative_fee_b_per_liquidity,
+        stake_escrow.fee_b_per_liquidity_checkpoint,
+        stake_escrow.stake_amount,
+    )?;
+
+    Ok((
+        new_fee_a
+            .checked_add(stake_escrow.fee_a_pending)
+            .ok_or(StakeForFeeError::MathOverflow)?,
+        new_fee_b
+            .checked_add(stake_escrow.fee_b_pending)
+            .ok_or(StakeForFeeError::MathOverflow)?,
+    ))
+}
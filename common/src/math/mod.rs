@@ -0,0 +1,13 @@
+pub mod fee;
+pub mod q64;
+pub mod u128x128_math;
+pub mod utils_math;
+
+#[allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+mod u256 {
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+
+pub use u256::U256;
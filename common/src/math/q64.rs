@@ -0,0 +1,41 @@
+//! Q64.64 fixed point numbers stored in u128, as used for the fee per liquidity accumulators.
+
+use super::{
+    u128x128_math::{mul_shr, shl_div},
+    utils_math::{safe_mul_shr_cast, safe_shl_div_cast},
+};
+use m3m3::{Rounding, StakeForFeeError};
+
+pub const SCALE_OFFSET: u32 = 64;
+pub const ONE: u128 = 1 << SCALE_OFFSET;
+
+pub fn from_u64(value: u64) -> u128 {
+    u128::from(value) << SCALE_OFFSET
+}
+
+/// `numerator / denominator` in Q64.64.
+pub fn from_ratio(
+    numerator: u64,
+    denominator: u64,
+    rounding: Rounding,
+) -> Result<u128, StakeForFeeError> {
+    safe_shl_div_cast(numerator.into(), denominator.into(), SCALE_OFFSET, rounding)
+}
+
+/// Integer part of `value`.
+pub fn to_u64(value: u128, rounding: Rounding) -> Result<u64, StakeForFeeError> {
+    safe_mul_shr_cast(value, 1, SCALE_OFFSET, rounding)
+}
+
+pub fn mul(x: u128, y: u128, rounding: Rounding) -> Result<u128, StakeForFeeError> {
+    mul_shr(x, y, SCALE_OFFSET, rounding)
+}
+
+pub fn div(x: u128, y: u128, rounding: Rounding) -> Result<u128, StakeForFeeError> {
+    shl_div(x, y, SCALE_OFFSET, rounding)
+}
+
+/// `amount * value`, as an integer amount.
+pub fn mul_u64(value: u128, amount: u64, rounding: Rounding) -> Result<u64, StakeForFeeError> {
+    safe_mul_shr_cast(value, amount.into(), SCALE_OFFSET, rounding)
+}
@@ -0,0 +1,63 @@
+use super::U256;
+use m3m3::{Rounding, StakeForFeeError};
+
+/// `x * y / denominator` with a U256 intermediate, so only the result has to fit in u128.
+pub fn mul_div(
+    x: u128,
+    y: u128,
+    denominator: u128,
+    rounding: Rounding,
+) -> Result<u128, StakeForFeeError> {
+    div_rounding(U256::from(x) * U256::from(y), denominator, rounding)
+}
+
+/// `(x * y) >> offset` with a U256 intermediate.
+pub fn mul_shr(
+    x: u128,
+    y: u128,
+    offset: u32,
+    rounding: Rounding,
+) -> Result<u128, StakeForFeeError> {
+    if offset >= 256 {
+        return Err(StakeForFeeError::MathOverflow);
+    }
+
+    let prod = U256::from(x) * U256::from(y);
+    let mut result = prod >> offset;
+    if rounding == Rounding::Up && (result << offset) != prod {
+        result += U256::one();
+    }
+
+    u128::try_from(result).map_err(|_| StakeForFeeError::MathOverflow)
+}
+
+/// `(x << offset) / y` with a U256 intermediate.
+pub fn shl_div(
+    x: u128,
+    y: u128,
+    offset: u32,
+    rounding: Rounding,
+) -> Result<u128, StakeForFeeError> {
+    if offset > 128 {
+        return Err(StakeForFeeError::MathOverflow);
+    }
+
+    div_rounding(U256::from(x) << offset, y, rounding)
+}
+
+fn div_rounding(
+    numerator: U256,
+    denominator: u128,
+    rounding: Rounding,
+) -> Result<u128, StakeForFeeError> {
+    if denominator == 0 {
+        return Err(StakeForFeeError::MathOverflow);
+    }
+
+    let (mut result, remainder) = numerator.div_mod(U256::from(denominator));
+    if rounding == Rounding::Up && !remainder.is_zero() {
+        result += U256::one();
+    }
+
+    u128::try_from(result).map_err(|_| StakeForFeeError::MathOverflow)
+}
@@ -0,0 +1,36 @@
+use super::u128x128_math::{mul_div, mul_shr, shl_div};
+use m3m3::{Rounding, StakeForFeeError};
+
+pub fn safe_cast<T, U>(value: U) -> Result<T, StakeForFeeError>
+where
+    T: TryFrom<U>,
+{
+    T::try_from(value).map_err(|_| StakeForFeeError::TypeCastFailed)
+}
+
+pub fn safe_mul_div_cast<T: TryFrom<u128>>(
+    x: u128,
+    y: u128,
+    denominator: u128,
+    rounding: Rounding,
+) -> Result<T, StakeForFeeError> {
+    safe_cast(mul_div(x, y, denominator, rounding)?)
+}
+
+pub fn safe_mul_shr_cast<T: TryFrom<u128>>(
+    x: u128,
+    y: u128,
+    offset: u32,
+    rounding: Rounding,
+) -> Result<T, StakeForFeeError> {
+    safe_cast(mul_shr(x, y, offset, rounding)?)
+}
+
+pub fn safe_shl_div_cast<T: TryFrom<u128>>(
+    x: u128,
+    y: u128,
+    offset: u32,
+    rounding: Rounding,
+) -> Result<T, StakeForFeeError> {
+    safe_cast(shl_div(x, y, offset, rounding)?)
+}
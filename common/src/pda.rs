@@ -1,4 +1,9 @@
+use crate::constants::*;
 use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
 pub fn derive_m3m3_vault_key(pool_key: Pubkey) -> Pubkey {
     Pubkey::find_program_address(&[b"vault", pool_key.as_ref()], &m3m3::ID).0
@@ -15,3 +20,75 @@ pub fn derive_full_balance_list_key(vault_key: Pubkey) -> Pubkey {
 pub fn derive_m3m3_event_authority_key() -> Pubkey {
     Pubkey::find_program_address(&[b"__event_authority"], &m3m3::ID).0
 }
+
+pub fn derive_stake_escrow_key(vault_key: Pubkey, owner: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"escrow", vault_key.as_ref(), owner.as_ref()], &m3m3::ID).0
+}
+
+pub fn derive_config_key(index: u64) -> Pubkey {
+    Pubkey::find_program_address(&[b"config", index.to_le_bytes().as_ref()], &m3m3::ID).0
+}
+
+pub fn derive_lock_escrow_key(pool_key: Pubkey, owner: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"lock_escrow", pool_key.as_ref(), owner.as_ref()],
+        &DYNAMIC_AMM_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn derive_associated_token_key(owner: Pubkey, mint: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// A (vault, owner) pair to derive addresses for. `stake_mint` is the vault's stake mint,
+/// required to derive the owner's stake token ATA.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VaultOwner {
+    pub vault: Pubkey,
+    pub stake_mint: Pubkey,
+    pub owner: Pubkey,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StakeEscrowAddresses {
+    pub vault: Pubkey,
+    pub owner: Pubkey,
+    pub stake_escrow: Pubkey,
+    pub user_stake_token: Pubkey,
+}
+
+impl From<VaultOwner> for StakeEscrowAddresses {
+    fn from(pair: VaultOwner) -> Self {
+        Self {
+            vault: pair.vault,
+            owner: pair.owner,
+            stake_escrow: derive_stake_escrow_key(pair.vault, pair.owner),
+            user_stake_token: derive_associated_token_key(pair.owner, pair.stake_mint),
+        }
+    }
+}
+
+/// Derive stake escrow and stake token ATA addresses for many (vault, owner) pairs.
+///
+/// The result is keyed by stake escrow address so it can be joined directly against
+/// `getProgramAccounts` results. Derivation runs on the rayon thread pool when the `rayon`
+/// feature is enabled.
+pub fn derive_stake_escrow_addresses(
+    pairs: &[VaultOwner],
+) -> HashMap<Pubkey, StakeEscrowAddresses> {
+    #[cfg(feature = "rayon")]
+    let iter = pairs.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let iter = pairs.iter();
+
+    iter.map(|pair| {
+        let addresses = StakeEscrowAddresses::from(*pair);
+        (addresses.stake_escrow, addresses)
+    })
+    .collect()
+}
@@ -0,0 +1,278 @@
+use crate::{
+    decoder::FullBalanceListState,
+    dynamic_amm::{get_locked_escrow_pending_fee, LockEscrow, PoolLpAmounts},
+    dynamic_vault::Vault,
+};
+use m3m3::{FeeVault, StakeEscrow, StakeForFeeError, TopStakerInfo};
+use rust_decimal::{Decimal, MathematicalOps};
+use solana_sdk::pubkey::Pubkey;
+use std::cmp::Reverse;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+const DAYS_PER_YEAR: u64 = 365;
+
+/// Accounts backing a fee vault, read at `current_time`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeVaultSnapshot {
+    pub current_time: i64,
+    pub fee_vault: FeeVault,
+    pub lock_escrow: LockEscrow,
+    pub a_vault: Vault,
+    pub b_vault: Vault,
+    pub lp_amounts: PoolLpAmounts,
+}
+
+impl FeeVaultSnapshot {
+    /// `Metrics::total_fee_a/b_amount` plus the fee still claimable from the lock escrow.
+    pub fn total_fees(&self) -> Result<(u128, u128), StakeForFeeError> {
+        let (claimable_fee_a, claimable_fee_b) = get_locked_escrow_pending_fee(
+            self.current_time,
+            &self.fee_vault,
+            &self.lock_escrow,
+            &self.a_vault,
+            &self.b_vault,
+            &self.lp_amounts,
+        )?;
+        let metrics = &self.fee_vault.metrics;
+
+        let total_fee_a = metrics
+            .total_fee_a_amount
+            .checked_add(claimable_fee_a.into())
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        let total_fee_b = metrics
+            .total_fee_b_amount
+            .checked_add(claimable_fee_b.into())
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        Ok((total_fee_a, total_fee_b))
+    }
+}
+
+/// Mint decimals and USD price of one UI unit of a token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenPrice {
+    pub decimals: u8,
+    pub usd_rate: Decimal,
+}
+
+impl TokenPrice {
+    pub fn to_ui_amount(&self, amount: i128) -> Result<Decimal, StakeForFeeError> {
+        Decimal::try_from_i128_with_scale(amount, self.decimals.into())
+            .map_err(|_| StakeForFeeError::MathOverflow)
+    }
+
+    pub fn to_usd(&self, amount: i128) -> Result<Decimal, StakeForFeeError> {
+        self.to_ui_amount(amount)?
+            .checked_mul(self.usd_rate)
+            .ok_or(StakeForFeeError::MathOverflow)
+    }
+}
+
+/// `apr` and `apy` are percentages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeFarmPerformance {
+    pub apr: Decimal,
+    pub apy: Decimal,
+    pub usd_per_day: Decimal,
+}
+
+/// Annualize the fees accrued between two snapshots of the same vault over the effective stake of
+/// the later one. Port of the TS `calculateFeeFarmPerformance`, with daily compounding for APY.
+pub fn calculate_fee_farm_performance(
+    snapshot_t0: &FeeVaultSnapshot,
+    snapshot_t1: &FeeVaultSnapshot,
+    token_a: TokenPrice,
+    token_b: TokenPrice,
+    stake_token: TokenPrice,
+) -> Result<FeeFarmPerformance, StakeForFeeError> {
+    let seconds_elapsed = snapshot_t1
+        .current_time
+        .checked_sub(snapshot_t0.current_time)
+        .filter(|seconds| *seconds > 0)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+
+    let (total_fee_a_t0, total_fee_b_t0) = snapshot_t0.total_fees()?;
+    let (total_fee_a_t1, total_fee_b_t1) = snapshot_t1.total_fees()?;
+
+    let fee_a_amount = to_i128(total_fee_a_t1)? - to_i128(total_fee_a_t0)?;
+    let fee_b_amount = to_i128(total_fee_b_t1)? - to_i128(total_fee_b_t0)?;
+
+    let fee_usd = token_a
+        .to_usd(fee_a_amount)?
+        .checked_add(token_b.to_usd(fee_b_amount)?)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+
+    let usd_per_day = fee_usd
+        .checked_mul(SECONDS_PER_DAY.into())
+        .and_then(|usd| usd.checked_div(seconds_elapsed.into()))
+        .ok_or(StakeForFeeError::MathOverflow)?;
+    let annualized_fee_usd = usd_per_day
+        .checked_mul(DAYS_PER_YEAR.into())
+        .ok_or(StakeForFeeError::MathOverflow)?;
+
+    let effective_stake_usd = stake_token.to_usd(
+        snapshot_t1
+            .fee_vault
+            .top_staker_info
+            .effective_stake_amount
+            .into(),
+    )?;
+
+    let nominal_rate = annualized_fee_usd
+        .checked_div(effective_stake_usd)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+    let effective_rate = (Decimal::ONE + nominal_rate / Decimal::from(DAYS_PER_YEAR))
+        .checked_powu(DAYS_PER_YEAR)
+        .ok_or(StakeForFeeError::MathOverflow)?
+        - Decimal::ONE;
+
+    Ok(FeeFarmPerformance {
+        apr: to_percentage(nominal_rate)?,
+        apy: to_percentage(effective_rate)?,
+        usd_per_day,
+    })
+}
+
+/// Fee A/B amounts earned per day.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DailyFee {
+    pub fee_a: u64,
+    pub fee_b: u64,
+}
+
+/// Fee A/B the vault collected per day between two snapshots. Fees are released to the top list
+/// linearly over `seconds_to_full_unlock`, so this is also the emission rate once that lag is
+/// over.
+pub fn get_fee_emission_per_day(
+    snapshot_t0: &FeeVaultSnapshot,
+    snapshot_t1: &FeeVaultSnapshot,
+) -> Result<DailyFee, StakeForFeeError> {
+    let seconds_elapsed = snapshot_t1
+        .current_time
+        .checked_sub(snapshot_t0.current_time)
+        .filter(|seconds| *seconds > 0)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+
+    let (total_fee_a_t0, total_fee_b_t0) = snapshot_t0.total_fees()?;
+    let (total_fee_a_t1, total_fee_b_t1) = snapshot_t1.total_fees()?;
+
+    let per_day = |total_fee_t0: u128, total_fee_t1: u128| {
+        let fee = total_fee_t1.saturating_sub(total_fee_t0);
+        let fee_per_day = fee
+            .checked_mul(SECONDS_PER_DAY as u128)
+            .ok_or(StakeForFeeError::MathOverflow)?
+            / seconds_elapsed as u128;
+        u64::try_from(fee_per_day).map_err(|_| StakeForFeeError::TypeCastFailed)
+    };
+
+    Ok(DailyFee {
+        fee_a: per_day(total_fee_a_t0, total_fee_a_t1)?,
+        fee_b: per_day(total_fee_b_t0, total_fee_b_t1)?,
+    })
+}
+
+/// Share of `fee_emission_per_day` earned by the escrow. Only top stakers earn fees. Same as the
+/// TS `getStakeEscrowEarningPerDay`, per token.
+pub fn get_stake_escrow_earning_per_day(
+    stake_escrow: &StakeEscrow,
+    top_staker_info: &TopStakerInfo,
+    fee_emission_per_day: DailyFee,
+) -> Result<DailyFee, StakeForFeeError> {
+    if stake_escrow.in_top_list == 0 {
+        return Ok(DailyFee::default());
+    }
+
+    get_earning_per_day(
+        stake_escrow.stake_amount,
+        top_staker_info.effective_stake_amount,
+        fee_emission_per_day,
+    )
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EarningAfterUnstake {
+    pub earning_per_day: DailyFee,
+    /// Whether the escrow keeps its top list slot once the unstake is requested.
+    pub in_top_list: bool,
+}
+
+/// Earning of the escrow once `unstake_amount` is requested for unstake.
+///
+/// Unlike the TS `getStakeEscrowEarningPerDayAfterUnstake`, this accounts for the escrow being
+/// replaced by the largest staker outside the top list when that staker now ranks above it. On
+/// equal stake the earlier full balance index ranks higher.
+pub fn get_stake_escrow_earning_per_day_after_unstake(
+    stake_escrow: &StakeEscrow,
+    top_staker_info: &TopStakerInfo,
+    full_balance_list: &FullBalanceListState,
+    unstake_amount: u64,
+    fee_emission_per_day: DailyFee,
+) -> Result<EarningAfterUnstake, StakeForFeeError> {
+    let new_stake_amount = stake_escrow
+        .stake_amount
+        .checked_sub(unstake_amount)
+        .ok_or(StakeForFeeError::InsufficientStakeAmount)?;
+
+    if stake_escrow.in_top_list == 0 {
+        return Ok(EarningAfterUnstake::default());
+    }
+
+    let replacement = full_balance_list
+        .stakers
+        .iter()
+        .enumerate()
+        .filter(|(_, staker)| {
+            staker.owner != Pubkey::default() && staker.is_in_top_list == 0 && staker.balance > 0
+        })
+        .map(|(idx, staker)| (staker.balance, Reverse(idx as u64)))
+        .max()
+        .filter(|rank| *rank > (new_stake_amount, Reverse(stake_escrow.full_balance_index)));
+
+    if replacement.is_some() {
+        return Ok(EarningAfterUnstake::default());
+    }
+
+    let new_effective_stake_amount = top_staker_info
+        .effective_stake_amount
+        .checked_sub(unstake_amount)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+
+    Ok(EarningAfterUnstake {
+        earning_per_day: get_earning_per_day(
+            new_stake_amount,
+            new_effective_stake_amount,
+            fee_emission_per_day,
+        )?,
+        in_top_list: true,
+    })
+}
+
+fn get_earning_per_day(
+    stake_amount: u64,
+    effective_stake_amount: u64,
+    fee_emission_per_day: DailyFee,
+) -> Result<DailyFee, StakeForFeeError> {
+    if effective_stake_amount == 0 {
+        return Ok(DailyFee::default());
+    }
+
+    let share = |fee: u64| {
+        let earning =
+            u128::from(stake_amount) * u128::from(fee) / u128::from(effective_stake_amount);
+        u64::try_from(earning).map_err(|_| StakeForFeeError::TypeCastFailed)
+    };
+
+    Ok(DailyFee {
+        fee_a: share(fee_emission_per_day.fee_a)?,
+        fee_b: share(fee_emission_per_day.fee_b)?,
+    })
+}
+
+fn to_i128(amount: u128) -> Result<i128, StakeForFeeError> {
+    i128::try_from(amount).map_err(|_| StakeForFeeError::TypeCastFailed)
+}
+
+fn to_percentage(rate: Decimal) -> Result<Decimal, StakeForFeeError> {
+    rate.checked_mul(Decimal::ONE_HUNDRED)
+        .ok_or(StakeForFeeError::MathOverflow)
+}
@@ -0,0 +1,287 @@
+use crate::{
+    constants::{FULL_BALANCE_LIST_HARD_LIMIT, NO_FULL_BALANCE_INDEX},
+    decoder::{FullBalanceListState, TopStakerListState},
+    pda::derive_stake_escrow_key,
+    top_list::{
+        find_largest_stakers_not_in_top_list, find_replaceable_top_stakers,
+        find_smallest_full_balance_index,
+    },
+};
+use m3m3::{FeeVault, StakeEscrow, StakeForFeeError};
+use rust_decimal::Decimal;
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey};
+use std::cmp::Reverse;
+
+/// Number of replaceable top stakers passed to `stake`, as in the TS `stake`.
+const STAKE_REPLACEABLE_TOP_STAKER_COUNT: usize = 2;
+/// Number of promotion candidates passed to `request_unstake`, as in the TS `unstake`.
+const UNSTAKE_PROMOTION_CANDIDATE_COUNT: usize = 3;
+
+/// Decoded vault and list state to preview an owner's action against.
+#[derive(Clone, Copy, Debug)]
+pub struct PreviewState<'a> {
+    pub fee_vault: &'a FeeVault,
+    pub top_staker_list: &'a TopStakerListState,
+    pub full_balance_list: &'a FullBalanceListState,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StakePreview {
+    pub stake_escrow: Pubkey,
+    /// The stake escrow does not exist yet and is initialized before staking.
+    pub initialize_stake_escrow: bool,
+    pub stake_amount: u64,
+    pub was_in_top_list: bool,
+    pub in_top_list: bool,
+    /// Owner of the top staker pushed out of the full top list.
+    pub displaced_owner: Option<Pubkey>,
+    /// Owner whose full balance list index is taken over, when the escrow has none. It is the
+    /// owner of `smallest_stake_escrow`.
+    pub reclaimed_owner: Option<Pubkey>,
+    pub effective_stake_amount: u64,
+    /// Share of the fee released to the top list, zero outside of it.
+    pub fee_share: Decimal,
+    /// `smallest_stake_escrow` account of the instruction.
+    pub smallest_stake_escrow: Option<Pubkey>,
+    pub remaining_accounts: Vec<AccountMeta>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnstakePreview {
+    pub stake_escrow: Pubkey,
+    pub stake_amount: u64,
+    pub was_in_top_list: bool,
+    pub in_top_list: bool,
+    /// Owner of the staker taking over the owner's top list slot.
+    pub promoted_owner: Option<Pubkey>,
+    pub effective_stake_amount: u64,
+    /// Share of the fee released to the top list, zero outside of it.
+    pub fee_share: Decimal,
+    pub remaining_accounts: Vec<AccountMeta>,
+}
+
+impl PreviewState<'_> {
+    fn vault(&self) -> Pubkey {
+        self.top_staker_list.metadata.vault
+    }
+
+    /// Outcome of `owner` staking `amount` more, with `stake_escrow` the owner's escrow if it
+    /// exists. Fees are not checkpointed, so a stake fee restaked by the instruction is not
+    /// counted.
+    pub fn preview_stake(
+        &self,
+        owner: Pubkey,
+        stake_escrow: Option<&StakeEscrow>,
+        amount: u64,
+    ) -> Result<StakePreview, StakeForFeeError> {
+        if amount == 0 {
+            return Err(StakeForFeeError::InsufficientStakeAmount);
+        }
+        if stake_escrow.is_some_and(|stake_escrow| stake_escrow.owner != owner) {
+            return Err(StakeForFeeError::InvalidStakeEscrow);
+        }
+
+        let stake_amount = stake_escrow
+            .map_or(0, |stake_escrow| stake_escrow.stake_amount)
+            .checked_add(amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        let was_in_top_list =
+            stake_escrow.is_some_and(|stake_escrow| stake_escrow.in_top_list != 0);
+        let smallest_index = find_smallest_full_balance_index(owner, self.full_balance_list);
+        let smallest_staker = smallest_index.map(|idx| &self.full_balance_list.stakers[idx]);
+
+        let full_balance_index = match stake_escrow {
+            Some(stake_escrow) if stake_escrow.full_balance_index != NO_FULL_BALANCE_INDEX => {
+                Some(stake_escrow.full_balance_index)
+            }
+            None if self.full_balance_list.metadata.length < FULL_BALANCE_LIST_HARD_LIMIT => {
+                Some(self.full_balance_list.metadata.length)
+            }
+            _ => None,
+        };
+
+        // Without an index, the escrow takes over the one of `smallest_stake_escrow`, if it now
+        // holds more. That staker is only in the top list when every staker is, and then leaves
+        // it along with its slot.
+        let (full_balance_index, reclaimed_staker) = match full_balance_index {
+            Some(full_balance_index) => (full_balance_index, None),
+            None => match smallest_index.zip(smallest_staker) {
+                Some((idx, staker)) if staker.balance < stake_amount => (idx as u64, Some(staker)),
+                _ => return Err(StakeForFeeError::FullBalanceListFull),
+            },
+        };
+        let reclaimed_top_stake_amount = reclaimed_staker
+            .filter(|staker| staker.is_in_top_list != 0)
+            .map(|staker| staker.balance);
+
+        let top_staker_info = &self.fee_vault.top_staker_info;
+        let mut displaced_owner = None;
+        let (in_top_list, effective_stake_amount) = if was_in_top_list {
+            (
+                true,
+                top_staker_info
+                    .effective_stake_amount
+                    .checked_add(amount)
+                    .ok_or(StakeForFeeError::MathOverflow)?,
+            )
+        } else if top_staker_info.current_length < top_staker_info.top_list_length
+            || reclaimed_top_stake_amount.is_some()
+        {
+            (
+                true,
+                top_staker_info
+                    .effective_stake_amount
+                    .checked_sub(reclaimed_top_stake_amount.unwrap_or_default())
+                    .and_then(|amount| amount.checked_add(stake_amount))
+                    .ok_or(StakeForFeeError::MathOverflow)?,
+            )
+        } else {
+            let smallest = self
+                .top_staker_list
+                .stakers
+                .iter()
+                .filter(|staker| staker.full_balance_index >= 0)
+                .min_by_key(|staker| {
+                    (
+                        staker.stake_amount,
+                        Reverse(staker.full_balance_index as u64),
+                    )
+                })
+                .filter(|staker| {
+                    (stake_amount, Reverse(full_balance_index))
+                        > (
+                            staker.stake_amount,
+                            Reverse(staker.full_balance_index as u64),
+                        )
+                });
+
+            match smallest {
+                Some(staker) => {
+                    displaced_owner = Some(staker.owner);
+                    (
+                        true,
+                        top_staker_info
+                            .effective_stake_amount
+                            .checked_sub(staker.stake_amount)
+                            .and_then(|amount| amount.checked_add(stake_amount))
+                            .ok_or(StakeForFeeError::MathOverflow)?,
+                    )
+                }
+                None => (false, top_staker_info.effective_stake_amount),
+            }
+        };
+
+        Ok(StakePreview {
+            stake_escrow: derive_stake_escrow_key(self.vault(), owner),
+            initialize_stake_escrow: stake_escrow.is_none(),
+            stake_amount,
+            was_in_top_list,
+            in_top_list,
+            displaced_owner,
+            reclaimed_owner: reclaimed_staker.map(|staker| staker.owner),
+            effective_stake_amount,
+            fee_share: get_fee_share(in_top_list, stake_amount, effective_stake_amount)?,
+            smallest_stake_escrow: smallest_staker
+                .map(|staker| derive_stake_escrow_key(self.vault(), staker.owner)),
+            remaining_accounts: writable_accounts(find_replaceable_top_stakers(
+                STAKE_REPLACEABLE_TOP_STAKER_COUNT,
+                self.top_staker_list,
+            )),
+        })
+    }
+
+    /// Outcome of `owner` requesting to unstake `amount` from `stake_escrow`.
+    pub fn preview_request_unstake(
+        &self,
+        owner: Pubkey,
+        stake_escrow: Option<&StakeEscrow>,
+        amount: u64,
+    ) -> Result<UnstakePreview, StakeForFeeError> {
+        let stake_escrow = stake_escrow
+            .filter(|stake_escrow| stake_escrow.owner == owner)
+            .ok_or(StakeForFeeError::InvalidStakeEscrow)?;
+        if amount == 0 {
+            return Err(StakeForFeeError::InsufficientStakeAmount);
+        }
+        let stake_amount = stake_escrow
+            .stake_amount
+            .checked_sub(amount)
+            .ok_or(StakeForFeeError::InsufficientStakeAmount)?;
+
+        let was_in_top_list = stake_escrow.in_top_list != 0;
+        let effective_stake_amount = self.fee_vault.top_staker_info.effective_stake_amount;
+        let mut promoted_owner = None;
+
+        let (in_top_list, effective_stake_amount, remaining_accounts) = if was_in_top_list {
+            let effective_stake_amount = effective_stake_amount
+                .checked_sub(amount)
+                .ok_or(StakeForFeeError::MathOverflow)?;
+
+            // The largest staker outside the top list swaps in if it now ranks higher.
+            let promoted = self
+                .full_balance_list
+                .stakers
+                .iter()
+                .enumerate()
+                .filter(|(_, staker)| staker.is_in_top_list == 0 && staker.balance > 0)
+                .max_by_key(|(idx, staker)| (staker.balance, Reverse(*idx as u64)))
+                .filter(|(idx, staker)| {
+                    (staker.balance, Reverse(*idx as u64))
+                        > (stake_amount, Reverse(stake_escrow.full_balance_index))
+                });
+
+            let remaining_accounts = writable_accounts(find_largest_stakers_not_in_top_list(
+                UNSTAKE_PROMOTION_CANDIDATE_COUNT,
+                self.full_balance_list,
+            ));
+
+            match promoted {
+                Some((_, staker)) => {
+                    promoted_owner = Some(staker.owner);
+                    (
+                        false,
+                        effective_stake_amount
+                            .checked_sub(stake_amount)
+                            .and_then(|amount| amount.checked_add(staker.balance))
+                            .ok_or(StakeForFeeError::MathOverflow)?,
+                        remaining_accounts,
+                    )
+                }
+                None => (true, effective_stake_amount, remaining_accounts),
+            }
+        } else {
+            (false, effective_stake_amount, vec![])
+        };
+
+        Ok(UnstakePreview {
+            stake_escrow: derive_stake_escrow_key(self.vault(), owner),
+            stake_amount,
+            was_in_top_list,
+            in_top_list,
+            promoted_owner,
+            effective_stake_amount,
+            fee_share: get_fee_share(in_top_list, stake_amount, effective_stake_amount)?,
+            remaining_accounts,
+        })
+    }
+}
+
+fn get_fee_share(
+    in_top_list: bool,
+    stake_amount: u64,
+    effective_stake_amount: u64,
+) -> Result<Decimal, StakeForFeeError> {
+    if !in_top_list || effective_stake_amount == 0 {
+        return Ok(Decimal::ZERO);
+    }
+
+    Decimal::from(stake_amount)
+        .checked_div(Decimal::from(effective_stake_amount))
+        .ok_or(StakeForFeeError::MathOverflow)
+}
+
+fn writable_accounts(keys: Vec<Pubkey>) -> Vec<AccountMeta> {
+    keys.into_iter()
+        .map(|key| AccountMeta::new(key, false))
+        .collect()
+}
@@ -0,0 +1,151 @@
+use crate::{
+    decoder::{FullBalanceListState, TopStakerListState},
+    pda::derive_stake_escrow_key,
+};
+use m3m3::{StakeEscrow, TopStakerInfo};
+use solana_sdk::pubkey::Pubkey;
+
+/// Where a stake escrow stands relative to the top list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TopListEntry {
+    /// Smallest stake amount that enters the top list. Any stake enters while the list has a
+    /// free slot.
+    pub entry_stake_amount: u64,
+    /// Stake the owner must add on top of `stake_amount` to enter. Zero when already in the list.
+    pub stake_needed: u64,
+    /// Same as `stake_needed` if every ongoing unstake of the escrow were cancelled first.
+    pub stake_needed_after_cancel_unstake: u64,
+    /// `current_length < top_list_length`.
+    pub has_free_slot: bool,
+}
+
+/// Smallest top stake + 1, or 1 if the list is empty. Same as the TS
+/// `getTopStakerListStateEntryStakeAmount`.
+pub fn get_top_staker_list_entry_stake_amount(top_staker_list: &TopStakerListState) -> u64 {
+    top_staker_list
+        .stakers
+        .iter()
+        .filter(|staker| staker.full_balance_index >= 0)
+        .map(|staker| staker.stake_amount)
+        .min()
+        .map_or(1, |smallest_stake_amount| {
+            smallest_stake_amount.saturating_add(1)
+        })
+}
+
+pub fn get_top_list_entry(
+    top_staker_info: &TopStakerInfo,
+    top_staker_list: &TopStakerListState,
+    stake_escrow: &StakeEscrow,
+) -> TopListEntry {
+    let has_free_slot = top_staker_info.current_length < top_staker_info.top_list_length;
+
+    let entry_stake_amount = if has_free_slot {
+        1
+    } else {
+        get_top_staker_list_entry_stake_amount(top_staker_list)
+    };
+
+    let (stake_needed, stake_needed_after_cancel_unstake) = if stake_escrow.in_top_list != 0 {
+        (0, 0)
+    } else {
+        let stake_needed = entry_stake_amount.saturating_sub(stake_escrow.stake_amount);
+        (
+            stake_needed,
+            stake_needed.saturating_sub(stake_escrow.ongoing_total_partial_unstake_amount),
+        )
+    };
+
+    TopListEntry {
+        entry_stake_amount,
+        stake_needed,
+        stake_needed_after_cancel_unstake,
+        has_free_slot,
+    }
+}
+
+/// Stake escrows of the `lookup_number` smallest top stakers, smallest first. On equal stake the
+/// later `full_balance_index` counts as smaller, as in the program. Same as the TS
+/// `findReplaceableTopStaker`.
+pub fn find_replaceable_top_stakers(
+    lookup_number: usize,
+    top_staker_list: &TopStakerListState,
+) -> Vec<Pubkey> {
+    let mut top_stakers: Vec<_> = top_staker_list
+        .stakers
+        .iter()
+        .filter(|staker| staker.full_balance_index >= 0)
+        .collect();
+
+    top_stakers.sort_by(|a, b| {
+        a.stake_amount
+            .cmp(&b.stake_amount)
+            .then(b.full_balance_index.cmp(&a.full_balance_index))
+    });
+
+    top_stakers
+        .into_iter()
+        .take(lookup_number)
+        .map(|staker| derive_stake_escrow_key(top_staker_list.metadata.vault, staker.owner))
+        .collect()
+}
+
+/// Stake escrows of the `lookup_number` largest stakers outside the top list, largest first. On
+/// equal balance the earlier full balance index counts as larger. Same as the TS
+/// `findLargestStakerNotInTopListFromFullBalanceList`.
+pub fn find_largest_stakers_not_in_top_list(
+    lookup_number: usize,
+    full_balance_list: &FullBalanceListState,
+) -> Vec<Pubkey> {
+    let mut stakers: Vec<_> = full_balance_list
+        .stakers
+        .iter()
+        .enumerate()
+        .filter(|(_, staker)| staker.owner != Pubkey::default() && staker.is_in_top_list == 0)
+        .collect();
+
+    stakers.sort_by(|(a_idx, a), (b_idx, b)| b.balance.cmp(&a.balance).then(a_idx.cmp(b_idx)));
+
+    stakers
+        .into_iter()
+        .take(lookup_number)
+        .map(|(_, staker)| derive_stake_escrow_key(full_balance_list.metadata.vault, staker.owner))
+        .collect()
+}
+
+/// Full balance list index of the stake escrow passed as `smallest_stake_escrow`: scanning from
+/// the end, the first empty balance, else the smallest one, skipping `skip_owner`. Top stakers
+/// are not skipped and on equal balance the later index is kept. Same as the TS
+/// `findSmallestStakeEscrowInFullBalanceList`.
+pub fn find_smallest_full_balance_index(
+    skip_owner: Pubkey,
+    full_balance_list: &FullBalanceListState,
+) -> Option<usize> {
+    let mut smallest_balance = u64::MAX;
+    let mut smallest = None;
+    for (idx, staker) in full_balance_list.stakers.iter().enumerate().rev() {
+        if staker.owner == skip_owner {
+            continue;
+        }
+        if staker.balance == 0 {
+            return Some(idx);
+        }
+        if staker.balance < smallest_balance {
+            smallest_balance = staker.balance;
+            smallest = Some(idx);
+        }
+    }
+    smallest
+}
+
+pub fn find_smallest_stake_escrow_in_full_balance_list(
+    skip_owner: Pubkey,
+    full_balance_list: &FullBalanceListState,
+) -> Option<Pubkey> {
+    find_smallest_full_balance_index(skip_owner, full_balance_list).map(|idx| {
+        derive_stake_escrow_key(
+            full_balance_list.metadata.vault,
+            full_balance_list.stakers[idx].owner,
+        )
+    })
+}
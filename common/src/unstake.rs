@@ -0,0 +1,131 @@
+use crate::{event::UpdateUnstakeLockDurationFields, pda::derive_stake_escrow_key};
+use m3m3::{FeeVault, StakeForFeeError, Unstake, UpdateUnstakeLockDuration};
+use solana_sdk::pubkey::Pubkey;
+
+/// When an unstake requested at `request_time` can be withdrawn.
+pub fn get_release_at(
+    unstake_lock_duration: u64,
+    request_time: i64,
+) -> Result<i64, StakeForFeeError> {
+    i64::try_from(unstake_lock_duration)
+        .ok()
+        .and_then(|duration| request_time.checked_add(duration))
+        .ok_or(StakeForFeeError::MathOverflow)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnstakeStatus {
+    pub unstake: Pubkey,
+    pub unstake_amount: u64,
+    pub release_at: i64,
+    /// Zero once withdrawable.
+    pub seconds_remaining: u64,
+}
+
+impl UnstakeStatus {
+    pub fn is_withdrawable(&self) -> bool {
+        self.seconds_remaining == 0
+    }
+}
+
+/// Unstakes of one stake escrow at `current_time`. Each list is sorted by `release_at`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnstakeTimeline {
+    /// Unstakes `withdraw` accepts now.
+    pub withdrawable: Vec<UnstakeStatus>,
+    /// Unstakes `withdraw` rejects with `CannotWithdrawUnstakeAmount`.
+    pub locked: Vec<UnstakeStatus>,
+    pub total_withdrawable_amount: u64,
+    /// Total amount of every unstake not withdrawn yet, withdrawable or not.
+    pub total_pending_amount: u64,
+    /// Earliest `release_at` among the locked unstakes.
+    pub next_release_at: Option<i64>,
+}
+
+/// Timeline of the `unstakes` belonging to `owner`'s stake escrow in `vault`. The others are
+/// ignored, so the unstake accounts of the whole vault can be passed in.
+pub fn get_unstake_timeline(
+    vault: Pubkey,
+    owner: Pubkey,
+    unstakes: &[(Pubkey, Unstake)],
+    current_time: i64,
+) -> Result<UnstakeTimeline, StakeForFeeError> {
+    let stake_escrow = derive_stake_escrow_key(vault, owner);
+
+    let mut statuses: Vec<UnstakeStatus> = unstakes
+        .iter()
+        .filter(|(_, unstake)| unstake.stake_escrow == stake_escrow)
+        .map(|(key, unstake)| UnstakeStatus {
+            unstake: *key,
+            unstake_amount: unstake.unstake_amount,
+            release_at: unstake.release_at,
+            seconds_remaining: unstake.release_at.saturating_sub(current_time).max(0) as u64,
+        })
+        .collect();
+    statuses.sort_by_key(|status| (status.release_at, status.unstake));
+
+    let (withdrawable, locked): (Vec<_>, Vec<_>) = statuses
+        .into_iter()
+        .partition(UnstakeStatus::is_withdrawable);
+
+    let total_withdrawable_amount = sum_unstake_amount(&withdrawable)?;
+    let total_pending_amount = sum_unstake_amount(&locked)?
+        .checked_add(total_withdrawable_amount)
+        .ok_or(StakeForFeeError::MathOverflow)?;
+    let next_release_at = locked.first().map(|status| status.release_at);
+
+    Ok(UnstakeTimeline {
+        withdrawable,
+        locked,
+        total_withdrawable_amount,
+        total_pending_amount,
+        next_release_at,
+    })
+}
+
+/// Release of an unstake requested at `request_time`, before and after an unstake lock duration
+/// change. Unstakes requested earlier keep their `release_at`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LockDurationChange {
+    pub release_at: i64,
+    pub release_at_after_change: i64,
+}
+
+impl LockDurationChange {
+    /// Positive when new requests are locked longer.
+    pub fn delay(&self) -> i64 {
+        self.release_at_after_change - self.release_at
+    }
+}
+
+pub fn get_lock_duration_change(
+    fee_vault: &FeeVault,
+    new_unstake_lock_duration: u64,
+    request_time: i64,
+) -> Result<LockDurationChange, StakeForFeeError> {
+    Ok(LockDurationChange {
+        release_at: get_release_at(fee_vault.configuration.unstake_lock_duration, request_time)?,
+        release_at_after_change: get_release_at(new_unstake_lock_duration, request_time)?,
+    })
+}
+
+/// Same as [`get_lock_duration_change`] for an emitted `UpdateUnstakeLockDuration` event.
+pub fn get_lock_duration_change_from_event(
+    event: &UpdateUnstakeLockDuration,
+    request_time: i64,
+) -> Result<LockDurationChange, StakeForFeeError> {
+    let event = UpdateUnstakeLockDurationFields::from(event);
+
+    Ok(LockDurationChange {
+        release_at: get_release_at(event.old_value, request_time)?,
+        release_at_after_change: get_release_at(event.new_value, request_time)?,
+    })
+}
+
+fn sum_unstake_amount(statuses: &[UnstakeStatus]) -> Result<u64, StakeForFeeError> {
+    statuses.iter().try_fold(0u64, |total, status| {
+        total
+            .checked_add(status.unstake_amount)
+            .ok_or(StakeForFeeError::MathOverflow)
+    })
+}
@@ -0,0 +1,153 @@
+mod utils;
+
+use common::allocation::{optimize_stake_allocation, AllocationPlan, VaultCandidate};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use utils::VaultState;
+
+/// A vault of `stake_mint` whose stakers are all in the top list.
+fn top_stakers_vault(stake_mint: Pubkey, top_list_length: u64, stakes: &[u64]) -> VaultState {
+    let stakes: Vec<_> = stakes
+        .iter()
+        .map(|stake_amount| (*stake_amount, true))
+        .collect();
+    let mut vault = VaultState::new(top_list_length, &stakes);
+    vault.fee_vault.stake_mint = stake_mint;
+    vault
+}
+
+fn optimize(vaults: &[(&VaultState, u64)], budgets: &[(Pubkey, u64)]) -> AllocationPlan {
+    let candidates: Vec<_> = vaults
+        .iter()
+        .map(|(vault, quote_fee_per_day)| VaultCandidate {
+            vault: vault.vault,
+            stake_mint: vault.fee_vault.stake_mint,
+            top_staker_info: &vault.fee_vault.top_staker_info,
+            top_staker_list: &vault.top_staker_list,
+            quote_fee_per_day: *quote_fee_per_day,
+        })
+        .collect();
+    optimize_stake_allocation(&candidates, &budgets.iter().copied().collect()).unwrap()
+}
+
+#[test]
+fn budget_is_split_at_equal_marginal_income() {
+    let stake_mint = Pubkey::new_unique();
+    // A free slot diluted by 1000, and a full list where entering pushes out the staker of 100.
+    let open = top_stakers_vault(stake_mint, 3, &[600, 400]);
+    let full = top_stakers_vault(stake_mint, 2, &[500, 100]);
+
+    let plan = optimize(&[(&open, 1_000), (&full, 1_000)], &[(stake_mint, 1_000)]);
+
+    // Brute force every split of the budget.
+    let income = |stake: u64, entry: u64, base: u64| {
+        if stake < entry {
+            0
+        } else {
+            stake * 1_000 / (base + stake)
+        }
+    };
+    let best = (0..=1_000u64)
+        .map(|stake| income(stake, 1, 1_000) + income(1_000 - stake, 101, 500))
+        .max()
+        .unwrap();
+    assert!(plan.projected_quote_fee_per_day() + 1 >= u128::from(best));
+    assert!(plan.unallocated.is_empty());
+
+    assert_eq!(plan.allocations.len(), 2);
+    let [first, second] = [plan.allocations[0], plan.allocations[1]];
+    assert_eq!(first.stake_amount + second.stake_amount, 1_000);
+    assert!(first.projected_quote_fee_per_day >= second.projected_quote_fee_per_day);
+
+    assert_eq!(first.vault, full.vault);
+    assert_eq!(
+        first.displaced_owner,
+        Some(full.top_staker_list.stakers[1].owner)
+    );
+    assert_eq!(first.effective_stake_amount, 500 + first.stake_amount);
+    assert_eq!(second.vault, open.vault);
+    assert_eq!(second.displaced_owner, None);
+    assert_eq!(second.effective_stake_amount, 1_000 + second.stake_amount);
+}
+
+#[test]
+fn vaults_out_of_reach_are_skipped() {
+    let stake_mint = Pubkey::new_unique();
+    let other_mint = Pubkey::new_unique();
+    // Entering needs 1001.
+    let expensive = top_stakers_vault(stake_mint, 1, &[1_000]);
+    // The only staker takes the whole fee, whatever the stake.
+    let empty = top_stakers_vault(stake_mint, 2, &[]);
+    let idle = top_stakers_vault(stake_mint, 2, &[100]);
+    let other = top_stakers_vault(other_mint, 2, &[100, 100]);
+
+    let plan = optimize(
+        &[
+            (&expensive, 1_000_000),
+            (&empty, 500),
+            (&idle, 0),
+            (&other, 900),
+        ],
+        &[(stake_mint, 500), (other_mint, 1_000)],
+    );
+
+    assert_eq!(plan.allocations.len(), 2);
+    assert_eq!(plan.allocations[0].vault, other.vault);
+    // 101 to 200 staked, and the 100 pushed out.
+    assert_eq!(plan.allocations[0].stake_amount, 1_000);
+    assert_eq!(plan.allocations[0].projected_quote_fee_per_day, 818);
+    assert_eq!(plan.allocations[1].vault, empty.vault);
+    assert_eq!(plan.allocations[1].stake_amount, 1);
+    assert_eq!(plan.allocations[1].projected_quote_fee_per_day, 500);
+
+    assert_eq!(plan.unallocated, HashMap::from([(stake_mint, 499)]));
+}
+
+#[test]
+fn large_budget_is_never_exceeded() {
+    let stake_mint = Pubkey::new_unique();
+    // Above 2^53 base units, where the stake amounts no longer fit an f64 exactly.
+    let budget = (1u64 << 62) + 12_345;
+    let vaults = [
+        top_stakers_vault(stake_mint, 3, &[(1 << 58) + 7, (1 << 57) + 3]),
+        top_stakers_vault(stake_mint, 2, &[(1 << 59) + 1, (1 << 56) + 5]),
+        top_stakers_vault(stake_mint, 4, &[(1 << 55) + 9]),
+    ];
+
+    let plan = optimize(
+        &[
+            (&vaults[0], u64::MAX / 4),
+            (&vaults[1], u64::MAX / 8),
+            (&vaults[2], u64::MAX / 16),
+        ],
+        &[(stake_mint, budget)],
+    );
+    let allocated: u128 = plan
+        .allocations
+        .iter()
+        .map(|allocation| u128::from(allocation.stake_amount))
+        .sum();
+    let unallocated = plan
+        .unallocated
+        .get(&stake_mint)
+        .copied()
+        .unwrap_or_default();
+    assert!(allocated <= u128::from(budget));
+    assert_eq!(allocated + u128::from(unallocated), u128::from(budget));
+}
+
+#[test]
+fn projected_income_is_summed_without_overflow() {
+    let stake_mint = Pubkey::new_unique();
+    let vaults = [
+        top_stakers_vault(stake_mint, 2, &[]),
+        top_stakers_vault(stake_mint, 2, &[]),
+    ];
+
+    let plan = optimize(
+        &[(&vaults[0], u64::MAX), (&vaults[1], u64::MAX)],
+        &[(stake_mint, 2)],
+    );
+    assert_eq!(plan.allocations.len(), 2);
+    assert_eq!(plan.projected_quote_fee_per_day(), 2 * u128::from(u64::MAX));
+}
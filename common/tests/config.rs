@@ -0,0 +1,88 @@
+use common::{
+    config::{create_vault_from_config_ix, decode_config},
+    constants::{ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID},
+    pda::*,
+};
+use m3m3::{CONFIG_ACCOUNT_DISCM, INITIALIZE_VAULT_WITH_CONFIG_IX_DISCM};
+use solana_sdk::{pubkey::Pubkey, system_program};
+use std::str::FromStr;
+
+#[test]
+fn decodes_config_layout() {
+    let mut data = CONFIG_ACCOUNT_DISCM.to_vec();
+    data.extend_from_slice(&3u64.to_le_bytes());
+    data.extend_from_slice(&86_400u64.to_le_bytes());
+    data.extend_from_slice(&259_200u64.to_le_bytes());
+    data.extend_from_slice(&3_600u64.to_le_bytes());
+    data.extend_from_slice(&10u16.to_le_bytes());
+    data.extend_from_slice(&[0u8; 6]);
+    data.extend_from_slice(&[0u8; 160]);
+
+    let config = decode_config(&data).unwrap();
+    assert_eq!(config.index, 3);
+    assert_eq!(config.seconds_to_full_unlock, 86_400);
+    assert_eq!(config.unstake_lock_duration, 259_200);
+    assert_eq!(config.join_window_duration, 3_600);
+    assert_eq!(config.top_list_length, 10);
+
+    data[0] ^= 1;
+    assert!(decode_config(&data).is_err());
+    assert!(decode_config(&data[..40]).is_err());
+}
+
+#[test]
+fn config_key_matches_known_vector() {
+    assert_eq!(
+        derive_config_key(7),
+        Pubkey::from_str("5DbSDeMWkyt6xpwXHDxodeD6rXWuRn1m7Bh4PD9QgHLS").unwrap()
+    );
+    assert_ne!(derive_config_key(7), derive_config_key(8));
+}
+
+#[test]
+fn vault_from_config_instruction_accounts_and_data() {
+    let pool = Pubkey::new_unique();
+    let stake_mint = Pubkey::new_unique();
+    let quote_mint = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+
+    let ix =
+        create_vault_from_config_ix(pool, stake_mint, quote_mint, 7, payer, Some(1_700_000_000))
+            .unwrap();
+    assert_eq!(ix.program_id, m3m3::ID);
+
+    let vault = derive_m3m3_vault_key(pool);
+    let expected = [
+        (vault, true, false),
+        (derive_associated_token_key(vault, stake_mint), true, false),
+        (derive_associated_token_key(vault, quote_mint), true, false),
+        (derive_top_staker_list_key(vault), true, false),
+        (derive_full_balance_list_key(vault), true, false),
+        (pool, false, false),
+        (stake_mint, false, false),
+        (quote_mint, false, false),
+        (derive_lock_escrow_key(pool, vault), false, false),
+        (derive_config_key(7), false, false),
+        (payer, true, true),
+        (system_program::ID, false, false),
+        (TOKEN_PROGRAM_ID, false, false),
+        (ASSOCIATED_TOKEN_PROGRAM_ID, false, false),
+        (derive_m3m3_event_authority_key(), false, false),
+        (m3m3::ID, false, false),
+    ];
+    let accounts: Vec<(Pubkey, bool, bool)> = ix
+        .accounts
+        .iter()
+        .map(|meta| (meta.pubkey, meta.is_writable, meta.is_signer))
+        .collect();
+    assert_eq!(accounts, expected);
+
+    let mut data = INITIALIZE_VAULT_WITH_CONFIG_IX_DISCM.to_vec();
+    data.push(1);
+    data.extend_from_slice(&1_700_000_000u64.to_le_bytes());
+    data.extend_from_slice(&[0u8; 64]);
+    assert_eq!(ix.data, data);
+
+    let ix = create_vault_from_config_ix(pool, stake_mint, quote_mint, 7, payer, None).unwrap();
+    assert_eq!(ix.data[8..], [[0u8].as_slice(), &[0u8; 64]].concat());
+}
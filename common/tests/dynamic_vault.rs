@@ -0,0 +1,50 @@
+mod utils;
+
+use common::dynamic_vault::{calculate_locked_profit, get_amount_by_share, get_unlocked_amount};
+use m3m3::StakeForFeeError;
+use utils::vault;
+
+// Vectors produced by the TS `getUnlockedAmount` and `getAmountByShare`.
+#[test]
+fn locked_profit_degradation_matches_ts_vectors() {
+    let vault = vault(1_000_000_007, 333_333_333, 0, 7_777);
+
+    let cases = [
+        (0, 333_333_333, 666_666_674, 83_333_333),
+        (12_345, 333_301_330, 666_698_677, 83_337_333),
+        (64_000_000, 167_423_999, 832_576_008, 104_072_000),
+        (128_592_001, 0, 1_000_000_007, 124_999_999),
+    ];
+
+    for (current_time, locked_profit, unlocked_amount, amount_by_share) in cases {
+        assert_eq!(calculate_locked_profit(&vault, current_time), locked_profit);
+        assert_eq!(
+            get_unlocked_amount(&vault, current_time).unwrap(),
+            unlocked_amount
+        );
+        assert_eq!(
+            get_amount_by_share(&vault, current_time, 123_456_789, 987_654_321).unwrap(),
+            amount_by_share
+        );
+    }
+}
+
+#[test]
+fn locked_profit_is_fully_released_at_denominator() {
+    let vault = vault(1_000, 600, 100, 1_000_000_000);
+
+    assert_eq!(calculate_locked_profit(&vault, 600), 300);
+    // `duration * degradation == denominator` is not past the denominator yet.
+    assert_eq!(calculate_locked_profit(&vault, 1_100), 0);
+    assert_eq!(calculate_locked_profit(&vault, 1_101), 0);
+}
+
+#[test]
+fn amount_by_share_of_empty_supply_fails() {
+    let vault = vault(1_000, 0, 0, 0);
+
+    assert_eq!(
+        get_amount_by_share(&vault, 0, 1, 0),
+        Err(StakeForFeeError::MathOverflow)
+    );
+}
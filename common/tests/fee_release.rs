@@ -0,0 +1,86 @@
+mod utils;
+
+use common::fee_release::{
+    get_released_fees, get_released_fees_with_pending_claim, FeeReleaseSchedule,
+};
+use m3m3::FeeVault;
+use std::num::NonZeroU64;
+use utils::zeroed_fee_vault;
+
+fn fee_vault(
+    locked_fee_a: u64,
+    locked_fee_b: u64,
+    last_updated_at: i64,
+    seconds_to_full_unlock: u64,
+) -> FeeVault {
+    let mut fee_vault = zeroed_fee_vault();
+    fee_vault.top_staker_info.locked_fee_a = locked_fee_a;
+    fee_vault.top_staker_info.locked_fee_b = locked_fee_b;
+    fee_vault.top_staker_info.last_updated_at = last_updated_at;
+    fee_vault.configuration.seconds_to_full_unlock = seconds_to_full_unlock;
+    fee_vault
+}
+
+#[test]
+fn releases_linearly_until_full_unlock() {
+    let fee_vault = fee_vault(1_000, 3_000, 100, 1_000);
+
+    let release = get_released_fees(&fee_vault, 100).unwrap();
+    assert_eq!((release.released_fee_a, release.released_fee_b), (0, 0));
+    assert_eq!((release.locked_fee_a, release.locked_fee_b), (1_000, 3_000));
+
+    let release = get_released_fees(&fee_vault, 433).unwrap();
+    assert_eq!((release.released_fee_a, release.released_fee_b), (333, 999));
+    assert_eq!((release.locked_fee_a, release.locked_fee_b), (667, 2_001));
+
+    let release = get_released_fees(&fee_vault, 1_100).unwrap();
+    assert_eq!(
+        (release.released_fee_a, release.released_fee_b),
+        (1_000, 3_000)
+    );
+    assert_eq!((release.locked_fee_a, release.locked_fee_b), (0, 0));
+
+    // Timestamps before the last update release nothing.
+    let release = get_released_fees(&fee_vault, 50).unwrap();
+    assert_eq!((release.released_fee_a, release.released_fee_b), (0, 0));
+}
+
+#[test]
+fn pending_claim_is_added_to_locked_fees() {
+    let fee_vault = fee_vault(1_000, 0, 0, 100);
+
+    let release = get_released_fees_with_pending_claim(&fee_vault, 50, 1_000, 200).unwrap();
+    assert_eq!(
+        (release.released_fee_a, release.released_fee_b),
+        (1_000, 100)
+    );
+    assert_eq!((release.locked_fee_a, release.locked_fee_b), (1_000, 100));
+}
+
+#[test]
+fn schedule_sums_to_locked_fees() {
+    let fee_vault = fee_vault(1_000_003, 7, 1_000, 86_400);
+
+    let schedule: Vec<_> =
+        FeeReleaseSchedule::new(&fee_vault, 1_000, NonZeroU64::new(3_600).unwrap()).collect();
+
+    assert_eq!(schedule.len(), 24);
+    assert_eq!(schedule.first().unwrap().start, 1_000);
+    assert_eq!(schedule.last().unwrap().end, 1_000 + 86_400);
+    assert_eq!(
+        schedule.iter().map(|i| i.released_fee_a).sum::<u64>(),
+        1_000_003
+    );
+    assert_eq!(schedule.iter().map(|i| i.released_fee_b).sum::<u64>(), 7);
+
+    // Starting halfway only covers what is still locked.
+    let schedule: Vec<_> =
+        FeeReleaseSchedule::new(&fee_vault, 1_000 + 43_200, NonZeroU64::new(3_600).unwrap())
+            .collect();
+    let released = get_released_fees(&fee_vault, 1_000 + 43_200).unwrap();
+    assert_eq!(schedule.len(), 12);
+    assert_eq!(
+        schedule.iter().map(|i| i.released_fee_a).sum::<u64>(),
+        released.locked_fee_a
+    );
+}
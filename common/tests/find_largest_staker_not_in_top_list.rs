@@ -0,0 +1,79 @@
+mod utils;
+
+use common::{
+    decoder::FullBalanceListState, pda::derive_stake_escrow_key,
+    top_list::find_largest_stakers_not_in_top_list,
+};
+use m3m3::{FullBalanceListMetadata, StakerBalance};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use utils::top_staker_list::setup_full_balance_list;
+
+#[test]
+fn largest_stakers_exclude_top_stakers() {
+    let top_staker_count = 20;
+    let non_top_staker_count = 40;
+
+    let full_balance_list = setup_full_balance_list(top_staker_count, non_top_staker_count);
+    let stakers: HashMap<Pubkey, &StakerBalance> = full_balance_list
+        .stakers
+        .iter()
+        .filter(|staker| staker.owner != Pubkey::default())
+        .map(|staker| {
+            (
+                derive_stake_escrow_key(full_balance_list.metadata.vault, staker.owner),
+                staker,
+            )
+        })
+        .collect();
+
+    let largest_stakers = find_largest_stakers_not_in_top_list(
+        top_staker_count + non_top_staker_count,
+        &full_balance_list,
+    );
+
+    assert_eq!(largest_stakers.len(), non_top_staker_count);
+    assert!(largest_stakers
+        .iter()
+        .all(|escrow| stakers[escrow].is_in_top_list == 0));
+
+    for pair in largest_stakers.windows(2) {
+        assert!(stakers[&pair[1]].balance <= stakers[&pair[0]].balance);
+    }
+}
+
+#[test]
+fn earlier_index_is_larger_when_balance_is_equal() {
+    let owners = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+
+    let full_balance_list = FullBalanceListState {
+        metadata: FullBalanceListMetadata {
+            vault: Pubkey::new_unique(),
+            length: owners.len() as u64,
+        },
+        stakers: owners
+            .iter()
+            .map(|owner| StakerBalance {
+                balance: 100,
+                owner: *owner,
+                is_in_top_list: 0,
+                padding: [0; 7],
+            })
+            .collect(),
+    };
+
+    let largest_stakers = find_largest_stakers_not_in_top_list(2, &full_balance_list);
+
+    let vault = full_balance_list.metadata.vault;
+    assert_eq!(
+        largest_stakers,
+        [
+            derive_stake_escrow_key(vault, owners[0]),
+            derive_stake_escrow_key(vault, owners[1]),
+        ]
+    );
+}
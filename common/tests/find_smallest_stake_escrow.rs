@@ -0,0 +1,86 @@
+use common::{
+    decoder::FullBalanceListState,
+    pda::derive_stake_escrow_key,
+    top_list::{find_smallest_full_balance_index, find_smallest_stake_escrow_in_full_balance_list},
+};
+use m3m3::{FullBalanceListMetadata, StakerBalance};
+use solana_sdk::pubkey::Pubkey;
+
+fn full_balance_list(balances: &[(u64, bool)]) -> FullBalanceListState {
+    FullBalanceListState {
+        metadata: FullBalanceListMetadata {
+            vault: Pubkey::new_unique(),
+            length: balances.len() as u64,
+        },
+        stakers: balances
+            .iter()
+            .map(|(balance, in_top_list)| StakerBalance {
+                balance: *balance,
+                owner: Pubkey::new_unique(),
+                is_in_top_list: (*in_top_list).into(),
+                padding: [0; 7],
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn first_empty_balance_from_the_end() {
+    let list = full_balance_list(&[(0, false), (300, true), (0, false), (50, false)]);
+
+    assert_eq!(
+        find_smallest_full_balance_index(Pubkey::default(), &list),
+        Some(2)
+    );
+    assert_eq!(
+        find_smallest_stake_escrow_in_full_balance_list(Pubkey::default(), &list),
+        Some(derive_stake_escrow_key(
+            list.metadata.vault,
+            list.stakers[2].owner
+        ))
+    );
+    // The skipped owner's empty balance is passed over.
+    assert_eq!(
+        find_smallest_full_balance_index(list.stakers[2].owner, &list),
+        Some(0)
+    );
+}
+
+#[test]
+fn later_index_is_smaller_when_balance_is_equal() {
+    let list = full_balance_list(&[(100, false), (50, false), (300, true), (50, true)]);
+
+    // The top staker at the end is found first and kept, as in the TS.
+    assert_eq!(
+        find_smallest_full_balance_index(Pubkey::default(), &list),
+        Some(3)
+    );
+    assert_eq!(
+        find_smallest_full_balance_index(list.stakers[3].owner, &list),
+        Some(1)
+    );
+}
+
+#[test]
+fn top_staker_is_returned_without_other_staker() {
+    let list = full_balance_list(&[(300, true), (100, false), (200, true)]);
+
+    assert_eq!(
+        find_smallest_full_balance_index(list.stakers[1].owner, &list),
+        Some(2)
+    );
+}
+
+#[test]
+fn none_without_any_other_staker() {
+    assert_eq!(
+        find_smallest_full_balance_index(Pubkey::default(), &full_balance_list(&[])),
+        None
+    );
+
+    let list = full_balance_list(&[(100, false)]);
+    assert_eq!(
+        find_smallest_stake_escrow_in_full_balance_list(list.stakers[0].owner, &list),
+        None
+    );
+}
@@ -0,0 +1,86 @@
+mod utils;
+
+use common::{
+    decoder::TopStakerListState, pda::derive_stake_escrow_key,
+    top_list::find_replaceable_top_stakers,
+};
+use m3m3::StakerMetadata;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use utils::top_staker_list::setup_top_staker_list_with_predefined_smallest_stakers;
+
+fn assert_smallest_stakers(extra_staker_count: usize, extra_list_length: usize) {
+    let fixture = setup_top_staker_list_with_predefined_smallest_stakers(
+        extra_staker_count,
+        extra_list_length,
+    );
+    let vault = fixture.top_staker_list.metadata.vault;
+
+    let asc_ordered_top_stakers = [
+        derive_stake_escrow_key(vault, fixture.smallest_staker_0.owner),
+        derive_stake_escrow_key(vault, fixture.smallest_staker_1.owner),
+        derive_stake_escrow_key(vault, fixture.smallest_staker_2.owner),
+    ];
+
+    for lookup_number in 1..=3 {
+        let smallest_top_stakers =
+            find_replaceable_top_stakers(lookup_number, &fixture.top_staker_list);
+
+        assert_eq!(smallest_top_stakers.len(), lookup_number);
+        assert_eq!(
+            smallest_top_stakers,
+            asc_ordered_top_stakers[..lookup_number]
+        );
+    }
+}
+
+fn stakers_by_escrow(top_staker_list: &TopStakerListState) -> HashMap<Pubkey, &StakerMetadata> {
+    top_staker_list
+        .stakers
+        .iter()
+        .map(|staker| {
+            (
+                derive_stake_escrow_key(top_staker_list.metadata.vault, staker.owner),
+                staker,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn smallest_stakers_when_top_staker_list_is_not_full() {
+    assert_smallest_stakers(50, 100);
+}
+
+#[test]
+fn smallest_stakers_when_top_staker_list_is_full() {
+    assert_smallest_stakers(50, 50);
+}
+
+#[test]
+fn all_top_stakers_when_lookup_number_exceeds_list() {
+    let extra_staker_count = 100;
+    let list_length = extra_staker_count + 3;
+
+    let fixture =
+        setup_top_staker_list_with_predefined_smallest_stakers(extra_staker_count, list_length);
+    let stakers = stakers_by_escrow(&fixture.top_staker_list);
+
+    for lookup_number in [1, 2, 50, list_length, list_length + 20] {
+        let smallest_top_stakers =
+            find_replaceable_top_stakers(lookup_number, &fixture.top_staker_list);
+
+        assert_eq!(smallest_top_stakers.len(), lookup_number.min(list_length));
+
+        for pair in smallest_top_stakers.windows(2) {
+            let smaller_staker = stakers[&pair[0]];
+            let current_staker = stakers[&pair[1]];
+
+            if current_staker.stake_amount == smaller_staker.stake_amount {
+                assert!(current_staker.full_balance_index < smaller_staker.full_balance_index);
+            } else {
+                assert!(current_staker.stake_amount > smaller_staker.stake_amount);
+            }
+        }
+    }
+}
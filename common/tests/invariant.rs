@@ -0,0 +1,158 @@
+mod utils;
+
+use common::{
+    constants::NO_FULL_BALANCE_INDEX,
+    invariant::{check_vault_invariants, InvariantViolation},
+    math::q64,
+    pda::derive_stake_escrow_key,
+};
+use m3m3::Unstake;
+use solana_sdk::pubkey::Pubkey;
+use utils::{zeroed_stake_escrow, VaultState};
+
+/// Two top stakers of 300 and 200 earning 1 token A per staked token, and a staker of 100 with 50
+/// more unstaking. 10 token A and 7 token B are still locked.
+fn consistent_vault() -> VaultState {
+    let mut vault = VaultState::new(2, &[(300, true), (200, true), (100, false)]);
+
+    let fee_vault = &mut vault.fee_vault;
+    fee_vault.metrics.ongoing_total_partial_unstake_amount = 50;
+    fee_vault.top_staker_info.cumulative_fee_a_per_liquidity = q64::ONE;
+    fee_vault.top_staker_info.locked_fee_a = 10;
+    fee_vault.top_staker_info.locked_fee_b = 7;
+
+    vault.stake_escrows[2].ongoing_total_partial_unstake_amount = 50;
+    vault.unstakes.push(Unstake {
+        stake_escrow: vault.escrow(2),
+        unstake_amount: 50,
+        created_at: 0,
+        release_at: 0,
+        padding: [0; 30],
+    });
+
+    vault
+}
+
+#[test]
+fn consistent_vault_has_no_violation() {
+    let vault = consistent_vault();
+
+    // 600 staked + 50 unstaking + 500 unclaimed + 10 locked.
+    let report = check_vault_invariants(&vault.accounts(1_160, 7)).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.violations);
+
+    let report = check_vault_invariants(&vault.accounts(1_159, 6)).unwrap();
+    assert_eq!(
+        report.violations,
+        vec![
+            InvariantViolation::InsufficientStakeTokenBalance {
+                balance: 1_159,
+                required: 1_160,
+            },
+            InvariantViolation::InsufficientQuoteTokenBalance {
+                balance: 6,
+                required: 7,
+            },
+        ]
+    );
+}
+
+#[test]
+fn report_lists_every_violation() {
+    let mut vault = consistent_vault();
+    let alice = vault.stake_escrows[0].owner;
+    let carol = vault.stake_escrows[2].owner;
+
+    // Carol's escrow claims to be in the top list and points at Alice's full balance entry, and
+    // her unstake is dropped.
+    vault.stake_escrows[2].in_top_list = 1;
+    vault.stake_escrows[2].full_balance_index = 0;
+    let unstake = vault.unstakes.pop().unwrap();
+    // An unstake of an unknown escrow.
+    let orphan = Pubkey::new_unique();
+    vault.unstakes.push(Unstake {
+        stake_escrow: orphan,
+        ..unstake
+    });
+    vault.fee_vault.top_staker_info.effective_stake_amount = 499;
+
+    let report = check_vault_invariants(&vault.accounts(u64::MAX, u64::MAX)).unwrap();
+    assert_eq!(
+        report.violations,
+        vec![
+            InvariantViolation::EffectiveStakeAmountMismatch {
+                top_staker_info: 499,
+                top_staker_list: 500,
+            },
+            InvariantViolation::FullBalanceIndexMismatch {
+                owner: carol,
+                full_balance_index: 0,
+                full_balance_list_owner: Some(alice),
+            },
+            InvariantViolation::TopListFlagMismatch {
+                owner: carol,
+                stake_escrow: true,
+                top_staker_list: false,
+                full_balance_list: None,
+            },
+            InvariantViolation::EscrowUnstakeAmountMismatch {
+                owner: carol,
+                stake_escrow: 50,
+                unstakes: 0,
+            },
+            InvariantViolation::MissingStakeEscrow {
+                owner: None,
+                stake_escrow: orphan,
+            },
+        ]
+    );
+}
+
+#[test]
+fn escrow_without_full_balance_index_is_consistent() {
+    let mut vault = consistent_vault();
+    let carol = vault.stake_escrows[2].owner;
+    // Dave's slot was reclaimed by Carol.
+    let mut dave = zeroed_stake_escrow();
+    dave.owner = Pubkey::new_unique();
+    dave.vault = vault.vault;
+    dave.full_balance_index = NO_FULL_BALANCE_INDEX;
+    dave.stake_amount = 50;
+    vault.stake_escrows.push(dave.clone());
+    vault.fee_vault.metrics.total_stake_escrow_count = 4;
+    vault.fee_vault.metrics.total_staked_amount = 650;
+
+    let report = check_vault_invariants(&vault.accounts(1_210, 7)).unwrap();
+    assert!(report.is_consistent(), "{:?}", report.violations);
+
+    // A stale index is not how a reclaimed slot is recorded.
+    for (full_balance_index, full_balance_list_owner) in [(2, Some(carol)), (3, None)] {
+        vault.stake_escrows[3].full_balance_index = full_balance_index;
+        let report = check_vault_invariants(&vault.accounts(1_210, 7)).unwrap();
+        assert_eq!(
+            report.violations,
+            vec![InvariantViolation::FullBalanceIndexMismatch {
+                owner: dave.owner,
+                full_balance_index,
+                full_balance_list_owner,
+            }]
+        );
+    }
+}
+
+#[test]
+fn list_entries_without_stake_escrow_are_reported() {
+    let mut vault = consistent_vault();
+    let bob = vault.stake_escrows.remove(1);
+    vault.fee_vault.metrics.total_stake_escrow_count = 2;
+    vault.fee_vault.metrics.total_staked_amount = 400;
+
+    let report = check_vault_invariants(&vault.accounts(u64::MAX, u64::MAX)).unwrap();
+    assert_eq!(
+        report.violations,
+        vec![InvariantViolation::MissingStakeEscrow {
+            owner: Some(bob.owner),
+            stake_escrow: derive_stake_escrow_key(bob.vault, bob.owner),
+        }]
+    );
+}
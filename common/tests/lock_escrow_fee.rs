@@ -0,0 +1,133 @@
+mod utils;
+
+use common::{
+    dynamic_amm::{
+        decode_lock_escrow, get_locked_escrow_pending_fee, get_virtual_price, PoolLpAmounts,
+        LOCK_ESCROW_ACCOUNT_DISCM,
+    },
+    dynamic_vault::get_amount_by_share,
+};
+use solana_sdk::pubkey::Pubkey;
+use utils::{lock_escrow, vault, zeroed_fee_vault};
+
+// Vectors produced by the TS `getVirtualPrice` and `getLockedEscrowPendingFee`.
+#[test]
+fn pending_fee_matches_ts_vectors() {
+    let cases = [
+        (
+            10_000,
+            9_000,
+            lock_escrow(1_000_000, 0),
+            vault(5_000_000_000, 0, 0, 0),
+            vault(2_000_000_000, 0, 0, 0),
+            PoolLpAmounts {
+                a_vault_lp_amount: 4_000_000_000,
+                b_vault_lp_amount: 1_500_000_000,
+                a_vault_lp_supply: 4_500_000_000,
+                b_vault_lp_supply: 1_600_000_000,
+                pool_lp_supply: 3_000_000_000,
+            },
+            17_750_387_755_217_275_755u128,
+            (1_481_481, 625_000),
+        ),
+        (
+            1_700_000_000,
+            1_699_999_000,
+            lock_escrow(250_000_000, (1u128 << 64) * 9 / 10),
+            vault(123_456_789_012, 3_000_000_000, 1_699_999_500, 1_000_000),
+            vault(98_765_432_109, 1_000_000, 1_699_990_000, 2_000_000_000),
+            PoolLpAmounts {
+                a_vault_lp_amount: 100_000_000_000,
+                b_vault_lp_amount: 90_000_000_000,
+                a_vault_lp_supply: 110_000_000_000,
+                b_vault_lp_supply: 95_000_000_000,
+                pool_lp_supply: 100_000_000_000,
+            },
+            18_672_551_294_736_193_861,
+            (30_356_501, 25_937_707),
+        ),
+        (
+            // Claimed less than `MIN_LOCK_ESCROW_CLAIM_FEE_DURATION` ago.
+            1_700_000_000,
+            1_699_999_800,
+            lock_escrow(250_000_000, 0),
+            vault(10, 0, 0, 0),
+            vault(10, 0, 0, 0),
+            PoolLpAmounts {
+                a_vault_lp_amount: 1,
+                b_vault_lp_amount: 1,
+                a_vault_lp_supply: 1,
+                b_vault_lp_supply: 1,
+                pool_lp_supply: 1,
+            },
+            184_467_440_737_095_516_160,
+            (0, 0),
+        ),
+    ];
+
+    for (current_time, last_claim_fee_at, lock_escrow, a_vault, b_vault, lp_amounts, vp, fees) in
+        cases
+    {
+        let mut fee_vault = zeroed_fee_vault();
+        fee_vault.top_staker_info.last_claim_fee_at = last_claim_fee_at;
+
+        let token_a_amount = get_amount_by_share(
+            &a_vault,
+            current_time,
+            lp_amounts.a_vault_lp_amount,
+            lp_amounts.a_vault_lp_supply,
+        )
+        .unwrap();
+        let token_b_amount = get_amount_by_share(
+            &b_vault,
+            current_time,
+            lp_amounts.b_vault_lp_amount,
+            lp_amounts.b_vault_lp_supply,
+        )
+        .unwrap();
+        assert_eq!(
+            get_virtual_price(token_a_amount, token_b_amount, lp_amounts.pool_lp_supply).unwrap(),
+            vp
+        );
+
+        let pending_fees = get_locked_escrow_pending_fee(
+            current_time,
+            &fee_vault,
+            &lock_escrow,
+            &a_vault,
+            &b_vault,
+            &lp_amounts,
+        )
+        .unwrap();
+        assert_eq!(pending_fees, fees);
+    }
+}
+
+#[test]
+fn decode_lock_escrow_account() {
+    let pool = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let escrow_vault = Pubkey::new_unique();
+
+    let mut data = LOCK_ESCROW_ACCOUNT_DISCM.to_vec();
+    data.extend_from_slice(pool.as_ref());
+    data.extend_from_slice(owner.as_ref());
+    data.extend_from_slice(escrow_vault.as_ref());
+    data.push(254);
+    data.extend_from_slice(&1_000u64.to_le_bytes());
+    data.extend_from_slice(&(1u128 << 64).to_le_bytes());
+    data.extend_from_slice(&1u64.to_le_bytes());
+    data.extend_from_slice(&2u64.to_le_bytes());
+    data.extend_from_slice(&3u64.to_le_bytes());
+
+    let lock_escrow = decode_lock_escrow(&data).unwrap();
+    assert_eq!(lock_escrow.pool, pool);
+    assert_eq!(lock_escrow.owner, owner);
+    assert_eq!(lock_escrow.escrow_vault, escrow_vault);
+    assert_eq!(lock_escrow.total_locked_amount, 1_000);
+    assert_eq!(lock_escrow.lp_per_token, 1u128 << 64);
+    assert_eq!((lock_escrow.a_fee, lock_escrow.b_fee), (2, 3));
+
+    data[0] ^= 1;
+    assert!(decode_lock_escrow(&data).is_err());
+}
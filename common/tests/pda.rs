@@ -0,0 +1,19 @@
+use common::pda::{derive_stake_escrow_addresses, derive_stake_escrow_key, VaultOwner};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+#[test]
+fn stake_escrow_key_matches_known_vector() {
+    let vault = Pubkey::new_from_array([1; 32]);
+    let owner = Pubkey::new_from_array([2; 32]);
+    let stake_escrow = Pubkey::from_str("B4qhoiezeNiXGwEut3415VFEWAvfXBvYCw9r6Tkiu5uL").unwrap();
+
+    assert_eq!(derive_stake_escrow_key(vault, owner), stake_escrow);
+
+    let addresses = derive_stake_escrow_addresses(&[VaultOwner {
+        vault,
+        stake_mint: Pubkey::new_unique(),
+        owner,
+    }]);
+    assert_eq!(addresses[&stake_escrow].owner, owner);
+}
@@ -0,0 +1,172 @@
+use common::math::fee::get_stake_escrow_pending_fees;
+use m3m3::{StakeEscrow, TopStakerInfo};
+use solana_sdk::pubkey::Pubkey;
+
+fn top_staker_info(
+    effective_stake_amount: u64,
+    cumulative_fee_a_per_liquidity: u128,
+    cumulative_fee_b_per_liquidity: u128,
+) -> TopStakerInfo {
+    TopStakerInfo {
+        top_list_length: 0,
+        current_length: 0,
+        effective_stake_amount,
+        last_claim_fee_at: 0,
+        last_updated_at: 0,
+        locked_fee_a: 0,
+        locked_fee_b: 0,
+        padding0: 0,
+        cumulative_fee_a_per_liquidity,
+        cumulative_fee_b_per_liquidity,
+        padding: [0; 4],
+    }
+}
+
+fn stake_escrow(
+    stake_amount: u64,
+    in_top_list: u8,
+    fee_a_per_liquidity_checkpoint: u128,
+    fee_b_per_liquidity_checkpoint: u128,
+    fee_a_pending: u64,
+    fee_b_pending: u64,
+) -> StakeEscrow {
+    StakeEscrow {
+        owner: Pubkey::new_unique(),
+        vault: Pubkey::new_unique(),
+        full_balance_index: 0,
+        stake_amount,
+        in_top_list,
+        padding0: [0; 15],
+        ongoing_total_partial_unstake_amount: 0,
+        created_at: 0,
+        fee_a_claimed_amount: 0,
+        fee_b_claimed_amount: 0,
+        fee_a_per_liquidity_checkpoint,
+        fee_b_per_liquidity_checkpoint,
+        fee_a_pending,
+        fee_b_pending,
+        padding: [0; 20],
+    }
+}
+
+// Vectors produced by the TS `StakeForFee.getUserStakeAndClaimBalance` computation.
+// released a, released b, effective stake, cumulative a, cumulative b, checkpoint a,
+// checkpoint b, stake amount, pending a, pending b, in top list, expected a, expected b
+#[allow(clippy::type_complexity)]
+const TS_VECTORS: [(
+    u64,
+    u64,
+    u64,
+    u128,
+    u128,
+    u128,
+    u128,
+    u64,
+    u64,
+    u64,
+    u8,
+    u64,
+    u64,
+); 6] = [
+    (
+        1000000, 2500000, 10000000, 0, 0, 0, 0, 2500000, 0, 0, 1, 249999, 625000,
+    ),
+    (
+        123456789,
+        987654321,
+        333333333,
+        55340232221128654848,
+        18446744073709551616,
+        36893488147419103232,
+        0,
+        111111111,
+        17,
+        42,
+        1,
+        152263390,
+        440329259,
+    ),
+    (999, 1, 7, 0, 0, 0, 0, 3, 0, 0, 1, 428, 0),
+    (
+        5000,
+        5000,
+        0,
+        92233720368547758080,
+        92233720368547758080,
+        18446744073709551616,
+        0,
+        10,
+        3,
+        4,
+        1,
+        43,
+        54,
+    ),
+    (
+        u64::MAX,
+        u64::MAX,
+        u64::MAX,
+        0,
+        0,
+        0,
+        0,
+        u64::MAX,
+        0,
+        0,
+        1,
+        u64::MAX,
+        u64::MAX,
+    ),
+    (1000000, 1000000, 10, 0, 0, 0, 0, 10, 7, 9, 0, 7, 9),
+];
+
+#[test]
+fn pending_fees_match_ts_vectors() {
+    for (
+        released_fee_a,
+        released_fee_b,
+        effective_stake_amount,
+        cumulative_a,
+        cumulative_b,
+        checkpoint_a,
+        checkpoint_b,
+        stake_amount,
+        pending_a,
+        pending_b,
+        in_top_list,
+        expected_a,
+        expected_b,
+    ) in TS_VECTORS
+    {
+        let top_staker_info = top_staker_info(effective_stake_amount, cumulative_a, cumulative_b);
+        let stake_escrow = stake_escrow(
+            stake_amount,
+            in_top_list,
+            checkpoint_a,
+            checkpoint_b,
+            pending_a,
+            pending_b,
+        );
+
+        let pending_fees = get_stake_escrow_pending_fees(
+            &top_staker_info,
+            &stake_escrow,
+            released_fee_a,
+            released_fee_b,
+        )
+        .unwrap();
+
+        assert_eq!(pending_fees, (expected_a, expected_b));
+    }
+}
+
+#[test]
+fn zero_effective_stake_does_not_divide_by_zero() {
+    let top_staker_info = top_staker_info(0, 1 << 64, 1 << 64);
+    let stake_escrow = stake_escrow(100, 1, 0, 0, 5, 6);
+
+    let pending_fees =
+        get_stake_escrow_pending_fees(&top_staker_info, &stake_escrow, 1_000, 1_000).unwrap();
+
+    assert_eq!(pending_fees, (105, 106));
+}
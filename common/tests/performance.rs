@@ -0,0 +1,193 @@
+mod utils;
+
+use common::{
+    decoder::FullBalanceListState,
+    dynamic_amm::PoolLpAmounts,
+    performance::{
+        calculate_fee_farm_performance, get_fee_emission_per_day, get_stake_escrow_earning_per_day,
+        get_stake_escrow_earning_per_day_after_unstake, DailyFee, EarningAfterUnstake,
+        FeeVaultSnapshot, TokenPrice,
+    },
+};
+use m3m3::{FullBalanceListMetadata, StakeForFeeError, StakerBalance};
+use rust_decimal::Decimal;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use utils::{lock_escrow, vault, zeroed_fee_vault, zeroed_stake_escrow};
+
+fn snapshot(current_time: i64, total_fee_a: u128, total_fee_b: u128) -> FeeVaultSnapshot {
+    let mut fee_vault = zeroed_fee_vault();
+    fee_vault.metrics.total_fee_a_amount = total_fee_a;
+    fee_vault.metrics.total_fee_b_amount = total_fee_b;
+    fee_vault.top_staker_info.last_claim_fee_at = 9_000;
+    fee_vault.top_staker_info.effective_stake_amount = 500_000_000_000;
+
+    // Same accounts as the first `lock_escrow_fee` vector, claimable (1_481_481, 625_000) at
+    // 10_000 and nothing before 9_300.
+    FeeVaultSnapshot {
+        current_time,
+        fee_vault,
+        lock_escrow: lock_escrow(1_000_000, 0),
+        a_vault: vault(5_000_000_000, 0, 0, 0),
+        b_vault: vault(2_000_000_000, 0, 0, 0),
+        lp_amounts: PoolLpAmounts {
+            a_vault_lp_amount: 4_000_000_000,
+            b_vault_lp_amount: 1_500_000_000,
+            a_vault_lp_supply: 4_500_000_000,
+            b_vault_lp_supply: 1_600_000_000,
+            pool_lp_supply: 3_000_000_000,
+        },
+    }
+}
+
+fn price(decimals: u8, usd_rate: &str) -> TokenPrice {
+    TokenPrice {
+        decimals,
+        usd_rate: Decimal::from_str(usd_rate).unwrap(),
+    }
+}
+
+#[test]
+fn performance_includes_lock_escrow_claimable_fee() {
+    let snapshot_t0 = snapshot(1_360, 0, 0);
+    let snapshot_t1 = snapshot(10_000, 518_519, 375_000);
+    assert_eq!(snapshot_t1.total_fees().unwrap(), (2_000_000, 1_000_000));
+
+    let token_a = price(6, "2.5");
+    let token_b = price(9, "150");
+
+    // $5.15 of fees over a tenth of a day, on $1.25M of effective stake.
+    let performance =
+        calculate_fee_farm_performance(&snapshot_t0, &snapshot_t1, token_a, token_b, token_a)
+            .unwrap();
+    assert_eq!(performance.usd_per_day, Decimal::from_str("51.5").unwrap());
+    assert_eq!(performance.apr, Decimal::from_str("1.5038").unwrap());
+    assert_eq!(
+        performance.apy.round_dp(12),
+        Decimal::from_str("1.515132517726").unwrap()
+    );
+}
+
+#[test]
+fn performance_rejects_invalid_inputs() {
+    let snapshot_t0 = snapshot(1_360, 0, 0);
+    let snapshot_t1 = snapshot(10_000, 518_519, 375_000);
+    let token = price(6, "1");
+
+    assert_eq!(
+        calculate_fee_farm_performance(&snapshot_t1, &snapshot_t1, token, token, token),
+        Err(StakeForFeeError::MathOverflow)
+    );
+
+    let mut unstaked = snapshot_t1.clone();
+    unstaked.fee_vault.top_staker_info.effective_stake_amount = 0;
+    assert_eq!(
+        calculate_fee_farm_performance(&snapshot_t0, &unstaked, token, token, token),
+        Err(StakeForFeeError::MathOverflow)
+    );
+
+    assert_eq!(
+        price(29, "1").to_ui_amount(1),
+        Err(StakeForFeeError::MathOverflow)
+    );
+}
+
+fn staker_balance(balance: u64, is_in_top_list: bool) -> StakerBalance {
+    StakerBalance {
+        balance,
+        owner: Pubkey::new_unique(),
+        is_in_top_list: is_in_top_list.into(),
+        padding: [0; 7],
+    }
+}
+
+#[test]
+fn earning_per_day_accounts_for_top_list_replacement() {
+    let fee_emission_per_day =
+        get_fee_emission_per_day(&snapshot(1_360, 0, 0), &snapshot(10_000, 518_519, 375_000))
+            .unwrap();
+    assert_eq!(
+        fee_emission_per_day,
+        DailyFee {
+            fee_a: 20_000_000,
+            fee_b: 10_000_000,
+        }
+    );
+
+    let mut top_staker_info = zeroed_fee_vault().top_staker_info;
+    top_staker_info.effective_stake_amount = 1_000;
+
+    let mut stake_escrow = zeroed_stake_escrow();
+    stake_escrow.stake_amount = 300;
+    stake_escrow.full_balance_index = 0;
+    stake_escrow.in_top_list = 1;
+
+    let full_balance_list = FullBalanceListState {
+        metadata: FullBalanceListMetadata {
+            vault: Pubkey::new_unique(),
+            length: 4,
+        },
+        stakers: vec![
+            staker_balance(300, true),
+            staker_balance(700, true),
+            staker_balance(150, false),
+            staker_balance(200, false),
+        ],
+    };
+
+    assert_eq!(
+        get_stake_escrow_earning_per_day(&stake_escrow, &top_staker_info, fee_emission_per_day)
+            .unwrap(),
+        DailyFee {
+            fee_a: 6_000_000,
+            fee_b: 3_000_000,
+        }
+    );
+
+    let earning_after_unstake = |unstake_amount| {
+        get_stake_escrow_earning_per_day_after_unstake(
+            &stake_escrow,
+            &top_staker_info,
+            &full_balance_list,
+            unstake_amount,
+            fee_emission_per_day,
+        )
+    };
+
+    assert_eq!(
+        earning_after_unstake(50).unwrap(),
+        EarningAfterUnstake {
+            earning_per_day: DailyFee {
+                fee_a: 5_263_157,
+                fee_b: 2_631_578,
+            },
+            in_top_list: true,
+        }
+    );
+    // Ties with the largest staker outside the top list but has the earlier full balance index.
+    assert_eq!(
+        earning_after_unstake(100).unwrap(),
+        EarningAfterUnstake {
+            earning_per_day: DailyFee {
+                fee_a: 4_444_444,
+                fee_b: 2_222_222,
+            },
+            in_top_list: true,
+        }
+    );
+    assert_eq!(
+        earning_after_unstake(101).unwrap(),
+        EarningAfterUnstake::default()
+    );
+    assert_eq!(
+        earning_after_unstake(301),
+        Err(StakeForFeeError::InsufficientStakeAmount)
+    );
+
+    stake_escrow.in_top_list = 0;
+    assert_eq!(
+        get_stake_escrow_earning_per_day(&stake_escrow, &top_staker_info, fee_emission_per_day)
+            .unwrap(),
+        DailyFee::default()
+    );
+}
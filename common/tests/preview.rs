@@ -0,0 +1,213 @@
+mod utils;
+
+use common::{constants::NO_FULL_BALANCE_INDEX, pda::derive_stake_escrow_key};
+use m3m3::{StakeEscrow, StakeForFeeError};
+use rust_decimal::Decimal;
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey};
+use utils::{zeroed_stake_escrow, VaultState};
+
+/// An escrow whose full balance list slot was reclaimed.
+fn escrow_without_index(state: &VaultState) -> StakeEscrow {
+    let mut stake_escrow = zeroed_stake_escrow();
+    stake_escrow.owner = Pubkey::new_unique();
+    stake_escrow.vault = state.vault;
+    stake_escrow.full_balance_index = NO_FULL_BALANCE_INDEX;
+    stake_escrow
+}
+
+#[test]
+fn new_staker_displaces_smallest_top_staker() {
+    let state = VaultState::new(2, &[(300, true), (100, true), (50, false)]);
+    let owner = Pubkey::new_unique();
+
+    let preview = state
+        .preview_state()
+        .preview_stake(owner, None, 200)
+        .unwrap();
+    assert_eq!(
+        preview.stake_escrow,
+        derive_stake_escrow_key(state.vault, owner)
+    );
+    assert!(preview.initialize_stake_escrow);
+    assert!(!preview.was_in_top_list && preview.in_top_list);
+    assert_eq!(preview.displaced_owner, Some(state.owner(1)));
+    assert_eq!(preview.reclaimed_owner, None);
+    assert_eq!(preview.effective_stake_amount, 500);
+    assert_eq!(preview.fee_share, Decimal::new(4, 1));
+    assert_eq!(preview.smallest_stake_escrow, Some(state.escrow(2)));
+    assert_eq!(
+        preview.remaining_accounts,
+        vec![
+            AccountMeta::new(state.escrow(1), false),
+            AccountMeta::new(state.escrow(0), false),
+        ]
+    );
+
+    // Equal to the smallest top stake, the later full balance index ranks lower.
+    let preview = state
+        .preview_state()
+        .preview_stake(owner, None, 100)
+        .unwrap();
+    assert!(!preview.in_top_list);
+    assert_eq!(preview.displaced_owner, None);
+    assert_eq!(preview.effective_stake_amount, 400);
+    assert_eq!(preview.fee_share, Decimal::ZERO);
+}
+
+#[test]
+fn top_staker_stake_adds_to_effective_stake() {
+    let state = VaultState::new(2, &[(300, true), (100, true)]);
+
+    let preview = state
+        .preview_state()
+        .preview_stake(state.owner(1), Some(&state.stake_escrows[1]), 100)
+        .unwrap();
+    assert!(!preview.initialize_stake_escrow);
+    assert!(preview.was_in_top_list && preview.in_top_list);
+    assert_eq!(preview.stake_amount, 200);
+    assert_eq!(preview.displaced_owner, None);
+    assert_eq!(preview.effective_stake_amount, 500);
+    assert_eq!(preview.fee_share, Decimal::new(4, 1));
+    // Without another staker outside the top list, a top staker is passed as in the TS.
+    assert_eq!(preview.smallest_stake_escrow, Some(state.escrow(0)));
+}
+
+#[test]
+fn escrow_without_index_reclaims_smallest_staker_outside_top_list() {
+    let state = VaultState::new(1, &[(300, true), (100, false), (50, false)]);
+    let stake_escrow = escrow_without_index(&state);
+
+    let preview = state
+        .preview_state()
+        .preview_stake(stake_escrow.owner, Some(&stake_escrow), 51)
+        .unwrap();
+    assert_eq!(preview.reclaimed_owner, Some(state.owner(2)));
+    assert_eq!(preview.smallest_stake_escrow, Some(state.escrow(2)));
+    assert!(!preview.in_top_list);
+
+    assert_eq!(
+        state
+            .preview_state()
+            .preview_stake(stake_escrow.owner, Some(&stake_escrow), 50),
+        Err(StakeForFeeError::FullBalanceListFull)
+    );
+}
+
+#[test]
+fn reclaimed_staker_is_the_smallest_stake_escrow_on_a_tie_with_a_top_staker() {
+    let state = VaultState::new(2, &[(300, true), (100, true), (100, false)]);
+    let stake_escrow = escrow_without_index(&state);
+
+    let preview = state
+        .preview_state()
+        .preview_stake(stake_escrow.owner, Some(&stake_escrow), 101)
+        .unwrap();
+    // On equal balance the later index is the smaller one, so the top staker keeps its slot.
+    assert_eq!(preview.reclaimed_owner, Some(state.owner(2)));
+    assert_eq!(preview.smallest_stake_escrow, Some(state.escrow(2)));
+    assert_eq!(preview.displaced_owner, Some(state.owner(1)));
+    assert_eq!(preview.effective_stake_amount, 401);
+}
+
+#[test]
+fn top_staker_is_reclaimed_when_every_staker_is_in_top_list() {
+    let state = VaultState::new(2, &[(300, true), (100, true)]);
+    let stake_escrow = escrow_without_index(&state);
+
+    let preview = state
+        .preview_state()
+        .preview_stake(stake_escrow.owner, Some(&stake_escrow), 101)
+        .unwrap();
+    assert_eq!(preview.reclaimed_owner, Some(state.owner(1)));
+    assert_eq!(preview.smallest_stake_escrow, Some(state.escrow(1)));
+    // The reclaimed staker leaves the top list with its slot, so nobody is displaced.
+    assert!(preview.in_top_list);
+    assert_eq!(preview.displaced_owner, None);
+    assert_eq!(preview.effective_stake_amount, 401);
+}
+
+#[test]
+fn invalid_stake_is_rejected() {
+    let state = VaultState::new(2, &[(300, true)]);
+
+    assert_eq!(
+        state.preview_state().preview_stake(state.owner(0), None, 0),
+        Err(StakeForFeeError::InsufficientStakeAmount)
+    );
+    assert_eq!(
+        state.preview_state().preview_stake(
+            Pubkey::new_unique(),
+            Some(&state.stake_escrows[0]),
+            10
+        ),
+        Err(StakeForFeeError::InvalidStakeEscrow)
+    );
+}
+
+#[test]
+fn unstaking_top_staker_is_replaced_by_largest_staker_outside_top_list() {
+    let state = VaultState::new(2, &[(300, true), (200, true), (100, false), (50, false)]);
+
+    let preview = state
+        .preview_state()
+        .preview_request_unstake(state.owner(0), Some(&state.stake_escrows[0]), 250)
+        .unwrap();
+    assert_eq!(preview.stake_amount, 50);
+    assert!(preview.was_in_top_list && !preview.in_top_list);
+    assert_eq!(preview.promoted_owner, Some(state.owner(2)));
+    assert_eq!(preview.effective_stake_amount, 300);
+    assert_eq!(preview.fee_share, Decimal::ZERO);
+    assert_eq!(
+        preview.remaining_accounts,
+        vec![
+            AccountMeta::new(state.escrow(2), false),
+            AccountMeta::new(state.escrow(3), false),
+        ]
+    );
+
+    // Still ranking above every staker outside the top list.
+    let preview = state
+        .preview_state()
+        .preview_request_unstake(state.owner(0), Some(&state.stake_escrows[0]), 100)
+        .unwrap();
+    assert!(preview.in_top_list);
+    assert_eq!(preview.promoted_owner, None);
+    assert_eq!(preview.effective_stake_amount, 400);
+    assert_eq!(preview.fee_share, Decimal::new(5, 1));
+}
+
+#[test]
+fn unstaking_outside_top_list_needs_no_accounts() {
+    let state = VaultState::new(1, &[(300, true), (50, false)]);
+
+    let preview = state
+        .preview_state()
+        .preview_request_unstake(state.owner(1), Some(&state.stake_escrows[1]), 10)
+        .unwrap();
+    assert!(!preview.was_in_top_list && !preview.in_top_list);
+    assert_eq!(preview.effective_stake_amount, 300);
+    assert!(preview.remaining_accounts.is_empty());
+
+    assert_eq!(
+        state.preview_state().preview_request_unstake(
+            state.owner(1),
+            Some(&state.stake_escrows[1]),
+            51
+        ),
+        Err(StakeForFeeError::InsufficientStakeAmount)
+    );
+    assert_eq!(
+        state.preview_state().preview_request_unstake(
+            state.owner(1),
+            Some(&state.stake_escrows[0]),
+            10
+        ),
+        Err(StakeForFeeError::InvalidStakeEscrow)
+    );
+    assert_eq!(
+        state
+            .preview_state()
+            .preview_request_unstake(state.owner(1), None, 10),
+        Err(StakeForFeeError::InvalidStakeEscrow)
+    );
+}
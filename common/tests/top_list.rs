@@ -0,0 +1,114 @@
+mod utils;
+
+use borsh::BorshSerialize;
+use common::{
+    decoder::{decode_top_staker_list_state, TopStakerListState},
+    top_list::{get_top_list_entry, get_top_staker_list_entry_stake_amount, TopListEntry},
+};
+use m3m3::{StakerMetadata, TopListMetadata, TOP_LIST_METADATA_ACCOUNT_DISCM};
+use solana_sdk::pubkey::Pubkey;
+use utils::{zeroed_fee_vault, zeroed_stake_escrow};
+
+fn top_staker_list(stake_amounts: &[u64]) -> TopStakerListState {
+    TopStakerListState {
+        metadata: TopListMetadata {
+            vault: Pubkey::new_unique(),
+        },
+        stakers: stake_amounts
+            .iter()
+            .enumerate()
+            .map(|(idx, stake_amount)| StakerMetadata {
+                stake_amount: *stake_amount,
+                full_balance_index: idx as i64,
+                owner: Pubkey::new_unique(),
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn decode_only_current_length_entries() {
+    let list = top_staker_list(&[100, 200, 300]);
+
+    let mut data = TOP_LIST_METADATA_ACCOUNT_DISCM.to_vec();
+    list.metadata.serialize(&mut data).unwrap();
+    for staker in &list.stakers {
+        staker.serialize(&mut data).unwrap();
+    }
+    // Unused slot of a list sized for 4 entries.
+    data.extend_from_slice(&[0u8; 48]);
+
+    let mut fee_vault = zeroed_fee_vault();
+    fee_vault.top_staker_info.current_length = 3;
+
+    assert_eq!(
+        decode_top_staker_list_state(&fee_vault, &data).unwrap(),
+        list
+    );
+
+    fee_vault.top_staker_info.current_length = 5;
+    assert!(decode_top_staker_list_state(&fee_vault, &data).is_err());
+}
+
+#[test]
+fn entry_stake_amount_is_smallest_top_stake_plus_one() {
+    assert_eq!(
+        get_top_staker_list_entry_stake_amount(&top_staker_list(&[])),
+        1
+    );
+    assert_eq!(
+        get_top_staker_list_entry_stake_amount(&top_staker_list(&[300, 100, 200])),
+        101
+    );
+}
+
+#[test]
+fn stake_needed_to_enter_full_list() {
+    let list = top_staker_list(&[300, 100, 200]);
+
+    let mut fee_vault = zeroed_fee_vault();
+    fee_vault.top_staker_info.top_list_length = 3;
+    fee_vault.top_staker_info.current_length = 3;
+
+    let mut stake_escrow = zeroed_stake_escrow();
+    stake_escrow.stake_amount = 40;
+    stake_escrow.ongoing_total_partial_unstake_amount = 50;
+
+    assert_eq!(
+        get_top_list_entry(&fee_vault.top_staker_info, &list, &stake_escrow),
+        TopListEntry {
+            entry_stake_amount: 101,
+            stake_needed: 61,
+            stake_needed_after_cancel_unstake: 11,
+            has_free_slot: false,
+        }
+    );
+
+    stake_escrow.in_top_list = 1;
+    let entry = get_top_list_entry(&fee_vault.top_staker_info, &list, &stake_escrow);
+    assert_eq!(
+        (entry.stake_needed, entry.stake_needed_after_cancel_unstake),
+        (0, 0)
+    );
+}
+
+#[test]
+fn any_stake_enters_list_with_free_slot() {
+    let list = top_staker_list(&[300, 100]);
+
+    let mut fee_vault = zeroed_fee_vault();
+    fee_vault.top_staker_info.top_list_length = 3;
+    fee_vault.top_staker_info.current_length = 2;
+
+    let stake_escrow = zeroed_stake_escrow();
+
+    assert_eq!(
+        get_top_list_entry(&fee_vault.top_staker_info, &list, &stake_escrow),
+        TopListEntry {
+            entry_stake_amount: 1,
+            stake_needed: 1,
+            stake_needed_after_cancel_unstake: 1,
+            has_free_slot: true,
+        }
+    );
+}
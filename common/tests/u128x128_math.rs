@@ -0,0 +1,92 @@
+use common::math::{
+    q64,
+    u128x128_math::{mul_div, mul_shr, shl_div},
+    utils_math::{safe_cast, safe_mul_div_cast},
+};
+use m3m3::{Rounding, StakeForFeeError};
+
+#[test]
+fn mul_div_rounds_as_requested() {
+    assert_eq!(mul_div(10, 10, 3, Rounding::Down), Ok(33));
+    assert_eq!(mul_div(10, 10, 3, Rounding::Up), Ok(34));
+    assert_eq!(mul_div(10, 9, 3, Rounding::Up), Ok(30));
+
+    // Intermediate product exceeds u128.
+    assert_eq!(
+        mul_div(u128::MAX, u128::MAX, u128::MAX, Rounding::Down),
+        Ok(u128::MAX)
+    );
+    assert_eq!(
+        mul_div(u128::MAX, 2, 1, Rounding::Down),
+        Err(StakeForFeeError::MathOverflow)
+    );
+    assert_eq!(
+        mul_div(1, 1, 0, Rounding::Down),
+        Err(StakeForFeeError::MathOverflow)
+    );
+}
+
+#[test]
+fn shift_operations_round_as_requested() {
+    assert_eq!(mul_shr(3, 3, 2, Rounding::Down), Ok(2));
+    assert_eq!(mul_shr(3, 3, 2, Rounding::Up), Ok(3));
+    assert_eq!(mul_shr(4, 4, 2, Rounding::Up), Ok(4));
+    assert_eq!(
+        mul_shr(u128::MAX, u128::MAX, 64, Rounding::Down),
+        Err(StakeForFeeError::MathOverflow)
+    );
+
+    assert_eq!(shl_div(1, 3, 2, Rounding::Down), Ok(1));
+    assert_eq!(shl_div(1, 3, 2, Rounding::Up), Ok(2));
+    assert_eq!(
+        shl_div(1, 1, 128, Rounding::Down),
+        Err(StakeForFeeError::MathOverflow)
+    );
+    assert_eq!(
+        shl_div(1, 0, 64, Rounding::Down),
+        Err(StakeForFeeError::MathOverflow)
+    );
+}
+
+#[test]
+fn q64_helpers() {
+    let one_third_down = q64::from_ratio(1, 3, Rounding::Down).unwrap();
+    let one_third_up = q64::from_ratio(1, 3, Rounding::Up).unwrap();
+    assert_eq!(one_third_up - one_third_down, 1);
+
+    assert_eq!(q64::from_u64(7), 7 * q64::ONE);
+    assert_eq!(q64::to_u64(q64::ONE + 1, Rounding::Down), Ok(1));
+    assert_eq!(q64::to_u64(q64::ONE + 1, Rounding::Up), Ok(2));
+    assert_eq!(
+        q64::to_u64(u128::MAX, Rounding::Up),
+        Err(StakeForFeeError::TypeCastFailed)
+    );
+
+    assert_eq!(
+        q64::mul(q64::ONE + 1, q64::ONE / 2, Rounding::Down),
+        Ok(q64::ONE / 2)
+    );
+    assert_eq!(
+        q64::mul(q64::ONE + 1, q64::ONE / 2, Rounding::Up),
+        Ok(q64::ONE / 2 + 1)
+    );
+    assert_eq!(
+        q64::div(q64::ONE, q64::from_u64(4), Rounding::Down),
+        Ok(q64::ONE / 4)
+    );
+    assert_eq!(q64::mul_u64(one_third_down, 300, Rounding::Down), Ok(99));
+    assert_eq!(q64::mul_u64(one_third_down, 300, Rounding::Up), Ok(100));
+}
+
+#[test]
+fn safe_casts_fail_with_type_cast_failed() {
+    assert_eq!(safe_cast::<u8, _>(255u64), Ok(255u8));
+    assert_eq!(
+        safe_cast::<u8, _>(256u64),
+        Err(StakeForFeeError::TypeCastFailed)
+    );
+    assert_eq!(
+        safe_mul_div_cast::<u64>(u64::MAX.into(), 2, 1, Rounding::Down),
+        Err(StakeForFeeError::TypeCastFailed)
+    );
+}
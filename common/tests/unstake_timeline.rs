@@ -0,0 +1,102 @@
+mod utils;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use common::{
+    pda::derive_stake_escrow_key,
+    unstake::{
+        get_lock_duration_change, get_lock_duration_change_from_event, get_release_at,
+        get_unstake_timeline, LockDurationChange, UnstakeStatus,
+    },
+};
+use m3m3::{StakeForFeeError, Unstake, UpdateUnstakeLockDuration};
+use solana_sdk::pubkey::Pubkey;
+use utils::zeroed_fee_vault;
+
+fn unstake(stake_escrow: Pubkey, unstake_amount: u64, release_at: i64) -> (Pubkey, Unstake) {
+    (
+        Pubkey::new_unique(),
+        Unstake {
+            stake_escrow,
+            unstake_amount,
+            created_at: release_at - 86_400,
+            release_at,
+            padding: [0; 30],
+        },
+    )
+}
+
+#[test]
+fn timeline_splits_withdrawable_and_locked_unstakes() {
+    let vault = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let stake_escrow = derive_stake_escrow_key(vault, owner);
+
+    let unstakes = vec![
+        unstake(stake_escrow, 300, 2_000),
+        unstake(stake_escrow, 100, 1_000),
+        // Another owner's unstake.
+        unstake(Pubkey::new_unique(), 1_000, 500),
+        unstake(stake_escrow, 50, 1_500),
+        unstake(stake_escrow, 25, 900),
+    ];
+
+    let timeline = get_unstake_timeline(vault, owner, &unstakes, 1_000).unwrap();
+
+    let status = |idx: usize, seconds_remaining| {
+        let (key, unstake) = &unstakes[idx];
+        UnstakeStatus {
+            unstake: *key,
+            unstake_amount: unstake.unstake_amount,
+            release_at: unstake.release_at,
+            seconds_remaining,
+        }
+    };
+    // Withdrawable from `release_at` on.
+    assert_eq!(timeline.withdrawable, vec![status(4, 0), status(1, 0)]);
+    assert_eq!(timeline.locked, vec![status(3, 500), status(0, 1_000)]);
+    assert_eq!(timeline.total_withdrawable_amount, 125);
+    assert_eq!(timeline.total_pending_amount, 475);
+    assert_eq!(timeline.next_release_at, Some(1_500));
+
+    let timeline = get_unstake_timeline(vault, owner, &unstakes, 2_000).unwrap();
+    assert!(timeline.locked.is_empty());
+    assert_eq!(timeline.total_withdrawable_amount, 475);
+    assert_eq!(timeline.next_release_at, None);
+
+    let timeline = get_unstake_timeline(vault, Pubkey::new_unique(), &unstakes, 0).unwrap();
+    assert_eq!(timeline.total_pending_amount, 0);
+}
+
+#[test]
+fn lock_duration_change_only_affects_new_requests() {
+    let mut fee_vault = zeroed_fee_vault();
+    fee_vault.configuration.unstake_lock_duration = 86_400;
+
+    let change = get_lock_duration_change(&fee_vault, 3 * 86_400, 1_000).unwrap();
+    assert_eq!(
+        change,
+        LockDurationChange {
+            release_at: 87_400,
+            release_at_after_change: 260_200,
+        }
+    );
+    assert_eq!(change.delay(), 2 * 86_400);
+
+    let event = UpdateUnstakeLockDuration::try_from_slice(
+        &(Pubkey::new_unique(), 86_400u64, 3_600u64)
+            .try_to_vec()
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        get_lock_duration_change_from_event(&event, 1_000)
+            .unwrap()
+            .delay(),
+        3_600 - 86_400
+    );
+
+    assert_eq!(
+        get_release_at(u64::MAX, 0),
+        Err(StakeForFeeError::MathOverflow)
+    );
+}
@@ -0,0 +1,198 @@
+#![allow(dead_code)]
+
+pub mod top_staker_list;
+
+use common::{
+    decoder::{FullBalanceListState, TopStakerListState},
+    dynamic_amm::LockEscrow,
+    dynamic_vault::{LockedProfitTracker, Vault, VaultBumps},
+    invariant::VaultAccounts,
+    pda::derive_stake_escrow_key,
+    preview::PreviewState,
+};
+use m3m3::{
+    FeeVault, FeeVaultAccount, FullBalanceListMetadata, StakeEscrow, StakeEscrowAccount,
+    StakerBalance, StakerMetadata, TopListMetadata, Unstake, FEE_VAULT_ACCOUNT_DISCM,
+    STAKE_ESCROW_ACCOUNT_DISCM,
+};
+use solana_sdk::pubkey::Pubkey;
+
+/// A `FeeVault` with every field zeroed, to be filled in by each test.
+pub fn zeroed_fee_vault() -> FeeVault {
+    let mut data = vec![0u8; 8 + std::mem::size_of::<FeeVault>()];
+    data[..8].copy_from_slice(&FEE_VAULT_ACCOUNT_DISCM);
+    FeeVaultAccount::deserialize(&data).unwrap().0
+}
+
+/// A `StakeEscrow` with every field zeroed, to be filled in by each test.
+pub fn zeroed_stake_escrow() -> StakeEscrow {
+    let mut data = vec![0u8; 8 + std::mem::size_of::<StakeEscrow>()];
+    data[..8].copy_from_slice(&STAKE_ESCROW_ACCOUNT_DISCM);
+    StakeEscrowAccount::deserialize(&data).unwrap().0
+}
+
+/// A vault's accounts, consistent with its stake escrows.
+pub struct VaultState {
+    pub vault: Pubkey,
+    pub fee_vault: FeeVault,
+    pub top_staker_list: TopStakerListState,
+    pub full_balance_list: FullBalanceListState,
+    pub stake_escrows: Vec<StakeEscrow>,
+    pub unstakes: Vec<Unstake>,
+}
+
+impl VaultState {
+    /// Stakers at their full balance index, in the top list when flagged. The top list has
+    /// `top_list_length` entries, the free ones empty.
+    pub fn new(top_list_length: u64, stakes: &[(u64, bool)]) -> Self {
+        let vault = Pubkey::new_unique();
+        let stake_escrows: Vec<_> = stakes
+            .iter()
+            .enumerate()
+            .map(|(idx, (stake_amount, in_top_list))| {
+                let mut stake_escrow = zeroed_stake_escrow();
+                stake_escrow.owner = Pubkey::new_unique();
+                stake_escrow.vault = vault;
+                stake_escrow.full_balance_index = idx as u64;
+                stake_escrow.stake_amount = *stake_amount;
+                stake_escrow.in_top_list = (*in_top_list).into();
+                stake_escrow
+            })
+            .collect();
+        let top_stakers: Vec<_> = stake_escrows
+            .iter()
+            .filter(|stake_escrow| stake_escrow.in_top_list != 0)
+            .collect();
+
+        let mut fee_vault = zeroed_fee_vault();
+        fee_vault.metrics.total_stake_escrow_count = stake_escrows.len() as u64;
+        fee_vault.metrics.total_staked_amount = stake_escrows
+            .iter()
+            .map(|stake_escrow| stake_escrow.stake_amount)
+            .sum();
+        fee_vault.top_staker_info.top_list_length = top_list_length;
+        fee_vault.top_staker_info.current_length = top_stakers.len() as u64;
+        fee_vault.top_staker_info.effective_stake_amount = top_stakers
+            .iter()
+            .map(|stake_escrow| stake_escrow.stake_amount)
+            .sum();
+
+        let free_slots = (top_list_length as usize).saturating_sub(top_stakers.len());
+        let top_staker_list = TopStakerListState {
+            metadata: TopListMetadata { vault },
+            stakers: top_stakers
+                .iter()
+                .map(|stake_escrow| StakerMetadata {
+                    stake_amount: stake_escrow.stake_amount,
+                    full_balance_index: stake_escrow.full_balance_index as i64,
+                    owner: stake_escrow.owner,
+                })
+                .chain((0..free_slots).map(|_| StakerMetadata {
+                    stake_amount: 0,
+                    full_balance_index: -1,
+                    owner: Pubkey::default(),
+                }))
+                .collect(),
+        };
+        let full_balance_list = FullBalanceListState {
+            metadata: FullBalanceListMetadata {
+                vault,
+                length: stake_escrows.len() as u64,
+            },
+            stakers: stake_escrows
+                .iter()
+                .map(|stake_escrow| StakerBalance {
+                    balance: stake_escrow.stake_amount,
+                    owner: stake_escrow.owner,
+                    is_in_top_list: stake_escrow.in_top_list,
+                    padding: [0; 7],
+                })
+                .collect(),
+        };
+
+        Self {
+            vault,
+            fee_vault,
+            top_staker_list,
+            full_balance_list,
+            stake_escrows,
+            unstakes: vec![],
+        }
+    }
+
+    pub fn owner(&self, idx: usize) -> Pubkey {
+        self.stake_escrows[idx].owner
+    }
+
+    pub fn escrow(&self, idx: usize) -> Pubkey {
+        derive_stake_escrow_key(self.vault, self.owner(idx))
+    }
+
+    pub fn preview_state(&self) -> PreviewState<'_> {
+        PreviewState {
+            fee_vault: &self.fee_vault,
+            top_staker_list: &self.top_staker_list,
+            full_balance_list: &self.full_balance_list,
+        }
+    }
+
+    pub fn accounts(
+        &self,
+        stake_token_balance: u64,
+        quote_token_balance: u64,
+    ) -> VaultAccounts<'_> {
+        VaultAccounts {
+            fee_vault: &self.fee_vault,
+            top_staker_list: &self.top_staker_list,
+            full_balance_list: &self.full_balance_list,
+            stake_escrows: &self.stake_escrows,
+            unstakes: &self.unstakes,
+            stake_token_balance,
+            quote_token_balance,
+            is_stake_token_a: true,
+        }
+    }
+}
+
+pub fn vault(
+    total_amount: u64,
+    last_updated_locked_profit: u64,
+    last_report: u64,
+    locked_profit_degradation: u64,
+) -> Vault {
+    Vault {
+        enabled: 1,
+        bumps: VaultBumps {
+            vault_bump: 0,
+            token_vault_bump: 0,
+        },
+        total_amount,
+        token_vault: Pubkey::default(),
+        fee_vault: Pubkey::default(),
+        token_mint: Pubkey::default(),
+        lp_mint: Pubkey::default(),
+        strategies: [Pubkey::default(); 30],
+        base: Pubkey::default(),
+        admin: Pubkey::default(),
+        operator: Pubkey::default(),
+        locked_profit_tracker: LockedProfitTracker {
+            last_updated_locked_profit,
+            last_report,
+            locked_profit_degradation,
+        },
+    }
+}
+
+pub fn lock_escrow(total_locked_amount: u64, lp_per_token: u128) -> LockEscrow {
+    LockEscrow {
+        pool: Pubkey::default(),
+        owner: Pubkey::default(),
+        escrow_vault: Pubkey::default(),
+        bump: 0,
+        total_locked_amount,
+        lp_per_token,
+        unclaimed_fee_pending: 0,
+        a_fee: 0,
+        b_fee: 0,
+    }
+}
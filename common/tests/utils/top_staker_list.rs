@@ -0,0 +1,134 @@
+use common::{
+    constants::FULL_BALANCE_LIST_HARD_LIMIT,
+    decoder::{FullBalanceListState, TopStakerListState},
+};
+use m3m3::{FullBalanceListMetadata, StakerBalance, StakerMetadata, TopListMetadata};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use solana_sdk::pubkey::Pubkey;
+
+pub struct TopStakerListWithSmallestStakers {
+    pub top_staker_list: TopStakerListState,
+    pub smallest_staker_0: StakerMetadata,
+    pub smallest_staker_1: StakerMetadata,
+    pub smallest_staker_2: StakerMetadata,
+}
+
+/// Shuffled top staker list of `extra_list_length` entries on top of 3 predefined smallest
+/// stakers, where only the first `extra_staker_count` extra entries are occupied.
+pub fn setup_top_staker_list_with_predefined_smallest_stakers(
+    extra_staker_count: usize,
+    extra_list_length: usize,
+) -> TopStakerListWithSmallestStakers {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let smallest_staker_0 = StakerMetadata {
+        full_balance_index: 1,
+        stake_amount: 0,
+        owner: Pubkey::new_unique(),
+    };
+    let smallest_staker_1 = StakerMetadata {
+        full_balance_index: 0,
+        stake_amount: 0,
+        owner: Pubkey::new_unique(),
+    };
+    let smallest_staker_2 = StakerMetadata {
+        full_balance_index: 2,
+        stake_amount: 1,
+        owner: Pubkey::new_unique(),
+    };
+
+    let mut stakers = vec![
+        smallest_staker_0.clone(),
+        smallest_staker_1.clone(),
+        smallest_staker_2.clone(),
+    ];
+
+    // Above the predefined stakers, so none of the extra ones ties with them.
+    let starting_stake_amount = smallest_staker_2.stake_amount + 1;
+    let max_staking_amount = 1_000_000;
+
+    for i in 0..extra_list_length {
+        if i < extra_staker_count {
+            let full_balance_index =
+                rng.gen_range(stakers.len() as i64..=FULL_BALANCE_LIST_HARD_LIMIT as i64);
+            let stake_amount = rng.gen_range(starting_stake_amount..=max_staking_amount);
+
+            stakers.push(StakerMetadata {
+                full_balance_index,
+                stake_amount,
+                owner: Pubkey::new_unique(),
+            });
+        } else {
+            stakers.push(StakerMetadata {
+                full_balance_index: -1,
+                stake_amount: 0,
+                owner: Pubkey::default(),
+            });
+        }
+    }
+
+    stakers.shuffle(&mut rng);
+
+    TopStakerListWithSmallestStakers {
+        top_staker_list: TopStakerListState {
+            metadata: TopListMetadata {
+                vault: Pubkey::new_unique(),
+            },
+            stakers,
+        },
+        smallest_staker_0,
+        smallest_staker_1,
+        smallest_staker_2,
+    }
+}
+
+/// Shuffled full balance list padded with empty entries up to `FULL_BALANCE_LIST_HARD_LIMIT`.
+/// Top stakers always hold more than non top stakers.
+pub fn setup_full_balance_list(
+    top_staker_count: usize,
+    non_top_staker_count: usize,
+) -> FullBalanceListState {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let min_top_list_stake_amount = 1_000_000;
+    let max_top_list_stake_amount = 1_000_000_000;
+
+    let mut stakers = Vec::with_capacity(FULL_BALANCE_LIST_HARD_LIMIT as usize);
+
+    for _ in 0..top_staker_count {
+        stakers.push(StakerBalance {
+            balance: rng.gen_range(min_top_list_stake_amount..=max_top_list_stake_amount),
+            owner: Pubkey::new_unique(),
+            is_in_top_list: 1,
+            padding: [0; 7],
+        });
+    }
+
+    for _ in 0..non_top_staker_count {
+        stakers.push(StakerBalance {
+            balance: rng.gen_range(1_000..min_top_list_stake_amount),
+            owner: Pubkey::new_unique(),
+            is_in_top_list: 0,
+            padding: [0; 7],
+        });
+    }
+
+    for _ in stakers.len()..FULL_BALANCE_LIST_HARD_LIMIT as usize {
+        stakers.push(StakerBalance {
+            balance: 0,
+            owner: Pubkey::default(),
+            is_in_top_list: 0,
+            padding: [0; 7],
+        });
+    }
+
+    stakers.shuffle(&mut rng);
+
+    FullBalanceListState {
+        metadata: FullBalanceListMetadata {
+            vault: Pubkey::new_unique(),
+            length: stakers.len() as u64,
+        },
+        stakers,
+    }
+}
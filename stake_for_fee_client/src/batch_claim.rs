@@ -0,0 +1,335 @@
+use crate::{
+    client::{claim_fee_instruction, ClaimFeeAccounts},
+    compute::{
+        get_compute_unit_limit_instruction, unsigned_transaction, DEFAULT_COMPUTE_UNIT_BUFFER,
+        MAX_COMPUTE_UNITS,
+    },
+    error::{ClientError, Result},
+    portfolio::{fetch_portfolio, VaultPosition},
+    rpc::RpcBackend,
+    state::{decode, get_multiple_accounts},
+    wsol::{is_native_mint, unwrap_sol_instruction},
+};
+use common::{
+    constants::TOKEN_PROGRAM_ID,
+    decoder::{
+        decode_full_balance_list_state, decode_top_staker_list_state, FullBalanceListState,
+        TopStakerListState,
+    },
+    pda::derive_associated_token_key,
+};
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    compute_budget::ComputeBudgetInstruction, hash::Hash, instruction::Instruction,
+    packet::PACKET_DATA_SIZE, pubkey::Pubkey, transaction::VersionedTransaction,
+};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use std::collections::HashSet;
+
+/// Compute units budgeted per `claim_fee` when packing, claiming from the lock escrow included.
+pub const CLAIM_FEE_COMPUTE_UNITS: u32 = 250_000;
+/// Compute units budgeted per idempotent quote token ATA creation when packing.
+pub const CREATE_ATA_COMPUTE_UNITS: u32 = 30_000;
+/// Compute units budgeted for closing the wSOL ATA when packing.
+pub const UNWRAP_SOL_COMPUTE_UNITS: u32 = 5_000;
+
+#[derive(Clone, Debug)]
+pub struct ClaimPlanConfig {
+    /// Existing tables holding vault accounts, so more claims fit in a transaction. The planner
+    /// only compiles against them: creating and extending tables is left to the caller.
+    pub lookup_tables: Vec<AddressLookupTableAccount>,
+    pub compute_units_per_claim: u32,
+    /// Close the wSOL ATA after claiming SOL, see [`crate::StakeForFee::unwrap_sol`].
+    pub unwrap_sol: bool,
+}
+
+impl Default for ClaimPlanConfig {
+    fn default() -> Self {
+        Self {
+            lookup_tables: vec![],
+            compute_units_per_claim: CLAIM_FEE_COMPUTE_UNITS,
+            unwrap_sol: true,
+        }
+    }
+}
+
+/// Fees one `claim_fee` is expected to pay out at the time of planning.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultClaim {
+    pub vault: Pubkey,
+    pub stake_escrow: Pubkey,
+    pub quote_mint: Pubkey,
+    pub expected_quote_fee: u64,
+    /// `max_fee` of the claim, the expected quote fee, so the claim pays out the planned amount
+    /// and fees accruing until it lands stay pending.
+    pub max_fee: u64,
+    /// Stake token fee restaked by the claim.
+    pub expected_restaked_fee: u64,
+}
+
+/// Unsigned transaction claiming from several vaults.
+#[derive(Clone, Debug)]
+pub struct ClaimBatch {
+    pub transaction: VersionedTransaction,
+    pub claims: Vec<VaultClaim>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ClaimPlan {
+    pub owner: Pubkey,
+    pub current_time: i64,
+    pub batches: Vec<ClaimBatch>,
+}
+
+impl ClaimPlan {
+    pub fn claims(&self) -> impl Iterator<Item = &VaultClaim> {
+        self.batches.iter().flat_map(|batch| &batch.claims)
+    }
+
+    /// Quote fee of `quote_mint` expected over every vault.
+    pub fn expected_quote_fee(&self, quote_mint: Pubkey) -> u64 {
+        self.claims()
+            .filter(|claim| claim.quote_mint == quote_mint)
+            .map(|claim| claim.expected_quote_fee)
+            .sum()
+    }
+}
+
+struct PlannedClaim {
+    position: VaultPosition,
+    top_staker_list: TopStakerListState,
+    full_balance_list: FullBalanceListState,
+}
+
+/// Plan claiming the fees of every vault `owner` has a pending quote fee in, packing the
+/// claims into as few transactions as the transaction size and the compute budget of the
+/// claims, ATA creations and wSOL close allow. Vaults are claimed in the order of the
+/// portfolio.
+pub async fn plan_batch_claim(
+    rpc: &impl RpcBackend,
+    owner: Pubkey,
+    config: &ClaimPlanConfig,
+) -> Result<ClaimPlan> {
+    let portfolio = fetch_portfolio(rpc, owner).await?;
+    let positions: Vec<VaultPosition> = portfolio
+        .positions
+        .into_iter()
+        .filter(|position| position.pending_quote_fee() > 0)
+        .collect();
+
+    let mut quote_mints = vec![];
+    for position in &positions {
+        if !quote_mints.contains(&position.fee_vault.quote_mint) {
+            quote_mints.push(position.fee_vault.quote_mint);
+        }
+    }
+    let mut keys: Vec<Pubkey> = positions
+        .iter()
+        .flat_map(|position| {
+            [
+                position.fee_vault.top_staker_list,
+                position.fee_vault.full_balance_list,
+            ]
+        })
+        .collect();
+    keys.extend(
+        quote_mints
+            .iter()
+            .map(|mint| derive_associated_token_key(owner, *mint)),
+    );
+    let accounts = get_multiple_accounts(rpc, &keys).await?;
+
+    let mut claims = Vec::with_capacity(positions.len());
+    for (idx, position) in positions.into_iter().enumerate() {
+        let top_staker_list = decode(keys[2 * idx], &accounts[2 * idx], |data| {
+            decode_top_staker_list_state(&position.fee_vault, data)
+        })?;
+        let full_balance_list = decode(
+            keys[2 * idx + 1],
+            &accounts[2 * idx + 1],
+            decode_full_balance_list_state,
+        )?;
+        claims.push(PlannedClaim {
+            position,
+            top_staker_list,
+            full_balance_list,
+        });
+    }
+    let missing_atas: HashSet<Pubkey> = quote_mints
+        .iter()
+        .zip(&accounts[2 * claims.len()..])
+        .filter(|(_, account)| account.is_none())
+        .map(|(mint, _)| *mint)
+        .collect();
+
+    let planner = Planner {
+        owner,
+        config,
+        missing_atas,
+    };
+    let mut groups: Vec<Vec<&PlannedClaim>> = vec![];
+    let mut group = vec![];
+    for claim in &claims {
+        group.push(claim);
+        if planner.fits(&group)? {
+            continue;
+        }
+        group.pop();
+        if !group.is_empty() {
+            groups.push(std::mem::replace(&mut group, vec![claim]));
+            if planner.fits(&group)? {
+                continue;
+            }
+        }
+        return Err(ClientError::TransactionTooLarge(
+            planner.size(&planner.instructions(&[claim])?)?,
+        ));
+    }
+    if !group.is_empty() {
+        groups.push(group);
+    }
+
+    let blockhash = rpc.get_latest_blockhash().await?;
+    let mut batches = Vec::with_capacity(groups.len());
+    for group in groups {
+        let mut instructions = planner.instructions(&group)?;
+        let compute_unit_limit = get_compute_unit_limit_instruction(
+            rpc,
+            &instructions,
+            owner,
+            &config.lookup_tables,
+            DEFAULT_COMPUTE_UNIT_BUFFER,
+        )
+        .await;
+        instructions.insert(0, compute_unit_limit);
+
+        batches.push(ClaimBatch {
+            transaction: unsigned_transaction(
+                &instructions,
+                owner,
+                &config.lookup_tables,
+                blockhash,
+            )?,
+            claims: group
+                .iter()
+                .map(|claim| {
+                    let position = &claim.position;
+                    VaultClaim {
+                        vault: position.vault,
+                        stake_escrow: position.stake_escrow,
+                        quote_mint: position.fee_vault.quote_mint,
+                        expected_quote_fee: position.pending_quote_fee(),
+                        max_fee: position.pending_quote_fee(),
+                        expected_restaked_fee: position.pending_stake_fee(),
+                    }
+                })
+                .collect(),
+        });
+    }
+
+    Ok(ClaimPlan {
+        owner,
+        current_time: portfolio.current_time,
+        batches,
+    })
+}
+
+struct Planner<'a> {
+    owner: Pubkey,
+    config: &'a ClaimPlanConfig,
+    /// Quote mints the owner has no ATA of.
+    missing_atas: HashSet<Pubkey>,
+}
+
+impl Planner<'_> {
+    /// Instructions claiming `claims`, without the compute unit limit. Each transaction creates
+    /// the ATAs it needs, so the batches can land in any order.
+    fn instructions(&self, claims: &[&PlannedClaim]) -> Result<Vec<Instruction>> {
+        let mut quote_mints = vec![];
+        for claim in claims {
+            if !quote_mints.contains(&claim.position.fee_vault.quote_mint) {
+                quote_mints.push(claim.position.fee_vault.quote_mint);
+            }
+        }
+
+        let unwraps_sol = |mint: &Pubkey| self.config.unwrap_sol && is_native_mint(mint);
+        let mut instructions: Vec<Instruction> = quote_mints
+            .iter()
+            .filter(|mint| self.missing_atas.contains(mint) || unwraps_sol(mint))
+            .map(|mint| {
+                create_associated_token_account_idempotent(
+                    &self.owner,
+                    &self.owner,
+                    mint,
+                    &TOKEN_PROGRAM_ID,
+                )
+            })
+            .collect();
+
+        for claim in claims {
+            let position = &claim.position;
+            instructions.push(claim_fee_instruction(
+                &ClaimFeeAccounts {
+                    vault: position.vault,
+                    fee_vault: &position.fee_vault,
+                    pool: &position.pool,
+                    lock_escrow: &position.lock_escrow,
+                    a_vault: &position.a_vault,
+                    b_vault: &position.b_vault,
+                    top_staker_list: &claim.top_staker_list,
+                    full_balance_list: &claim.full_balance_list,
+                },
+                self.owner,
+                derive_associated_token_key(self.owner, position.fee_vault.quote_mint),
+                position.pending_quote_fee(),
+            )?);
+        }
+
+        if quote_mints.iter().any(unwraps_sol) {
+            instructions.push(unwrap_sol_instruction(self.owner)?);
+        }
+        Ok(instructions)
+    }
+
+    /// Serialized size of the transaction of `instructions`.
+    fn size(&self, instructions: &[Instruction]) -> Result<usize> {
+        let mut sized_instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            MAX_COMPUTE_UNITS,
+        )];
+        sized_instructions.extend_from_slice(instructions);
+        let transaction = unsigned_transaction(
+            &sized_instructions,
+            self.owner,
+            &self.config.lookup_tables,
+            Hash::default(),
+        )?;
+
+        // One byte encodes the signature count.
+        Ok(1 + transaction.signatures.len() * 64 + transaction.message.serialize().len())
+    }
+
+    /// Compute units budgeted for `instructions`.
+    fn compute_units(&self, instructions: &[Instruction]) -> u64 {
+        instructions
+            .iter()
+            .map(|instruction| {
+                let units = if instruction.program_id == m3m3::ID {
+                    self.config.compute_units_per_claim
+                } else if instruction.program_id == spl_associated_token_account::ID {
+                    CREATE_ATA_COMPUTE_UNITS
+                } else {
+                    UNWRAP_SOL_COMPUTE_UNITS
+                };
+                u64::from(units)
+            })
+            .sum()
+    }
+
+    fn fits(&self, claims: &[&PlannedClaim]) -> Result<bool> {
+        let instructions = self.instructions(claims)?;
+        Ok(
+            self.compute_units(&instructions) <= u64::from(MAX_COMPUTE_UNITS)
+                && self.size(&instructions)? <= PACKET_DATA_SIZE,
+        )
+    }
+}
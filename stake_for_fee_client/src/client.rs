@@ -0,0 +1,568 @@
+use crate::{
+    error::{ClientError, Result},
+    rpc::RpcBackend,
+    state::{get_multiple_accounts, AccountStates},
+    wsol::{is_native_mint, unwrap_sol_instruction, wrap_sol_instructions},
+};
+use common::{
+    constants::{DYNAMIC_AMM_PROGRAM_ID, DYNAMIC_VAULT_PROGRAM_ID, TOKEN_PROGRAM_ID},
+    decoder::{FullBalanceListState, TopStakerListState},
+    dynamic_amm::{LockEscrow, Pool},
+    dynamic_vault::Vault,
+    pda::{
+        derive_associated_token_key, derive_full_balance_list_key, derive_m3m3_event_authority_key,
+        derive_m3m3_vault_key, derive_stake_escrow_key, derive_top_staker_list_key,
+    },
+    top_list::{find_replaceable_top_stakers, find_smallest_stake_escrow_in_full_balance_list},
+};
+use m3m3::{
+    cancel_unstake_ix, claim_fee_ix, initialize_stake_escrow_ix, request_unstake_ix, stake_ix,
+    withdraw_ix, CancelUnstakeKeys, ClaimFeeIxArgs, ClaimFeeKeys, FeeVault,
+    InitializeStakeEscrowKeys, RequestUnstakeIxArgs, RequestUnstakeKeys, StakeEscrow,
+    StakeEscrowAccount, StakeIxArgs, StakeKeys, WithdrawKeys,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Keypair,
+    system_program,
+    transaction::VersionedTransaction,
+};
+use spl_associated_token_account::instruction::{
+    create_associated_token_account, create_associated_token_account_idempotent,
+};
+use std::sync::Arc;
+
+/// Most top stakers `stake` can pass as replaceable.
+pub const MAX_REPLACEABLE_TOP_STAKER_COUNT: usize = 2;
+/// Top stakers passed to `cancel_unstake`, and candidates passed to `request_unstake`.
+const LOOKUP_STAKER_COUNT: usize = 3;
+
+/// Client of one fee vault. Same as the TS `StakeForFee` class.
+pub struct StakeForFee<R: RpcBackend = RpcClient> {
+    rpc: Arc<R>,
+    pub vault: Pubkey,
+    pub account_states: AccountStates,
+    /// When the stake or quote token is SOL, wrap staked SOL and close the wSOL ATA after
+    /// `stake`, `claim_fee` and `withdraw`, so the owner deals in SOL. Clear to keep wSOL.
+    pub unwrap_sol: bool,
+}
+
+impl<R: RpcBackend> StakeForFee<R> {
+    /// Client of the fee vault of `pool`.
+    pub async fn create(rpc: Arc<R>, pool: Pubkey) -> Result<Self> {
+        let vault = derive_m3m3_vault_key(pool);
+        let account_states = AccountStates::fetch(rpc.as_ref(), vault, Some(pool)).await?;
+
+        Ok(Self {
+            rpc,
+            vault,
+            account_states,
+            unwrap_sol: true,
+        })
+    }
+
+    pub async fn from_vault(rpc: Arc<R>, vault: Pubkey) -> Result<Self> {
+        let account_states = AccountStates::fetch(rpc.as_ref(), vault, None).await?;
+
+        Ok(Self {
+            rpc,
+            vault,
+            account_states,
+            unwrap_sol: true,
+        })
+    }
+
+    pub fn rpc(&self) -> &R {
+        &self.rpc
+    }
+
+    pub async fn refresh_states(&mut self) -> Result<()> {
+        self.account_states = AccountStates::fetch(
+            self.rpc.as_ref(),
+            self.vault,
+            Some(self.account_states.fee_vault.pool),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Stake exactly `amount` in one transaction, as the TS `stake`: the stake escrow and stake
+    /// token ATA are created if missing, the smallest stake escrow and up to
+    /// `replaceable_top_staker_count` replaceable top stakers are passed, and the compute unit
+    /// limit is sized by simulation. SOL is wrapped first when it is the stake token.
+    pub async fn stake(
+        &self,
+        amount: u64,
+        owner: Pubkey,
+        replaceable_top_staker_count: usize,
+    ) -> Result<VersionedTransaction> {
+        let (stake_escrow, has_user_stake_token) = self.fetch_stake_accounts(owner).await?;
+        self.stake_transaction(
+            amount,
+            owner,
+            stake_escrow.is_some(),
+            has_user_stake_token,
+            replaceable_top_staker_count,
+        )
+        .await
+    }
+
+    /// [`StakeForFee::prepare_unstake`] of `amount` into the new `unstake` account, which signs
+    /// along with the owner.
+    pub async fn unstake(
+        &self,
+        amount: u64,
+        unstake: &Keypair,
+        owner: Pubkey,
+    ) -> Result<VersionedTransaction> {
+        let prepared = self
+            .prepare_unstake(amount, owner, Some(unstake.insecure_clone()))
+            .await?;
+        Ok(prepared.transaction)
+    }
+
+    /// Claim up to `max_fee` quote token, unwrapped when it is SOL. The stake token fee is
+    /// restaked.
+    pub async fn claim_fee(&self, owner: Pubkey, max_fee: u64) -> Result<VersionedTransaction> {
+        let states = &self.account_states;
+        let fee_vault = &states.fee_vault;
+        let quote_mint = if states.is_stake_token_a() {
+            states.pool.token_b_mint
+        } else {
+            states.pool.token_a_mint
+        };
+
+        let (user_quote_token, mut instructions) = self
+            .get_or_create_ata_instruction(quote_mint, owner)
+            .await?;
+
+        let instruction = claim_fee_instruction(
+            &ClaimFeeAccounts {
+                vault: self.vault,
+                fee_vault,
+                pool: &states.pool,
+                lock_escrow: &states.lock_escrow,
+                a_vault: &states.a_vault,
+                b_vault: &states.b_vault,
+                top_staker_list: &states.top_staker_list,
+                full_balance_list: &states.full_balance_list,
+            },
+            owner,
+            user_quote_token,
+            max_fee,
+        )?;
+        instructions.push(instruction);
+        if self.unwraps_sol(&quote_mint) {
+            instructions.push(unwrap_sol_instruction(owner)?);
+        }
+
+        self.sized_transaction(instructions, owner).await
+    }
+
+    /// Withdraw a released `unstake` to the owner's stake token account, unwrapped when it is
+    /// SOL.
+    pub async fn withdraw(&self, unstake: Pubkey, owner: Pubkey) -> Result<VersionedTransaction> {
+        let stake_mint = self.account_states.fee_vault.stake_mint;
+        let (user_stake_token, mut instructions) = self
+            .get_or_create_ata_instruction(stake_mint, owner)
+            .await?;
+        instructions.push(self.withdraw_instruction(unstake, owner, user_stake_token)?);
+        if self.unwraps_sol(&stake_mint) {
+            instructions.push(unwrap_sol_instruction(owner)?);
+        }
+
+        self.sized_transaction(instructions, owner).await
+    }
+
+    pub async fn cancel_unstake(
+        &self,
+        unstake: Pubkey,
+        owner: Pubkey,
+    ) -> Result<VersionedTransaction> {
+        let stake_escrow = self.required_stake_escrow(owner).await?;
+        let instruction = self.cancel_unstake_instruction(unstake, owner, &stake_escrow)?;
+
+        self.sized_transaction(vec![instruction], owner).await
+    }
+
+    pub async fn fetch_stake_escrow(&self, owner: Pubkey) -> Result<Option<StakeEscrow>> {
+        let key = derive_stake_escrow_key(self.vault, owner);
+        let account = get_multiple_accounts(self.rpc.as_ref(), &[key])
+            .await?
+            .remove(0);
+
+        account
+            .map(|account| {
+                StakeEscrowAccount::deserialize(&account.data)
+                    .map(|account| account.0)
+                    .map_err(|_| ClientError::InvalidAccountData(key))
+            })
+            .transpose()
+    }
+
+    /// The owner's stake escrow, and whether the owner's stake token ATA exists.
+    pub(crate) async fn fetch_stake_accounts(
+        &self,
+        owner: Pubkey,
+    ) -> Result<(Option<StakeEscrow>, bool)> {
+        let stake_escrow_key = derive_stake_escrow_key(self.vault, owner);
+        let user_stake_token =
+            derive_associated_token_key(owner, self.account_states.fee_vault.stake_mint);
+        let accounts =
+            get_multiple_accounts(self.rpc.as_ref(), &[stake_escrow_key, user_stake_token]).await?;
+        let stake_escrow = accounts[0]
+            .as_ref()
+            .map(|account| {
+                StakeEscrowAccount::deserialize(&account.data)
+                    .map(|account| account.0)
+                    .map_err(|_| ClientError::InvalidAccountData(stake_escrow_key))
+            })
+            .transpose()?;
+
+        Ok((stake_escrow, accounts[1].is_some()))
+    }
+
+    /// Transaction of [`StakeForFee::stake`], given which of the owner's accounts exist.
+    pub(crate) async fn stake_transaction(
+        &self,
+        amount: u64,
+        owner: Pubkey,
+        has_stake_escrow: bool,
+        has_user_stake_token: bool,
+        replaceable_top_staker_count: usize,
+    ) -> Result<VersionedTransaction> {
+        if replaceable_top_staker_count > MAX_REPLACEABLE_TOP_STAKER_COUNT {
+            return Err(ClientError::InvalidReplaceableTopStakerCount(
+                replaceable_top_staker_count,
+            ));
+        }
+
+        let stake_mint = self.account_states.fee_vault.stake_mint;
+        let unwrap_sol = self.unwraps_sol(&stake_mint);
+        let mut instructions = vec![];
+        if unwrap_sol {
+            instructions.extend(wrap_sol_instructions(owner, amount)?);
+        } else if !has_user_stake_token {
+            instructions.push(create_associated_token_account_idempotent(
+                &owner,
+                &owner,
+                &stake_mint,
+                &TOKEN_PROGRAM_ID,
+            ));
+        }
+        if !has_stake_escrow {
+            instructions.push(self.initialize_stake_escrow_instruction(owner)?);
+        }
+        instructions.push(self.stake_instruction(
+            owner,
+            amount,
+            self.smallest_stake_escrow(owner),
+            writable_accounts(find_replaceable_top_stakers(
+                replaceable_top_staker_count,
+                &self.account_states.top_staker_list,
+            )),
+        )?);
+        if unwrap_sol {
+            instructions.push(unwrap_sol_instruction(owner)?);
+        }
+
+        self.sized_transaction(instructions, owner).await
+    }
+
+    pub(crate) async fn required_stake_escrow(&self, owner: Pubkey) -> Result<StakeEscrow> {
+        self.fetch_stake_escrow(owner)
+            .await?
+            .ok_or(ClientError::AccountNotFound(derive_stake_escrow_key(
+                self.vault, owner,
+            )))
+    }
+
+    pub(crate) fn initialize_stake_escrow_instruction(&self, owner: Pubkey) -> Result<Instruction> {
+        Ok(initialize_stake_escrow_ix(InitializeStakeEscrowKeys {
+            vault: self.vault,
+            escrow: derive_stake_escrow_key(self.vault, owner),
+            full_balance_list: derive_full_balance_list_key(self.vault),
+            top_staker_list: derive_top_staker_list_key(self.vault),
+            owner,
+            payer: owner,
+            system_program: system_program::ID,
+            event_authority: derive_m3m3_event_authority_key(),
+            program: m3m3::ID,
+        })?)
+    }
+
+    /// `stake` of exactly `amount` from the owner's stake token ATA.
+    pub(crate) fn stake_instruction(
+        &self,
+        owner: Pubkey,
+        amount: u64,
+        smallest_stake_escrow: Pubkey,
+        remaining_accounts: Vec<AccountMeta>,
+    ) -> Result<Instruction> {
+        let states = &self.account_states;
+        let fee_vault = &states.fee_vault;
+        let mut instruction = stake_ix(
+            StakeKeys {
+                vault: self.vault,
+                stake_token_vault: fee_vault.stake_token_vault,
+                quote_token_vault: fee_vault.quote_token_vault,
+                top_staker_list: fee_vault.top_staker_list,
+                full_balance_list: fee_vault.full_balance_list,
+                stake_escrow: derive_stake_escrow_key(self.vault, owner),
+                smallest_stake_escrow,
+                user_stake_token: derive_associated_token_key(owner, fee_vault.stake_mint),
+                owner,
+                pool: fee_vault.pool,
+                lp_mint: states.pool.lp_mint,
+                lock_escrow: fee_vault.lock_escrow,
+                escrow_vault: states.lock_escrow.escrow_vault,
+                a_token_vault: states.a_vault.token_vault,
+                b_token_vault: states.b_vault.token_vault,
+                a_vault: states.pool.a_vault,
+                b_vault: states.pool.b_vault,
+                a_vault_lp: states.pool.a_vault_lp,
+                b_vault_lp: states.pool.b_vault_lp,
+                a_vault_lp_mint: states.a_vault.lp_mint,
+                b_vault_lp_mint: states.b_vault.lp_mint,
+                amm_program: DYNAMIC_AMM_PROGRAM_ID,
+                vault_program: DYNAMIC_VAULT_PROGRAM_ID,
+                token_program: TOKEN_PROGRAM_ID,
+                event_authority: derive_m3m3_event_authority_key(),
+                program: m3m3::ID,
+            },
+            StakeIxArgs { amount },
+        )?;
+        instruction.accounts.extend(remaining_accounts);
+        Ok(instruction)
+    }
+
+    /// `request_unstake` of `amount` into the new `unstake` account.
+    pub(crate) fn request_unstake_instruction(
+        &self,
+        amount: u64,
+        unstake: Pubkey,
+        owner: Pubkey,
+        remaining_accounts: Vec<AccountMeta>,
+    ) -> Result<Instruction> {
+        let states = &self.account_states;
+        let fee_vault = &states.fee_vault;
+        let mut instruction = request_unstake_ix(
+            RequestUnstakeKeys {
+                unstake,
+                vault: self.vault,
+                top_staker_list: fee_vault.top_staker_list,
+                full_balance_list: fee_vault.full_balance_list,
+                stake_escrow: derive_stake_escrow_key(self.vault, owner),
+                stake_token_vault: fee_vault.stake_token_vault,
+                quote_token_vault: fee_vault.quote_token_vault,
+                owner,
+                pool: fee_vault.pool,
+                lp_mint: states.pool.lp_mint,
+                lock_escrow: fee_vault.lock_escrow,
+                escrow_vault: states.lock_escrow.escrow_vault,
+                a_token_vault: states.a_vault.token_vault,
+                b_token_vault: states.b_vault.token_vault,
+                a_vault: states.pool.a_vault,
+                b_vault: states.pool.b_vault,
+                a_vault_lp: states.pool.a_vault_lp,
+                b_vault_lp: states.pool.b_vault_lp,
+                a_vault_lp_mint: states.a_vault.lp_mint,
+                b_vault_lp_mint: states.b_vault.lp_mint,
+                amm_program: DYNAMIC_AMM_PROGRAM_ID,
+                vault_program: DYNAMIC_VAULT_PROGRAM_ID,
+                token_program: TOKEN_PROGRAM_ID,
+                system_program: system_program::ID,
+                event_authority: derive_m3m3_event_authority_key(),
+                program: m3m3::ID,
+            },
+            RequestUnstakeIxArgs {
+                unstake_amount: amount,
+            },
+        )?;
+        instruction.accounts.extend(remaining_accounts);
+        Ok(instruction)
+    }
+
+    pub(crate) fn withdraw_instruction(
+        &self,
+        unstake: Pubkey,
+        owner: Pubkey,
+        user_stake_token: Pubkey,
+    ) -> Result<Instruction> {
+        let fee_vault = &self.account_states.fee_vault;
+        Ok(withdraw_ix(WithdrawKeys {
+            unstake,
+            stake_escrow: derive_stake_escrow_key(self.vault, owner),
+            stake_token_vault: fee_vault.stake_token_vault,
+            vault: self.vault,
+            user_stake_token,
+            owner,
+            token_program: TOKEN_PROGRAM_ID,
+            event_authority: derive_m3m3_event_authority_key(),
+            program: m3m3::ID,
+        })?)
+    }
+
+    /// `cancel_unstake`, passing replaceable top stakers when `stake_escrow` is outside the top
+    /// list.
+    pub(crate) fn cancel_unstake_instruction(
+        &self,
+        unstake: Pubkey,
+        owner: Pubkey,
+        stake_escrow: &StakeEscrow,
+    ) -> Result<Instruction> {
+        let states = &self.account_states;
+        let fee_vault = &states.fee_vault;
+        let mut instruction = cancel_unstake_ix(CancelUnstakeKeys {
+            unstake,
+            stake_escrow: derive_stake_escrow_key(self.vault, owner),
+            smallest_stake_escrow: self.smallest_stake_escrow(owner),
+            top_staker_list: fee_vault.top_staker_list,
+            full_balance_list: fee_vault.full_balance_list,
+            vault: self.vault,
+            stake_token_vault: fee_vault.stake_token_vault,
+            quote_token_vault: fee_vault.quote_token_vault,
+            owner,
+            pool: fee_vault.pool,
+            lp_mint: states.pool.lp_mint,
+            lock_escrow: fee_vault.lock_escrow,
+            escrow_vault: states.lock_escrow.escrow_vault,
+            a_token_vault: states.a_vault.token_vault,
+            b_token_vault: states.b_vault.token_vault,
+            a_vault: states.pool.a_vault,
+            b_vault: states.pool.b_vault,
+            a_vault_lp: states.pool.a_vault_lp,
+            b_vault_lp: states.pool.b_vault_lp,
+            a_vault_lp_mint: states.a_vault.lp_mint,
+            b_vault_lp_mint: states.b_vault.lp_mint,
+            amm_program: DYNAMIC_AMM_PROGRAM_ID,
+            vault_program: DYNAMIC_VAULT_PROGRAM_ID,
+            token_program: TOKEN_PROGRAM_ID,
+            event_authority: derive_m3m3_event_authority_key(),
+            program: m3m3::ID,
+        })?;
+        if stake_escrow.in_top_list == 0 {
+            push_remaining_accounts(
+                &mut instruction,
+                find_replaceable_top_stakers(LOOKUP_STAKER_COUNT, &states.top_staker_list),
+            );
+        }
+        Ok(instruction)
+    }
+
+    /// Whether SOL of `mint` is wrapped and unwrapped around the instructions.
+    pub(crate) fn unwraps_sol(&self, mint: &Pubkey) -> bool {
+        self.unwrap_sol && is_native_mint(mint)
+    }
+
+    /// `smallest_stake_escrow` account, the program ID standing for none.
+    fn smallest_stake_escrow(&self, owner: Pubkey) -> Pubkey {
+        find_smallest_stake_escrow_in_full_balance_list(
+            owner,
+            &self.account_states.full_balance_list,
+        )
+        .unwrap_or(m3m3::ID)
+    }
+
+    /// The owner's associated token account of `mint`, and the instruction creating it if it
+    /// does not exist.
+    async fn get_or_create_ata_instruction(
+        &self,
+        mint: Pubkey,
+        owner: Pubkey,
+    ) -> Result<(Pubkey, Vec<Instruction>)> {
+        let ata = derive_associated_token_key(owner, mint);
+        let account = get_multiple_accounts(self.rpc.as_ref(), &[ata])
+            .await?
+            .remove(0);
+
+        let instructions = match account {
+            Some(_) => vec![],
+            None => vec![create_associated_token_account(
+                &owner,
+                &owner,
+                &mint,
+                &TOKEN_PROGRAM_ID,
+            )],
+        };
+
+        Ok((ata, instructions))
+    }
+}
+
+/// Accounts of one fee vault `claim_fee` reads.
+pub(crate) struct ClaimFeeAccounts<'a> {
+    pub vault: Pubkey,
+    pub fee_vault: &'a FeeVault,
+    pub pool: &'a Pool,
+    pub lock_escrow: &'a LockEscrow,
+    pub a_vault: &'a Vault,
+    pub b_vault: &'a Vault,
+    pub top_staker_list: &'a TopStakerListState,
+    pub full_balance_list: &'a FullBalanceListState,
+}
+
+/// `claim_fee` of up to `max_fee` quote token, passing replaceable top stakers.
+pub(crate) fn claim_fee_instruction(
+    accounts: &ClaimFeeAccounts,
+    owner: Pubkey,
+    user_quote_token: Pubkey,
+    max_fee: u64,
+) -> Result<Instruction> {
+    let fee_vault = accounts.fee_vault;
+    let mut instruction = claim_fee_ix(
+        ClaimFeeKeys {
+            vault: accounts.vault,
+            top_staker_list: fee_vault.top_staker_list,
+            full_balance_list: fee_vault.full_balance_list,
+            stake_escrow: derive_stake_escrow_key(accounts.vault, owner),
+            smallest_stake_escrow: find_smallest_stake_escrow_in_full_balance_list(
+                owner,
+                accounts.full_balance_list,
+            )
+            .unwrap_or(m3m3::ID),
+            user_quote_token,
+            stake_token_vault: fee_vault.stake_token_vault,
+            quote_token_vault: fee_vault.quote_token_vault,
+            owner,
+            pool: fee_vault.pool,
+            lp_mint: accounts.pool.lp_mint,
+            lock_escrow: fee_vault.lock_escrow,
+            escrow_vault: accounts.lock_escrow.escrow_vault,
+            a_token_vault: accounts.a_vault.token_vault,
+            b_token_vault: accounts.b_vault.token_vault,
+            a_vault: accounts.pool.a_vault,
+            b_vault: accounts.pool.b_vault,
+            a_vault_lp: accounts.pool.a_vault_lp,
+            b_vault_lp: accounts.pool.b_vault_lp,
+            a_vault_lp_mint: accounts.a_vault.lp_mint,
+            b_vault_lp_mint: accounts.b_vault.lp_mint,
+            amm_program: DYNAMIC_AMM_PROGRAM_ID,
+            vault_program: DYNAMIC_VAULT_PROGRAM_ID,
+            token_program: TOKEN_PROGRAM_ID,
+            event_authority: derive_m3m3_event_authority_key(),
+            program: m3m3::ID,
+        },
+        ClaimFeeIxArgs { max_fee },
+    )?;
+    push_remaining_accounts(
+        &mut instruction,
+        find_replaceable_top_stakers(MAX_REPLACEABLE_TOP_STAKER_COUNT, accounts.top_staker_list),
+    );
+    Ok(instruction)
+}
+
+fn push_remaining_accounts(instruction: &mut Instruction, stake_escrows: Vec<Pubkey>) {
+    instruction
+        .accounts
+        .extend(writable_accounts(stake_escrows));
+}
+
+fn writable_accounts(stake_escrows: Vec<Pubkey>) -> Vec<AccountMeta> {
+    stake_escrows
+        .into_iter()
+        .map(|stake_escrow| AccountMeta::new(stake_escrow, false))
+        .collect()
+}
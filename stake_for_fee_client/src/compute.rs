@@ -0,0 +1,95 @@
+use crate::{
+    error::{ClientError, Result},
+    rpc::RpcBackend,
+};
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+
+/// Most compute units a transaction can request, used to simulate.
+pub const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+/// Share of the simulated compute units added on top, as in the TS client.
+pub const DEFAULT_COMPUTE_UNIT_BUFFER: f64 = 0.1;
+
+/// Compute units consumed by `instructions` in a simulation, `None` if the RPC does not report
+/// them. Same as the TS `getSimulationComputeUnits`.
+pub async fn get_simulation_compute_units(
+    rpc: &impl RpcBackend,
+    instructions: &[Instruction],
+    payer: Pubkey,
+    lookup_tables: &[AddressLookupTableAccount],
+) -> Result<Option<u64>> {
+    let mut simulated_instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+        MAX_COMPUTE_UNITS,
+    )];
+    simulated_instructions.extend_from_slice(instructions);
+    // The blockhash is replaced by the simulation.
+    let transaction = unsigned_transaction(
+        &simulated_instructions,
+        payer,
+        lookup_tables,
+        Hash::default(),
+    )?;
+
+    let result = rpc.simulate_transaction(&transaction).await?;
+    if let Some(err) = result.err {
+        return Err(ClientError::SimulationFailed {
+            err,
+            logs: result.logs.unwrap_or_default(),
+        });
+    }
+    Ok(result.units_consumed)
+}
+
+/// `SetComputeUnitLimit` for the simulated usage of `instructions` plus `buffer` of it, capped
+/// at [`MAX_COMPUTE_UNITS`]. Like the TS `getEstimatedComputeUnitIxWithBuffer`, the limit falls
+/// back to the maximum when the simulation fails.
+pub async fn get_compute_unit_limit_instruction(
+    rpc: &impl RpcBackend,
+    instructions: &[Instruction],
+    payer: Pubkey,
+    lookup_tables: &[AddressLookupTableAccount],
+    buffer: f64,
+) -> Instruction {
+    let units = match get_simulation_compute_units(rpc, instructions, payer, lookup_tables).await {
+        Ok(Some(units)) => {
+            // In basis points, so a round buffer gives a round limit.
+            let buffer_bps = (buffer.clamp(0.0, 1.0) * 10_000.0).round() as u64;
+            let units = units.saturating_mul(10_000 + buffer_bps).div_ceil(10_000);
+            units.min(MAX_COMPUTE_UNITS.into()) as u32
+        }
+        _ => MAX_COMPUTE_UNITS,
+    };
+
+    ComputeBudgetInstruction::set_compute_unit_limit(units)
+}
+
+/// V0 transaction with default signatures to be replaced by signing.
+pub fn unsigned_transaction(
+    instructions: &[Instruction],
+    payer: Pubkey,
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Result<VersionedTransaction> {
+    let message = VersionedMessage::V0(v0::Message::try_compile(
+        &payer,
+        instructions,
+        lookup_tables,
+        recent_blockhash,
+    )?);
+
+    Ok(VersionedTransaction {
+        signatures: vec![
+            Signature::default();
+            usize::from(message.header().num_required_signatures)
+        ],
+        message,
+    })
+}
@@ -0,0 +1,155 @@
+use m3m3::StakeForFeeError;
+use num_traits::FromPrimitive;
+use solana_sdk::{
+    instruction::InstructionError,
+    message::CompileError,
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::SignerError,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use std::fmt;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error(transparent)]
+    Rpc(Box<solana_client::client_error::ClientError>),
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("Account {0} could not be decoded")]
+    InvalidAccountData(Pubkey),
+    #[error(transparent)]
+    Program(#[from] StakeForFeeError),
+    #[error("At most 2 top stakers can be replaced, got {0}")]
+    InvalidReplaceableTopStakerCount(usize),
+    #[error("Instruction could not be built: {0}")]
+    Instruction(#[from] std::io::Error),
+    #[error("Invalid account fixture: {0}")]
+    InvalidFixture(String),
+    #[error("Transaction could not be compiled: {0}")]
+    Compile(#[from] CompileError),
+    #[error("Instructions do not fit in one transaction: {0} bytes")]
+    TransactionTooLarge(usize),
+    #[error("Simulation failed: {err}")]
+    SimulationFailed {
+        err: TransactionError,
+        logs: Vec<String>,
+    },
+    #[error("Transaction could not be signed: {0}")]
+    Signer(#[from] SignerError),
+    #[error(transparent)]
+    TransactionFailed(Box<TransactionFailure>),
+    #[error("Blockhash of transaction {0} expired after every retry")]
+    BlockhashExpired(Signature),
+}
+
+impl From<solana_client::client_error::ClientError> for ClientError {
+    fn from(err: solana_client::client_error::ClientError) -> Self {
+        Self::Rpc(Box::new(err))
+    }
+}
+
+impl From<TransactionFailure> for ClientError {
+    fn from(failure: TransactionFailure) -> Self {
+        Self::TransactionFailed(Box::new(failure))
+    }
+}
+
+/// A transaction rejected by its preflight simulation or failed on chain, with the program
+/// error decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionFailure {
+    /// `None` when the preflight simulation rejected the transaction.
+    pub signature: Option<Signature>,
+    pub err: TransactionError,
+    /// Instruction that failed, if an instruction failed.
+    pub instruction_index: Option<u8>,
+    /// Error of a failed stake-for-fee instruction, decoded from its custom error code.
+    pub program_error: Option<StakeForFeeError>,
+    /// Log lines of the failed instruction, or of the whole transaction when it cannot be
+    /// told apart.
+    pub logs: Vec<String>,
+}
+
+impl TransactionFailure {
+    pub fn new(
+        transaction: &VersionedTransaction,
+        signature: Option<Signature>,
+        err: TransactionError,
+        logs: Vec<String>,
+    ) -> Self {
+        let (instruction_index, program_error) = match &err {
+            TransactionError::InstructionError(idx, instruction_err) => {
+                // Custom codes of other programs overlap with the stake-for-fee ones.
+                let is_stake_for_fee = transaction
+                    .message
+                    .instructions()
+                    .get(usize::from(*idx))
+                    .and_then(|instruction| {
+                        transaction
+                            .message
+                            .static_account_keys()
+                            .get(usize::from(instruction.program_id_index))
+                    })
+                    == Some(&m3m3::ID);
+                let program_error = match instruction_err {
+                    InstructionError::Custom(code) if is_stake_for_fee => {
+                        StakeForFeeError::from_u32(*code)
+                    }
+                    _ => None,
+                };
+                (Some(*idx), program_error)
+            }
+            _ => (None, None),
+        };
+        let logs = match instruction_index {
+            Some(idx) => instruction_logs(logs, usize::from(idx)),
+            None => logs,
+        };
+
+        Self {
+            signature,
+            err,
+            instruction_index,
+            program_error,
+            logs,
+        }
+    }
+}
+
+impl fmt::Display for TransactionFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Transaction ")?;
+        if let Some(signature) = &self.signature {
+            write!(f, "{} ", signature)?;
+        }
+        write!(f, "failed")?;
+        if let Some(program_error) = &self.program_error {
+            write!(f, ": {:?} ({})", program_error, program_error)?;
+        }
+        write!(f, ": {}", self.err)
+    }
+}
+
+impl std::error::Error for TransactionFailure {}
+
+/// Log lines of the `idx`th top-level instruction, its inner instructions included.
+fn instruction_logs(logs: Vec<String>, idx: usize) -> Vec<String> {
+    let starts: Vec<usize> = logs
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.starts_with("Program ") && line.ends_with(" invoke [1]"))
+        .map(|(line_idx, _)| line_idx)
+        .collect();
+
+    match starts.get(idx) {
+        Some(start) => {
+            let end = starts.get(idx + 1).copied().unwrap_or(logs.len());
+            logs[*start..end].to_vec()
+        }
+        None => logs,
+    }
+}
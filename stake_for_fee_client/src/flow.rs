@@ -0,0 +1,217 @@
+use crate::{
+    client::{StakeForFee, MAX_REPLACEABLE_TOP_STAKER_COUNT},
+    compute::{
+        get_compute_unit_limit_instruction, unsigned_transaction, DEFAULT_COMPUTE_UNIT_BUFFER,
+    },
+    error::Result,
+    query::AccountQuery,
+    rpc::RpcBackend,
+    wsol::unwrap_sol_instruction,
+};
+use common::{
+    constants::TOKEN_PROGRAM_ID,
+    pda::{derive_associated_token_key, derive_stake_escrow_key},
+    preview::{StakePreview, UnstakePreview},
+    unstake::{get_unstake_timeline, UnstakeStatus, UnstakeTimeline},
+};
+use futures::try_join;
+use m3m3::Unstake;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::VersionedTransaction,
+};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+
+/// Unsigned transaction ready for the owner to sign, and the list changes it causes.
+#[derive(Clone, Debug)]
+pub struct PreparedStake {
+    pub transaction: VersionedTransaction,
+    pub preview: StakePreview,
+}
+
+/// Unsigned `request_unstake` transaction, and the list changes it causes.
+#[derive(Debug)]
+pub struct PreparedUnstake {
+    pub transaction: VersionedTransaction,
+    pub preview: UnstakePreview,
+    /// The new unstake account, which signs along with the owner.
+    pub unstake: Keypair,
+    /// Signers in the order of the transaction signatures.
+    pub signers: Vec<Pubkey>,
+}
+
+/// Unsigned transaction withdrawing or cancelling one existing unstake.
+#[derive(Clone, Debug)]
+pub struct UnstakeTransaction {
+    pub unstake: UnstakeStatus,
+    pub transaction: VersionedTransaction,
+}
+
+impl<R: RpcBackend> StakeForFee<R> {
+    /// [`StakeForFee::stake`] of exactly `amount` passing two replaceable top stakers, with the
+    /// list changes it causes. The preview is computed against the loaded account states, see
+    /// [`StakeForFee::refresh_states`].
+    pub async fn prepare_stake(&self, amount: u64, owner: Pubkey) -> Result<PreparedStake> {
+        let (stake_escrow, has_user_stake_token) = self.fetch_stake_accounts(owner).await?;
+        let preview = self.account_states.preview_state().preview_stake(
+            owner,
+            stake_escrow.as_ref(),
+            amount,
+        )?;
+
+        Ok(PreparedStake {
+            transaction: self
+                .stake_transaction(
+                    amount,
+                    owner,
+                    stake_escrow.is_some(),
+                    has_user_stake_token,
+                    MAX_REPLACEABLE_TOP_STAKER_COUNT,
+                )
+                .await?,
+            preview,
+        })
+    }
+
+    /// Request to unstake `amount` in one transaction, as the TS `unstake`. A new unstake
+    /// keypair is generated unless one is given, and up to three promotion candidates from the
+    /// full balance list are passed when the owner is in the top list.
+    pub async fn prepare_unstake(
+        &self,
+        amount: u64,
+        owner: Pubkey,
+        unstake: Option<Keypair>,
+    ) -> Result<PreparedUnstake> {
+        let stake_escrow = self.required_stake_escrow(owner).await?;
+        let preview = self
+            .account_states
+            .preview_state()
+            .preview_request_unstake(owner, Some(&stake_escrow), amount)?;
+
+        let unstake = unstake.unwrap_or_else(Keypair::new);
+        let instruction = self.request_unstake_instruction(
+            amount,
+            unstake.pubkey(),
+            owner,
+            preview.remaining_accounts.clone(),
+        )?;
+        let transaction = self.sized_transaction(vec![instruction], owner).await?;
+        let signers =
+            transaction.message.static_account_keys()[..transaction.signatures.len()].to_vec();
+
+        Ok(PreparedUnstake {
+            transaction,
+            preview,
+            unstake,
+            signers,
+        })
+    }
+
+    /// Unstakes of the owner's stake escrow, split by whether they can be withdrawn now.
+    pub async fn fetch_unstake_timeline(&self, owner: Pubkey) -> Result<UnstakeTimeline> {
+        let stake_escrow = derive_stake_escrow_key(self.vault, owner);
+        let (unstakes, clock) = try_join!(
+            AccountQuery::<Unstake>::new()
+                .stake_escrow(stake_escrow)
+                .fetch(self.rpc()),
+            self.rpc().get_clock(),
+        )?;
+
+        Ok(get_unstake_timeline(
+            self.vault,
+            owner,
+            &unstakes,
+            clock.unix_timestamp,
+        )?)
+    }
+
+    /// One `withdraw` transaction per released unstake of the owner, creating the stake token
+    /// ATA if missing and unwrapping SOL.
+    pub async fn prepare_withdraw_unstakes(
+        &self,
+        owner: Pubkey,
+    ) -> Result<Vec<UnstakeTransaction>> {
+        let timeline = self.fetch_unstake_timeline(owner).await?;
+        if timeline.withdrawable.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let stake_mint = self.account_states.fee_vault.stake_mint;
+        let user_stake_token = derive_associated_token_key(owner, stake_mint);
+        let unwrap_sol = self.unwraps_sol(&stake_mint);
+        // Each transaction closes the wSOL ATA it withdraws to.
+        let create_ata = unwrap_sol || self.rpc().get_account(&user_stake_token).await?.is_none();
+
+        let mut transactions = Vec::with_capacity(timeline.withdrawable.len());
+        for status in timeline.withdrawable {
+            let mut instructions = vec![];
+            // Idempotent, so the transactions can land in any order.
+            if create_ata {
+                instructions.push(create_associated_token_account_idempotent(
+                    &owner,
+                    &owner,
+                    &stake_mint,
+                    &TOKEN_PROGRAM_ID,
+                ));
+            }
+            instructions.push(self.withdraw_instruction(
+                status.unstake,
+                owner,
+                user_stake_token,
+            )?);
+            if unwrap_sol {
+                instructions.push(unwrap_sol_instruction(owner)?);
+            }
+            transactions.push(UnstakeTransaction {
+                unstake: status,
+                transaction: self.sized_transaction(instructions, owner).await?,
+            });
+        }
+
+        Ok(transactions)
+    }
+
+    /// One `cancel_unstake` transaction per unstake of the owner still locked, restaking its
+    /// amount. Released unstakes are left to [`StakeForFee::prepare_withdraw_unstakes`].
+    pub async fn prepare_cancel_unstakes(&self, owner: Pubkey) -> Result<Vec<UnstakeTransaction>> {
+        let timeline = self.fetch_unstake_timeline(owner).await?;
+        if timeline.locked.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let stake_escrow = self.required_stake_escrow(owner).await?;
+        let mut transactions = Vec::with_capacity(timeline.locked.len());
+        for status in timeline.locked {
+            let instruction =
+                self.cancel_unstake_instruction(status.unstake, owner, &stake_escrow)?;
+            transactions.push(UnstakeTransaction {
+                unstake: status,
+                transaction: self.sized_transaction(vec![instruction], owner).await?,
+            });
+        }
+
+        Ok(transactions)
+    }
+
+    /// Unsigned v0 transaction with a compute unit limit sized by simulation.
+    pub(crate) async fn sized_transaction(
+        &self,
+        mut instructions: Vec<Instruction>,
+        payer: Pubkey,
+    ) -> Result<VersionedTransaction> {
+        let compute_unit_limit = get_compute_unit_limit_instruction(
+            self.rpc(),
+            &instructions,
+            payer,
+            &[],
+            DEFAULT_COMPUTE_UNIT_BUFFER,
+        )
+        .await;
+        instructions.insert(0, compute_unit_limit);
+
+        let blockhash = self.rpc().get_latest_blockhash().await?;
+        unsigned_transaction(&instructions, payer, &[], blockhash)
+    }
+}
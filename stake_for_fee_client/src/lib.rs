@@ -0,0 +1,21 @@
+pub mod batch_claim;
+pub mod client;
+pub mod compute;
+pub mod error;
+pub mod flow;
+pub mod portfolio;
+pub mod query;
+pub mod rpc;
+pub mod send;
+pub mod state;
+pub mod wsol;
+
+pub use batch_claim::{plan_batch_claim, ClaimBatch, ClaimPlan, ClaimPlanConfig, VaultClaim};
+pub use client::StakeForFee;
+pub use error::{ClientError, Result, TransactionFailure};
+pub use flow::{PreparedStake, PreparedUnstake, UnstakeTransaction};
+pub use portfolio::{fetch_portfolio, Portfolio, VaultPosition};
+pub use query::{AccountQuery, ProgramAccount};
+pub use rpc::{InMemoryRpc, RpcBackend};
+pub use send::{send_and_confirm, SendConfig};
+pub use state::AccountStates;
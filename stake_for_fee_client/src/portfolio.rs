@@ -0,0 +1,235 @@
+use crate::{
+    error::Result,
+    query::AccountQuery,
+    rpc::RpcBackend,
+    state::{decode, decode_clock, get_multiple_accounts, unpack},
+};
+use common::{
+    dynamic_amm::{
+        decode_lock_escrow, decode_pool, get_locked_escrow_pending_fee, LockEscrow, Pool,
+        PoolLpAmounts,
+    },
+    dynamic_vault::{decode_vault, Vault},
+    fee_release::get_released_fees_with_pending_claim,
+    math::fee::get_stake_escrow_pending_fees,
+    unstake::{get_unstake_timeline, UnstakeTimeline},
+};
+use futures::try_join;
+use m3m3::{Configuration, FeeVault, FeeVaultAccount, StakeEscrow, Unstake};
+use solana_sdk::{pubkey::Pubkey, sysvar};
+use spl_token::state::{Account as TokenAccount, Mint};
+
+/// Accounts fetched per pool: a_vault, b_vault, a_vault_lp, b_vault_lp and the pool LP mint.
+const POOL_ACCOUNT_COUNT: usize = 5;
+
+/// An owner's stake escrow in one fee vault, with the vault and pool state it was valued with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VaultPosition {
+    pub vault: Pubkey,
+    pub fee_vault: FeeVault,
+    pub pool: Pool,
+    pub lock_escrow: LockEscrow,
+    pub a_vault: Vault,
+    pub b_vault: Vault,
+    pub lp_amounts: PoolLpAmounts,
+    pub stake_escrow: Pubkey,
+    pub stake_amount: u64,
+    pub in_top_list: bool,
+    /// Fee A/B `claim_fee` would pay out now, including fees not yet claimed from the lock
+    /// escrow.
+    pub pending_fee_a: u64,
+    pub pending_fee_b: u64,
+    pub fee_a_claimed_amount: u128,
+    pub fee_b_claimed_amount: u128,
+    pub unstakes: UnstakeTimeline,
+}
+
+impl VaultPosition {
+    pub fn configuration(&self) -> &Configuration {
+        &self.fee_vault.configuration
+    }
+
+    pub fn is_stake_token_a(&self) -> bool {
+        self.fee_vault.stake_mint == self.pool.token_a_mint
+    }
+
+    /// Pending fee in the quote token, the part `claim_fee` transfers. The stake token fee is
+    /// restaked.
+    pub fn pending_quote_fee(&self) -> u64 {
+        if self.is_stake_token_a() {
+            self.pending_fee_b
+        } else {
+            self.pending_fee_a
+        }
+    }
+
+    /// Pending fee in the stake token, restaked by `claim_fee`.
+    pub fn pending_stake_fee(&self) -> u64 {
+        if self.is_stake_token_a() {
+            self.pending_fee_a
+        } else {
+            self.pending_fee_b
+        }
+    }
+}
+
+/// Every fee vault an owner has a stake escrow in. Same as the TS `getAllStakedVaultByUser`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Portfolio {
+    pub owner: Pubkey,
+    pub current_time: i64,
+    /// Sorted by vault.
+    pub positions: Vec<VaultPosition>,
+}
+
+impl Portfolio {
+    pub fn position(&self, vault: Pubkey) -> Option<&VaultPosition> {
+        self.positions
+            .iter()
+            .find(|position| position.vault == vault)
+    }
+}
+
+/// Fetch the portfolio of `owner` with two `getProgramAccounts` requests and four batched
+/// `getMultipleAccounts` rounds, whatever the number of vaults.
+pub async fn fetch_portfolio(rpc: &impl RpcBackend, owner: Pubkey) -> Result<Portfolio> {
+    let (mut stake_escrows, unstakes) = try_join!(
+        AccountQuery::<StakeEscrow>::new().owner(owner).fetch(rpc),
+        AccountQuery::<Unstake>::new().owner(owner).fetch(rpc),
+    )?;
+    stake_escrows.sort_by_key(|(_, stake_escrow)| stake_escrow.vault);
+
+    let mut keys = vec![sysvar::clock::ID];
+    keys.extend(
+        stake_escrows
+            .iter()
+            .map(|(_, stake_escrow)| stake_escrow.vault),
+    );
+    let accounts = get_multiple_accounts(rpc, &keys).await?;
+    let current_time = decode_clock(&accounts[0])?.unix_timestamp;
+    let fee_vaults = keys[1..]
+        .iter()
+        .zip(&accounts[1..])
+        .map(|(key, account)| {
+            decode(*key, account, |data| {
+                Ok(FeeVaultAccount::deserialize(data)?.0)
+            })
+        })
+        .collect::<Result<Vec<FeeVault>>>()?;
+
+    let keys: Vec<Pubkey> = fee_vaults
+        .iter()
+        .flat_map(|fee_vault| [fee_vault.pool, fee_vault.lock_escrow])
+        .collect();
+    let accounts = get_multiple_accounts(rpc, &keys).await?;
+    let mut pools = vec![];
+    let mut lock_escrows = vec![];
+    for (keys, accounts) in keys.chunks(2).zip(accounts.chunks(2)) {
+        pools.push(decode(keys[0], &accounts[0], decode_pool)?);
+        lock_escrows.push(decode(keys[1], &accounts[1], decode_lock_escrow)?);
+    }
+
+    let keys: Vec<Pubkey> = pools
+        .iter()
+        .flat_map(|pool| {
+            [
+                pool.a_vault,
+                pool.b_vault,
+                pool.a_vault_lp,
+                pool.b_vault_lp,
+                pool.lp_mint,
+            ]
+        })
+        .collect();
+    let accounts = get_multiple_accounts(rpc, &keys).await?;
+    let mut vaults = vec![];
+    let mut lp_accounts = vec![];
+    for (keys, accounts) in keys
+        .chunks(POOL_ACCOUNT_COUNT)
+        .zip(accounts.chunks(POOL_ACCOUNT_COUNT))
+    {
+        vaults.push((
+            decode(keys[0], &accounts[0], decode_vault)?,
+            decode(keys[1], &accounts[1], decode_vault)?,
+        ));
+        lp_accounts.push((
+            unpack::<TokenAccount>(keys[2], &accounts[2])?,
+            unpack::<TokenAccount>(keys[3], &accounts[3])?,
+            unpack::<Mint>(keys[4], &accounts[4])?,
+        ));
+    }
+
+    let keys: Vec<Pubkey> = vaults
+        .iter()
+        .flat_map(|(a_vault, b_vault)| [a_vault.lp_mint, b_vault.lp_mint])
+        .collect();
+    let accounts = get_multiple_accounts(rpc, &keys).await?;
+    let mut vault_lp_mints = vec![];
+    for (keys, accounts) in keys.chunks(2).zip(accounts.chunks(2)) {
+        vault_lp_mints.push((
+            unpack::<Mint>(keys[0], &accounts[0])?,
+            unpack::<Mint>(keys[1], &accounts[1])?,
+        ));
+    }
+
+    let mut positions = Vec::with_capacity(stake_escrows.len());
+    for (idx, (stake_escrow_key, stake_escrow)) in stake_escrows.into_iter().enumerate() {
+        let fee_vault = fee_vaults[idx].clone();
+        let (a_vault, b_vault) = vaults[idx].clone();
+        let (a_vault_lp, b_vault_lp, pool_lp_mint) = &lp_accounts[idx];
+        let (a_vault_lp_mint, b_vault_lp_mint) = &vault_lp_mints[idx];
+        let lp_amounts = PoolLpAmounts {
+            a_vault_lp_amount: a_vault_lp.amount,
+            b_vault_lp_amount: b_vault_lp.amount,
+            a_vault_lp_supply: a_vault_lp_mint.supply,
+            b_vault_lp_supply: b_vault_lp_mint.supply,
+            pool_lp_supply: pool_lp_mint.supply,
+        };
+
+        let (claimable_fee_a, claimable_fee_b) = get_locked_escrow_pending_fee(
+            current_time,
+            &fee_vault,
+            &lock_escrows[idx],
+            &a_vault,
+            &b_vault,
+            &lp_amounts,
+        )?;
+        let release = get_released_fees_with_pending_claim(
+            &fee_vault,
+            current_time,
+            claimable_fee_a,
+            claimable_fee_b,
+        )?;
+        let (pending_fee_a, pending_fee_b) = get_stake_escrow_pending_fees(
+            &fee_vault.top_staker_info,
+            &stake_escrow,
+            release.released_fee_a,
+            release.released_fee_b,
+        )?;
+
+        let vault = stake_escrow.vault;
+        positions.push(VaultPosition {
+            vault,
+            fee_vault,
+            pool: pools[idx].clone(),
+            lock_escrow: lock_escrows[idx].clone(),
+            a_vault,
+            b_vault,
+            lp_amounts,
+            stake_escrow: stake_escrow_key,
+            stake_amount: stake_escrow.stake_amount,
+            in_top_list: stake_escrow.in_top_list != 0,
+            pending_fee_a,
+            pending_fee_b,
+            fee_a_claimed_amount: stake_escrow.fee_a_claimed_amount,
+            fee_b_claimed_amount: stake_escrow.fee_b_claimed_amount,
+            unstakes: get_unstake_timeline(vault, owner, &unstakes, current_time)?,
+        });
+    }
+
+    Ok(Portfolio {
+        owner,
+        current_time,
+        positions,
+    })
+}
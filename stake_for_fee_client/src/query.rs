@@ -0,0 +1,181 @@
+use crate::{
+    error::{ClientError, Result},
+    rpc::RpcBackend,
+};
+use m3m3::{
+    FeeVault, FeeVaultAccount, StakeEscrow, StakeEscrowAccount, Unstake, UnstakeAccount,
+    FEE_VAULT_ACCOUNT_DISCM, STAKE_ESCROW_ACCOUNT_DISCM, UNSTAKE_ACCOUNT_DISCM,
+};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::pubkey::{Pubkey, PUBKEY_BYTES};
+use std::marker::PhantomData;
+
+const DISCRIMINATOR_LEN: usize = 8;
+
+// Borsh sizes of the accounts and of the `FeeVault` structs before `creator`.
+const METRICS_SIZE: usize = 160;
+const CONFIGURATION_SIZE: usize = 96;
+const TOP_STAKER_INFO_SIZE: usize = 160;
+const FEE_VAULT_SIZE: usize = DISCRIMINATOR_LEN
+    + 9 * PUBKEY_BYTES
+    + METRICS_SIZE
+    + CONFIGURATION_SIZE
+    + TOP_STAKER_INFO_SIZE
+    + 16
+    + 20 * 16;
+const STAKE_ESCROW_SIZE: usize =
+    DISCRIMINATOR_LEN + 2 * PUBKEY_BYTES + 6 * 8 + 16 + 4 * 16 + 20 * 16;
+const UNSTAKE_SIZE: usize = DISCRIMINATOR_LEN + PUBKEY_BYTES + 3 * 8 + 30 * 8;
+
+/// A program account type `getProgramAccounts` can be queried for.
+pub trait ProgramAccount: Sized {
+    const DISCRIMINATOR: [u8; 8];
+    /// Account size, discriminator included.
+    const LEN: usize;
+
+    fn decode(data: &[u8]) -> std::io::Result<Self>;
+}
+
+impl ProgramAccount for FeeVault {
+    const DISCRIMINATOR: [u8; 8] = FEE_VAULT_ACCOUNT_DISCM;
+    const LEN: usize = FEE_VAULT_SIZE;
+
+    fn decode(data: &[u8]) -> std::io::Result<Self> {
+        Ok(FeeVaultAccount::deserialize(data)?.0)
+    }
+}
+
+impl ProgramAccount for StakeEscrow {
+    const DISCRIMINATOR: [u8; 8] = STAKE_ESCROW_ACCOUNT_DISCM;
+    const LEN: usize = STAKE_ESCROW_SIZE;
+
+    fn decode(data: &[u8]) -> std::io::Result<Self> {
+        Ok(StakeEscrowAccount::deserialize(data)?.0)
+    }
+}
+
+impl ProgramAccount for Unstake {
+    const DISCRIMINATOR: [u8; 8] = UNSTAKE_ACCOUNT_DISCM;
+    const LEN: usize = UNSTAKE_SIZE;
+
+    fn decode(data: &[u8]) -> std::io::Result<Self> {
+        Ok(UnstakeAccount::deserialize(data)?.0)
+    }
+}
+
+// `FeeVault`: lock_escrow, stake_mint, quote_mint, pool, four more pubkeys, metrics,
+// configuration, top_staker_info, creator.
+const FEE_VAULT_STAKE_MINT_OFFSET: usize = DISCRIMINATOR_LEN + PUBKEY_BYTES;
+const FEE_VAULT_QUOTE_MINT_OFFSET: usize = DISCRIMINATOR_LEN + 2 * PUBKEY_BYTES;
+const FEE_VAULT_POOL_OFFSET: usize = DISCRIMINATOR_LEN + 3 * PUBKEY_BYTES;
+const FEE_VAULT_CREATOR_OFFSET: usize =
+    DISCRIMINATOR_LEN + 8 * PUBKEY_BYTES + METRICS_SIZE + CONFIGURATION_SIZE + TOP_STAKER_INFO_SIZE;
+
+// `StakeEscrow`: owner, vault, full_balance_index, stake_amount, in_top_list.
+const STAKE_ESCROW_OWNER_OFFSET: usize = DISCRIMINATOR_LEN;
+const STAKE_ESCROW_VAULT_OFFSET: usize = DISCRIMINATOR_LEN + PUBKEY_BYTES;
+const STAKE_ESCROW_IN_TOP_LIST_OFFSET: usize = DISCRIMINATOR_LEN + 2 * PUBKEY_BYTES + 2 * 8;
+
+// `Unstake`: stake_escrow, unstake_amount, created_at, release_at, then the owner the program
+// writes for indexing, at the start of what the interface declares as `padding`.
+const UNSTAKE_STAKE_ESCROW_OFFSET: usize = DISCRIMINATOR_LEN;
+const UNSTAKE_OWNER_OFFSET: usize = DISCRIMINATOR_LEN + PUBKEY_BYTES + 3 * 8;
+
+/// `getProgramAccounts` filters on the fields of `T`, always matching its discriminator and
+/// size, e.g. `AccountQuery::<StakeEscrow>::new().owner(owner).fetch(&rpc)`.
+#[derive(Clone, Debug)]
+pub struct AccountQuery<T> {
+    filters: Vec<RpcFilterType>,
+    account: PhantomData<T>,
+}
+
+impl<T: ProgramAccount> Default for AccountQuery<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ProgramAccount> AccountQuery<T> {
+    pub fn new() -> Self {
+        Self {
+            filters: vec![
+                RpcFilterType::DataSize(T::LEN as u64),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, T::DISCRIMINATOR.to_vec())),
+            ],
+            account: PhantomData,
+        }
+    }
+
+    pub fn filters(&self) -> &[RpcFilterType] {
+        &self.filters
+    }
+
+    pub fn into_filters(self) -> Vec<RpcFilterType> {
+        self.filters
+    }
+
+    /// Matching accounts of the program, decoded.
+    pub async fn fetch(self, rpc: &impl RpcBackend) -> Result<Vec<(Pubkey, T)>> {
+        rpc.get_program_accounts(&m3m3::ID, self.filters)
+            .await?
+            .into_iter()
+            .map(|(key, account)| {
+                T::decode(&account.data)
+                    .map(|decoded| (key, decoded))
+                    .map_err(|_| ClientError::InvalidAccountData(key))
+            })
+            .collect()
+    }
+
+    fn memcmp(mut self, offset: usize, bytes: &[u8]) -> Self {
+        self.filters
+            .push(RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                offset,
+                bytes.to_vec(),
+            )));
+        self
+    }
+}
+
+impl AccountQuery<FeeVault> {
+    pub fn pool(self, pool: Pubkey) -> Self {
+        self.memcmp(FEE_VAULT_POOL_OFFSET, pool.as_ref())
+    }
+
+    pub fn stake_mint(self, stake_mint: Pubkey) -> Self {
+        self.memcmp(FEE_VAULT_STAKE_MINT_OFFSET, stake_mint.as_ref())
+    }
+
+    pub fn quote_mint(self, quote_mint: Pubkey) -> Self {
+        self.memcmp(FEE_VAULT_QUOTE_MINT_OFFSET, quote_mint.as_ref())
+    }
+
+    pub fn creator(self, creator: Pubkey) -> Self {
+        self.memcmp(FEE_VAULT_CREATOR_OFFSET, creator.as_ref())
+    }
+}
+
+impl AccountQuery<StakeEscrow> {
+    pub fn owner(self, owner: Pubkey) -> Self {
+        self.memcmp(STAKE_ESCROW_OWNER_OFFSET, owner.as_ref())
+    }
+
+    pub fn vault(self, vault: Pubkey) -> Self {
+        self.memcmp(STAKE_ESCROW_VAULT_OFFSET, vault.as_ref())
+    }
+
+    pub fn in_top_list(self, in_top_list: bool) -> Self {
+        self.memcmp(STAKE_ESCROW_IN_TOP_LIST_OFFSET, &[u8::from(in_top_list)])
+    }
+}
+
+impl AccountQuery<Unstake> {
+    pub fn stake_escrow(self, stake_escrow: Pubkey) -> Self {
+        self.memcmp(UNSTAKE_STAKE_ESCROW_OFFSET, stake_escrow.as_ref())
+    }
+
+    /// Same filter as the TS `getAllStakedVaultByUser`.
+    pub fn owner(self, owner: Pubkey) -> Self {
+        self.memcmp(UNSTAKE_OWNER_OFFSET, owner.as_ref())
+    }
+}
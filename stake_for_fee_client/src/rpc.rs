@@ -0,0 +1,447 @@
+use crate::error::{ClientError, Result};
+use async_trait::async_trait;
+use serde_json::json;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionConfig,
+        RpcTransactionConfig,
+    },
+    rpc_filter::RpcFilterType,
+    rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
+    rpc_response::{RpcKeyedAccount, RpcSimulateTransactionResult},
+};
+use solana_sdk::{
+    account::{create_account_for_test, from_account, Account, AccountSharedData},
+    clock::Clock,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::Signature,
+    sysvar,
+    transaction::{self, TransactionError, VersionedTransaction},
+};
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::{
+    collections::HashMap,
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, RwLock,
+    },
+};
+
+/// Blocks a blockhash of [`InMemoryRpc`] stays valid for, as on a cluster.
+const BLOCKHASH_VALIDITY: u64 = 150;
+
+/// RPC methods used by the client, so it runs against a cluster or an [`InMemoryRpc`].
+#[async_trait]
+pub trait RpcBackend: Send + Sync {
+    async fn get_multiple_accounts(&self, keys: &[Pubkey]) -> Result<Vec<Option<Account>>>;
+
+    /// Accounts owned by `program` passing every filter.
+    async fn get_program_accounts(
+        &self,
+        program: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>>;
+
+    /// Simulate without signature verification and with the latest blockhash, so unsigned
+    /// transactions can be simulated.
+    async fn simulate_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> Result<RpcSimulateTransactionResult>;
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature>;
+
+    async fn get_latest_blockhash(&self) -> Result<Hash>;
+
+    /// Latest blockhash and the last block height it is valid at.
+    async fn get_latest_blockhash_with_commitment(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> Result<(Hash, u64)>;
+
+    async fn get_block_height(&self, commitment: CommitmentConfig) -> Result<u64>;
+
+    /// Result of the transaction, `None` while it has not reached `commitment`.
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<transaction::Result<()>>>;
+
+    /// Log lines of a processed transaction, `None` if it is not found.
+    async fn get_transaction_logs(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Vec<String>>>;
+
+    async fn get_account(&self, key: &Pubkey) -> Result<Option<Account>> {
+        Ok(self.get_multiple_accounts(&[*key]).await?.remove(0))
+    }
+
+    /// Clock sysvar.
+    async fn get_clock(&self) -> Result<Clock> {
+        let account = self
+            .get_account(&sysvar::clock::ID)
+            .await?
+            .ok_or(ClientError::AccountNotFound(sysvar::clock::ID))?;
+        from_account(&account).ok_or(ClientError::InvalidAccountData(sysvar::clock::ID))
+    }
+}
+
+#[async_trait]
+impl RpcBackend for RpcClient {
+    async fn get_multiple_accounts(&self, keys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        Ok(RpcClient::get_multiple_accounts(self, keys).await?)
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        Ok(self
+            .get_program_accounts_with_config(program, config)
+            .await?)
+    }
+
+    async fn simulate_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> Result<RpcSimulateTransactionResult> {
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            ..Default::default()
+        };
+        Ok(self
+            .simulate_transaction_with_config(transaction, config)
+            .await?
+            .value)
+    }
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature> {
+        Ok(RpcClient::send_transaction(self, transaction).await?)
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<Hash> {
+        Ok(RpcClient::get_latest_blockhash(self).await?)
+    }
+
+    async fn get_latest_blockhash_with_commitment(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> Result<(Hash, u64)> {
+        Ok(RpcClient::get_latest_blockhash_with_commitment(self, commitment).await?)
+    }
+
+    async fn get_block_height(&self, commitment: CommitmentConfig) -> Result<u64> {
+        Ok(self.get_block_height_with_commitment(commitment).await?)
+    }
+
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<transaction::Result<()>>> {
+        Ok(self
+            .get_signature_status_with_commitment(signature, commitment)
+            .await?)
+    }
+
+    async fn get_transaction_logs(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Vec<String>>> {
+        let config = RpcTransactionConfig {
+            commitment: Some(commitment),
+            max_supported_transaction_version: Some(0),
+            ..Default::default()
+        };
+        // `getTransaction` answers null for an unknown signature, which
+        // `get_transaction_with_config` reports as a decoding error.
+        let transaction: Option<EncodedConfirmedTransactionWithStatusMeta> = self
+            .send(
+                RpcRequest::GetTransaction,
+                json!([signature.to_string(), config]),
+            )
+            .await?;
+
+        Ok(transaction
+            .and_then(|transaction| transaction.transaction.meta)
+            .and_then(|meta| meta.log_messages.into()))
+    }
+}
+
+/// Offline [`RpcBackend`] over a fixed set of accounts.
+///
+/// Accounts are loaded from the JSON output of `solana account --output json`, or of
+/// `getProgramAccounts` for a list. Transactions are never executed: sent and simulated ones are
+/// recorded for assertions, as are `get_multiple_accounts` requests. Simulations succeed and sent
+/// transactions land at once, unless a transaction error is set or they are delayed. Every
+/// `get_block_height` call produces a block.
+#[derive(Debug, Default)]
+pub struct InMemoryRpc {
+    accounts: RwLock<HashMap<Pubkey, Account>>,
+    latest_blockhash: Hash,
+    units_consumed: Option<u64>,
+    transaction_error: Option<(TransactionError, Vec<String>)>,
+    block_height: AtomicU64,
+    /// Count of the next sent transactions to delay, and the blocks they are delayed by.
+    transaction_delay: Mutex<(usize, u64)>,
+    /// Delayed transactions, with the block height they land at.
+    pending_transactions: Mutex<HashMap<Signature, u64>>,
+    landed_transactions: Mutex<HashMap<Signature, transaction::Result<()>>>,
+    sent_transactions: Mutex<Vec<VersionedTransaction>>,
+    simulated_transactions: Mutex<Vec<VersionedTransaction>>,
+    multiple_accounts_requests: Mutex<Vec<Vec<Pubkey>>>,
+}
+
+impl InMemoryRpc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_latest_blockhash(mut self, latest_blockhash: Hash) -> Self {
+        self.latest_blockhash = latest_blockhash;
+        self
+    }
+
+    /// Compute units every simulation reports as consumed.
+    pub fn with_units_consumed(mut self, units_consumed: u64) -> Self {
+        self.units_consumed = Some(units_consumed);
+        self
+    }
+
+    /// Error and log lines every simulation reports, failing the preflight of sent
+    /// transactions.
+    pub fn with_transaction_error(mut self, err: TransactionError, logs: Vec<String>) -> Self {
+        self.transaction_error = Some((err, logs));
+        self
+    }
+
+    /// Make the next `count` sent transactions never land, as when their blockhash expires.
+    pub fn drop_next_transactions(&self, count: usize) {
+        self.delay_next_transactions(count, u64::MAX);
+    }
+
+    /// Make the next `count` sent transactions land `blocks` blocks after being sent.
+    pub fn delay_next_transactions(&self, count: usize, blocks: u64) {
+        *self.transaction_delay.lock().unwrap() = (count, blocks);
+    }
+
+    pub fn set_account(&self, key: Pubkey, account: Account) {
+        self.accounts.write().unwrap().insert(key, account);
+    }
+
+    pub fn remove_account(&self, key: &Pubkey) -> Option<Account> {
+        self.accounts.write().unwrap().remove(key)
+    }
+
+    /// Store `clock` as the clock sysvar.
+    pub fn set_clock(&self, clock: &Clock) {
+        self.set_account(sysvar::clock::ID, create_account_for_test(clock));
+    }
+
+    /// Load a keyed account, or a list of them, from a JSON dump with base64 or base58 data.
+    pub fn load_fixture(&self, json: &str) -> Result<()> {
+        let keyed_accounts = match serde_json::from_str::<Vec<RpcKeyedAccount>>(json) {
+            Ok(keyed_accounts) => keyed_accounts,
+            Err(_) => vec![serde_json::from_str::<RpcKeyedAccount>(json)
+                .map_err(|err| ClientError::InvalidFixture(err.to_string()))?],
+        };
+
+        for keyed_account in keyed_accounts {
+            let key = Pubkey::from_str(&keyed_account.pubkey)
+                .map_err(|err| ClientError::InvalidFixture(err.to_string()))?;
+            let account = keyed_account
+                .account
+                .decode::<Account>()
+                .ok_or(ClientError::InvalidAccountData(key))?;
+            self.set_account(key, account);
+        }
+        Ok(())
+    }
+
+    pub fn load_fixture_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = std::fs::read_to_string(path.as_ref()).map_err(|err| {
+            ClientError::InvalidFixture(format!("{}: {}", path.as_ref().display(), err))
+        })?;
+        self.load_fixture(&json)
+    }
+
+    pub fn sent_transactions(&self) -> Vec<VersionedTransaction> {
+        self.sent_transactions.lock().unwrap().clone()
+    }
+
+    pub fn simulated_transactions(&self) -> Vec<VersionedTransaction> {
+        self.simulated_transactions.lock().unwrap().clone()
+    }
+
+    /// Keys of every `get_multiple_accounts` request, in order.
+    pub fn multiple_accounts_requests(&self) -> Vec<Vec<Pubkey>> {
+        self.multiple_accounts_requests.lock().unwrap().clone()
+    }
+
+    /// Result of a landed transaction, landing it first if it was delayed up to the current
+    /// block height.
+    fn landed_transaction(&self, signature: &Signature) -> Option<transaction::Result<()>> {
+        let mut landed_transactions = self.landed_transactions.lock().unwrap();
+        let mut pending_transactions = self.pending_transactions.lock().unwrap();
+        let block_height = self.block_height.load(Ordering::SeqCst);
+        if pending_transactions
+            .get(signature)
+            .is_some_and(|landing_height| *landing_height <= block_height)
+        {
+            pending_transactions.remove(signature);
+            landed_transactions.insert(*signature, Ok(()));
+        }
+        landed_transactions.get(signature).cloned()
+    }
+
+    fn simulation_result(&self) -> RpcSimulateTransactionResult {
+        let (err, logs) = match &self.transaction_error {
+            Some((err, logs)) => (Some(err.clone()), logs.clone()),
+            None => (None, vec![]),
+        };
+
+        RpcSimulateTransactionResult {
+            err,
+            logs: Some(logs),
+            accounts: None,
+            units_consumed: self.units_consumed,
+            return_data: None,
+            inner_instructions: None,
+        }
+    }
+}
+
+#[async_trait]
+impl RpcBackend for InMemoryRpc {
+    async fn get_multiple_accounts(&self, keys: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        self.multiple_accounts_requests
+            .lock()
+            .unwrap()
+            .push(keys.to_vec());
+
+        let accounts = self.accounts.read().unwrap();
+        Ok(keys.iter().map(|key| accounts.get(key).cloned()).collect())
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let accounts = self.accounts.read().unwrap();
+        let mut program_accounts: Vec<(Pubkey, Account)> = accounts
+            .iter()
+            .filter(|(_, account)| account.owner == *program)
+            .filter(|(_, account)| {
+                let account = AccountSharedData::from((*account).clone());
+                filters.iter().all(|filter| filter.allows(&account))
+            })
+            .map(|(key, account)| (*key, account.clone()))
+            .collect();
+        program_accounts.sort_by_key(|(key, _)| *key);
+
+        Ok(program_accounts)
+    }
+
+    async fn simulate_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> Result<RpcSimulateTransactionResult> {
+        self.simulated_transactions
+            .lock()
+            .unwrap()
+            .push(transaction.clone());
+
+        Ok(self.simulation_result())
+    }
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature> {
+        self.sent_transactions
+            .lock()
+            .unwrap()
+            .push(transaction.clone());
+
+        if self.transaction_error.is_some() {
+            let err = RpcError::RpcResponseError {
+                code: -32002,
+                message: "Transaction simulation failed".to_string(),
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(
+                    self.simulation_result(),
+                ),
+            };
+            return Err(solana_client::client_error::ClientError::from(err).into());
+        }
+
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        let mut transaction_delay = self.transaction_delay.lock().unwrap();
+        match *transaction_delay {
+            (0, _) => {
+                self.landed_transactions
+                    .lock()
+                    .unwrap()
+                    .insert(signature, Ok(()));
+            }
+            (count, blocks) => {
+                *transaction_delay = (count - 1, blocks);
+                let block_height = self.block_height.load(Ordering::SeqCst);
+                self.pending_transactions
+                    .lock()
+                    .unwrap()
+                    .insert(signature, block_height.saturating_add(blocks));
+            }
+        }
+        Ok(signature)
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<Hash> {
+        Ok(self.latest_blockhash)
+    }
+
+    async fn get_latest_blockhash_with_commitment(
+        &self,
+        _commitment: CommitmentConfig,
+    ) -> Result<(Hash, u64)> {
+        let block_height = self.block_height.load(Ordering::SeqCst);
+        Ok((self.latest_blockhash, block_height + BLOCKHASH_VALIDITY))
+    }
+
+    async fn get_block_height(&self, _commitment: CommitmentConfig) -> Result<u64> {
+        Ok(self.block_height.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+        _commitment: CommitmentConfig,
+    ) -> Result<Option<transaction::Result<()>>> {
+        Ok(self.landed_transaction(signature))
+    }
+
+    async fn get_transaction_logs(
+        &self,
+        signature: &Signature,
+        _commitment: CommitmentConfig,
+    ) -> Result<Option<Vec<String>>> {
+        Ok(self.landed_transaction(signature).map(|_| vec![]))
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    error::{ClientError, Result, TransactionFailure},
+    rpc::RpcBackend,
+};
+use solana_client::{
+    client_error::ClientErrorKind,
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::Signature,
+    signer::signers::Signers,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct SendConfig {
+    pub commitment: CommitmentConfig,
+    /// Times the transaction is signed again with a fresh blockhash after its blockhash
+    /// expired.
+    pub max_retries: usize,
+    /// Delay between signature status polls.
+    pub poll_interval: Duration,
+}
+
+impl Default for SendConfig {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            max_retries: 3,
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Sign `transaction` with a fresh blockhash, send it and wait for `commitment`, signing and
+/// sending again while its blockhash expires. A failed transaction is returned as
+/// [`ClientError::TransactionFailed`], with the stake-for-fee error decoded.
+pub async fn send_and_confirm<T: Signers + ?Sized>(
+    rpc: &impl RpcBackend,
+    transaction: &VersionedTransaction,
+    signers: &T,
+    config: &SendConfig,
+) -> Result<Signature> {
+    let mut message = transaction.message.clone();
+    let mut last_signature = Signature::default();
+
+    for _ in 0..=config.max_retries {
+        let (blockhash, last_valid_block_height) = rpc
+            .get_latest_blockhash_with_commitment(config.commitment)
+            .await?;
+        message.set_recent_blockhash(blockhash);
+        let transaction = VersionedTransaction::try_new(message.clone(), signers)?;
+        last_signature = transaction.signatures[0];
+
+        match rpc.send_transaction(&transaction).await {
+            Ok(_) => {}
+            Err(err) => match preflight_error(&err) {
+                Some((TransactionError::BlockhashNotFound, _)) => continue,
+                Some((err, logs)) => {
+                    return Err(TransactionFailure::new(&transaction, None, err, logs).into())
+                }
+                None => return Err(err),
+            },
+        }
+
+        loop {
+            let expired = rpc.get_block_height(config.commitment).await? > last_valid_block_height;
+            // Checked after the block height, so a transaction landing before expiry is never
+            // signed and sent again.
+            let status = rpc
+                .get_signature_status(&last_signature, config.commitment)
+                .await?;
+            match status {
+                Some(Ok(())) => return Ok(last_signature),
+                Some(Err(err)) => {
+                    let logs = rpc
+                        .get_transaction_logs(&last_signature, config.commitment)
+                        .await?
+                        .unwrap_or_default();
+                    return Err(TransactionFailure::new(
+                        &transaction,
+                        Some(last_signature),
+                        err,
+                        logs,
+                    )
+                    .into());
+                }
+                None if expired => break,
+                None => tokio::time::sleep(config.poll_interval).await,
+            }
+        }
+    }
+
+    Err(ClientError::BlockhashExpired(last_signature))
+}
+
+/// Error and log lines of a transaction rejected by its preflight simulation.
+fn preflight_error(err: &ClientError) -> Option<(TransactionError, Vec<String>)> {
+    let ClientError::Rpc(err) = err else {
+        return None;
+    };
+
+    match err.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => result
+            .err
+            .clone()
+            .map(|err| (err, result.logs.clone().unwrap_or_default())),
+        ClientErrorKind::TransactionError(err) => Some((err.clone(), vec![])),
+        _ => None,
+    }
+}
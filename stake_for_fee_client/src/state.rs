@@ -0,0 +1,192 @@
+use crate::{
+    error::{ClientError, Result},
+    rpc::RpcBackend,
+};
+use common::{
+    decoder::{
+        decode_full_balance_list_state, decode_top_staker_list_state, FullBalanceListState,
+        TopStakerListState,
+    },
+    dynamic_amm::{decode_lock_escrow, decode_pool, LockEscrow, Pool, PoolLpAmounts},
+    dynamic_vault::{decode_vault, Vault},
+    pda::{derive_full_balance_list_key, derive_lock_escrow_key, derive_top_staker_list_key},
+    preview::PreviewState,
+};
+use m3m3::{FeeVault, FeeVaultAccount};
+use solana_sdk::{
+    account::{from_account, Account},
+    clock::Clock,
+    program_pack::{IsInitialized, Pack},
+    pubkey::Pubkey,
+    sysvar,
+};
+use spl_token::state::{Account as TokenAccount, Mint};
+
+/// Maximum number of accounts per `getMultipleAccounts` request.
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Every account a fee vault's instructions read, decoded. Same as the TS `AccountStates`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountStates {
+    pub fee_vault: FeeVault,
+    pub top_staker_list: TopStakerListState,
+    pub full_balance_list: FullBalanceListState,
+    pub pool: Pool,
+    pub a_vault: Vault,
+    pub b_vault: Vault,
+    pub a_vault_lp: TokenAccount,
+    pub b_vault_lp: TokenAccount,
+    pub lock_escrow: LockEscrow,
+    pub token_a_mint: Mint,
+    pub token_b_mint: Mint,
+    pub a_vault_lp_mint: Mint,
+    pub b_vault_lp_mint: Mint,
+    pub pool_lp_mint: Mint,
+    pub clock: Clock,
+}
+
+impl AccountStates {
+    /// Fetch the states of `vault` in three batched `getMultipleAccounts` rounds, or four when
+    /// `pool` is not given, as the pool is then read from the fee vault first.
+    pub async fn fetch(rpc: &impl RpcBackend, vault: Pubkey, pool: Option<Pubkey>) -> Result<Self> {
+        let top_staker_list_key = derive_top_staker_list_key(vault);
+        let full_balance_list_key = derive_full_balance_list_key(vault);
+
+        let mut keys = vec![
+            vault,
+            top_staker_list_key,
+            full_balance_list_key,
+            sysvar::clock::ID,
+        ];
+        if let Some(pool) = pool {
+            keys.extend([pool, derive_lock_escrow_key(pool, vault)]);
+        }
+        let accounts = get_multiple_accounts(rpc, &keys).await?;
+
+        let fee_vault = decode(vault, &accounts[0], |data| {
+            Ok(FeeVaultAccount::deserialize(data)?.0)
+        })?;
+        let top_staker_list = decode(top_staker_list_key, &accounts[1], |data| {
+            decode_top_staker_list_state(&fee_vault, data)
+        })?;
+        let full_balance_list = decode(
+            full_balance_list_key,
+            &accounts[2],
+            decode_full_balance_list_state,
+        )?;
+        let clock = decode_clock(&accounts[3])?;
+
+        let pool_accounts = match pool {
+            Some(_) => accounts[4..].to_vec(),
+            None => get_multiple_accounts(rpc, &[fee_vault.pool, fee_vault.lock_escrow]).await?,
+        };
+        let pool = decode(fee_vault.pool, &pool_accounts[0], decode_pool)?;
+        let lock_escrow = decode(fee_vault.lock_escrow, &pool_accounts[1], decode_lock_escrow)?;
+
+        let keys = [
+            pool.a_vault,
+            pool.b_vault,
+            pool.a_vault_lp,
+            pool.b_vault_lp,
+            pool.token_a_mint,
+            pool.token_b_mint,
+            pool.lp_mint,
+        ];
+        let accounts = get_multiple_accounts(rpc, &keys).await?;
+        let a_vault = decode(keys[0], &accounts[0], decode_vault)?;
+        let b_vault = decode(keys[1], &accounts[1], decode_vault)?;
+        let a_vault_lp = unpack::<TokenAccount>(keys[2], &accounts[2])?;
+        let b_vault_lp = unpack::<TokenAccount>(keys[3], &accounts[3])?;
+        let token_a_mint = unpack::<Mint>(keys[4], &accounts[4])?;
+        let token_b_mint = unpack::<Mint>(keys[5], &accounts[5])?;
+        let pool_lp_mint = unpack::<Mint>(keys[6], &accounts[6])?;
+
+        let keys = [a_vault.lp_mint, b_vault.lp_mint];
+        let accounts = get_multiple_accounts(rpc, &keys).await?;
+        let a_vault_lp_mint = unpack::<Mint>(keys[0], &accounts[0])?;
+        let b_vault_lp_mint = unpack::<Mint>(keys[1], &accounts[1])?;
+
+        Ok(Self {
+            fee_vault,
+            top_staker_list,
+            full_balance_list,
+            pool,
+            a_vault,
+            b_vault,
+            a_vault_lp,
+            b_vault_lp,
+            lock_escrow,
+            token_a_mint,
+            token_b_mint,
+            a_vault_lp_mint,
+            b_vault_lp_mint,
+            pool_lp_mint,
+            clock,
+        })
+    }
+
+    pub fn is_stake_token_a(&self) -> bool {
+        self.fee_vault.stake_mint == self.pool.token_a_mint
+    }
+
+    pub fn stake_mint(&self) -> &Mint {
+        if self.is_stake_token_a() {
+            &self.token_a_mint
+        } else {
+            &self.token_b_mint
+        }
+    }
+
+    pub fn lp_amounts(&self) -> PoolLpAmounts {
+        PoolLpAmounts {
+            a_vault_lp_amount: self.a_vault_lp.amount,
+            b_vault_lp_amount: self.b_vault_lp.amount,
+            a_vault_lp_supply: self.a_vault_lp_mint.supply,
+            b_vault_lp_supply: self.b_vault_lp_mint.supply,
+            pool_lp_supply: self.pool_lp_mint.supply,
+        }
+    }
+
+    pub fn preview_state(&self) -> PreviewState<'_> {
+        PreviewState {
+            fee_vault: &self.fee_vault,
+            top_staker_list: &self.top_staker_list,
+            full_balance_list: &self.full_balance_list,
+        }
+    }
+}
+
+/// `getMultipleAccounts` split into requests of at most [`MAX_MULTIPLE_ACCOUNTS`] keys.
+pub async fn get_multiple_accounts(
+    rpc: &impl RpcBackend,
+    keys: &[Pubkey],
+) -> Result<Vec<Option<Account>>> {
+    let mut accounts = Vec::with_capacity(keys.len());
+    for chunk in keys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        accounts.extend(rpc.get_multiple_accounts(chunk).await?);
+    }
+    Ok(accounts)
+}
+
+pub(crate) fn decode_clock(account: &Option<Account>) -> Result<Clock> {
+    from_account::<Clock, _>(required(sysvar::clock::ID, account)?)
+        .ok_or(ClientError::InvalidAccountData(sysvar::clock::ID))
+}
+
+pub(crate) fn required(key: Pubkey, account: &Option<Account>) -> Result<&Account> {
+    account.as_ref().ok_or(ClientError::AccountNotFound(key))
+}
+
+pub(crate) fn decode<T>(
+    key: Pubkey,
+    account: &Option<Account>,
+    decoder: impl FnOnce(&[u8]) -> std::io::Result<T>,
+) -> Result<T> {
+    decoder(&required(key, account)?.data).map_err(|_| ClientError::InvalidAccountData(key))
+}
+
+pub(crate) fn unpack<T: Pack + IsInitialized>(key: Pubkey, account: &Option<Account>) -> Result<T> {
+    decode(key, account, |data| {
+        T::unpack(data).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    })
+}
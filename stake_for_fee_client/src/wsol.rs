@@ -0,0 +1,39 @@
+use crate::error::{ClientError, Result};
+use common::{constants::TOKEN_PROGRAM_ID, pda::derive_associated_token_key};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_instruction};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use spl_token::{
+    instruction::{close_account, sync_native},
+    native_mint,
+};
+
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == native_mint::ID
+}
+
+/// Move `amount` lamports of the owner into its wSOL ATA, creating it if missing.
+pub fn wrap_sol_instructions(owner: Pubkey, amount: u64) -> Result<Vec<Instruction>> {
+    let wsol_ata = derive_associated_token_key(owner, native_mint::ID);
+
+    Ok(vec![
+        create_associated_token_account_idempotent(
+            &owner,
+            &owner,
+            &native_mint::ID,
+            &TOKEN_PROGRAM_ID,
+        ),
+        system_instruction::transfer(&owner, &wsol_ata, amount),
+        sync_native(&TOKEN_PROGRAM_ID, &wsol_ata).map_err(invalid_instruction)?,
+    ])
+}
+
+/// Close the owner's wSOL ATA, returning its whole balance as SOL. Same as the TS
+/// `unwrapSOLInstruction`.
+pub fn unwrap_sol_instruction(owner: Pubkey) -> Result<Instruction> {
+    let wsol_ata = derive_associated_token_key(owner, native_mint::ID);
+    close_account(&TOKEN_PROGRAM_ID, &wsol_ata, &owner, &owner, &[]).map_err(invalid_instruction)
+}
+
+fn invalid_instruction(err: impl std::error::Error + Send + Sync + 'static) -> ClientError {
+    ClientError::Instruction(std::io::Error::new(std::io::ErrorKind::InvalidInput, err))
+}
@@ -0,0 +1,168 @@
+mod utils;
+
+use m3m3::{ClaimFeeIxArgs, ClaimFeeIxData};
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount, packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey, transaction::VersionedTransaction,
+};
+use spl_token::native_mint;
+use stake_for_fee_client::{
+    plan_batch_claim, wsol::unwrap_sol_instruction, ClaimPlanConfig, InMemoryRpc,
+};
+use stake_for_fee_simulator::Simulator;
+use utils::{
+    decompile, simulator, simulator_with_mints, staker, write_stake_escrow, write_vault, START,
+};
+
+const CURRENT_TIME: i64 = START + 700;
+
+/// A vault where `owner` is in the top list and has fees released to it.
+fn vault_with_fees(rpc: &InMemoryRpc, mut sim: Simulator, owner: Pubkey) -> Simulator {
+    sim.initialize_stake_escrow(owner, START).unwrap();
+    sim.stake(owner, 1_000_000, START).unwrap();
+    staker(&mut sim, 3_000_000, START);
+    sim.accrue_lock_escrow_fee(500_000, 700_000).unwrap();
+    sim.claim_fee_crank(START + 400).unwrap();
+    write_vault(rpc, &sim, CURRENT_TIME);
+    write_stake_escrow(rpc, &sim, owner);
+    sim
+}
+
+fn serialized_size(transaction: &VersionedTransaction) -> usize {
+    1 + transaction.signatures.len() * 64 + transaction.message.serialize().len()
+}
+
+#[tokio::test]
+async fn claims_are_packed_per_vault_with_pending_fee() {
+    let rpc = InMemoryRpc::new();
+    let owner = Pubkey::new_unique();
+    let mut sims = [
+        vault_with_fees(&rpc, simulator(2, 10), owner),
+        vault_with_fees(
+            &rpc,
+            simulator_with_mints(2, 10, Pubkey::new_unique(), native_mint::ID),
+            owner,
+        ),
+        vault_with_fees(&rpc, simulator(2, 10), owner),
+    ];
+    // Out of the top list, so without fees.
+    let mut no_fee_sim = simulator(1, 10);
+    staker(&mut no_fee_sim, 1_000, START);
+    no_fee_sim.initialize_stake_escrow(owner, START).unwrap();
+    no_fee_sim.stake(owner, 100, START).unwrap();
+    write_vault(&rpc, &no_fee_sim, CURRENT_TIME);
+    write_stake_escrow(&rpc, &no_fee_sim, owner);
+
+    // Without lookup tables a claim takes a whole transaction.
+    let plan = plan_batch_claim(&rpc, owner, &ClaimPlanConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(plan.current_time, CURRENT_TIME);
+    assert_eq!(plan.batches.len(), 3);
+    assert!(plan.claims().all(|claim| claim.vault != no_fee_sim.vault()));
+    for batch in &plan.batches {
+        assert_eq!(batch.claims.len(), 1);
+        assert!(serialized_size(&batch.transaction) <= PACKET_DATA_SIZE);
+    }
+
+    // The SOL claim creates and closes the wSOL ATA.
+    let sol_vault = sims[1].vault();
+    let sol_batch = plan
+        .batches
+        .iter()
+        .find(|batch| batch.claims[0].vault == sol_vault)
+        .unwrap();
+    let instructions = decompile(&sol_batch.transaction);
+    assert_eq!(instructions.len(), 4);
+    assert_eq!(instructions[1].program_id, spl_associated_token_account::ID);
+    assert_eq!(instructions[2].program_id, m3m3::ID);
+    assert_eq!(
+        instructions[2].data,
+        ClaimFeeIxData(ClaimFeeIxArgs {
+            max_fee: sol_batch.claims[0].max_fee,
+        })
+        .try_to_vec()
+        .unwrap()
+    );
+    assert_eq!(
+        instructions[3].data,
+        unwrap_sol_instruction(owner).unwrap().data
+    );
+
+    // Every vault account in a table, so the claims share a transaction.
+    let mut addresses = vec![];
+    for batch in &plan.batches {
+        for key in &batch.transaction.message.static_account_keys()[1..] {
+            if !addresses.contains(key) {
+                addresses.push(*key);
+            }
+        }
+    }
+    let config = ClaimPlanConfig {
+        lookup_tables: vec![AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses,
+        }],
+        ..ClaimPlanConfig::default()
+    };
+    let packed = plan_batch_claim(&rpc, owner, &config).await.unwrap();
+    assert_eq!(packed.batches.len(), 1);
+    assert_eq!(packed.batches[0].claims.len(), 3);
+    assert!(serialized_size(&packed.batches[0].transaction) <= PACKET_DATA_SIZE);
+    assert_eq!(
+        packed.claims().collect::<Vec<_>>(),
+        plan.claims().collect::<Vec<_>>()
+    );
+
+    // The compute budget splits them again.
+    let config = ClaimPlanConfig {
+        compute_units_per_claim: 600_000,
+        ..config
+    };
+    let split = plan_batch_claim(&rpc, owner, &config).await.unwrap();
+    assert_eq!(
+        split
+            .batches
+            .iter()
+            .map(|batch| batch.claims.len())
+            .collect::<Vec<_>>(),
+        vec![2, 1]
+    );
+
+    // Along with the two quote token ATAs and the wSOL close, two claims no longer fit.
+    let config = ClaimPlanConfig {
+        compute_units_per_claim: 670_000,
+        ..config
+    };
+    let split = plan_batch_claim(&rpc, owner, &config).await.unwrap();
+    assert_eq!(split.batches.len(), 3);
+
+    // The expected amounts are what the claims pay out.
+    for claim in plan.claims() {
+        let sim = sims
+            .iter_mut()
+            .find(|sim| sim.vault() == claim.vault)
+            .unwrap();
+        let stake_amount = sim.stake_escrow(owner).unwrap().stake_amount;
+        let claimed = sim.claim_fee(owner, claim.max_fee, CURRENT_TIME).unwrap();
+        assert!(claimed > 0);
+        assert_eq!(claim.expected_quote_fee, claimed);
+        assert_eq!(claim.max_fee, claimed);
+        assert_eq!(
+            claim.expected_restaked_fee,
+            sim.stake_escrow(owner).unwrap().stake_amount - stake_amount
+        );
+    }
+    assert_eq!(
+        plan.expected_quote_fee(native_mint::ID),
+        plan.claims()
+            .find(|claim| claim.vault == sol_vault)
+            .unwrap()
+            .expected_quote_fee
+    );
+
+    let empty = plan_batch_claim(&rpc, Pubkey::new_unique(), &ClaimPlanConfig::default())
+        .await
+        .unwrap();
+    assert!(empty.batches.is_empty());
+}
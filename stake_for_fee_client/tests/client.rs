@@ -0,0 +1,298 @@
+mod utils;
+
+use common::{
+    constants::{DYNAMIC_AMM_PROGRAM_ID, DYNAMIC_VAULT_PROGRAM_ID, TOKEN_PROGRAM_ID},
+    pda::{
+        derive_associated_token_key, derive_full_balance_list_key, derive_lock_escrow_key,
+        derive_m3m3_event_authority_key, derive_stake_escrow_key, derive_top_staker_list_key,
+    },
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::VersionedTransaction,
+};
+use stake_for_fee_client::{ClientError, InMemoryRpc, StakeForFee};
+use stake_for_fee_simulator::Simulator;
+use std::sync::Arc;
+use utils::{decompile, simulator, staker, write_stake_escrow, write_vault, PoolKeys, START};
+
+/// Two top stakers of 300 and 200, and `owner` and a smaller staker outside the top list.
+struct Setup {
+    sim: Simulator,
+    keys: PoolKeys,
+    client: StakeForFee<InMemoryRpc>,
+    top_stakers: [Pubkey; 2],
+    owner: Pubkey,
+    smallest: Pubkey,
+}
+
+impl Setup {
+    async fn new() -> Self {
+        let mut sim = simulator(2, 10);
+        let top_stakers = [staker(&mut sim, 300, START), staker(&mut sim, 200, START)];
+        let owner = staker(&mut sim, 100, START);
+        let smallest = staker(&mut sim, 50, START);
+
+        let rpc = Arc::new(InMemoryRpc::new());
+        let keys = write_vault(&rpc, &sim, START);
+        for staker in top_stakers.iter().chain([&owner, &smallest]) {
+            write_stake_escrow(&rpc, &sim, *staker);
+        }
+        let client = StakeForFee::from_vault(rpc, sim.vault()).await.unwrap();
+
+        Self {
+            sim,
+            keys,
+            client,
+            top_stakers,
+            owner,
+            smallest,
+        }
+    }
+
+    fn escrow(&self, owner: Pubkey) -> Pubkey {
+        derive_stake_escrow_key(self.sim.vault(), owner)
+    }
+
+    /// Accounts from `pool` to `program`, shared by `stake`, `claim_fee` and `cancel_unstake`.
+    fn pool_accounts(&self) -> Vec<Pubkey> {
+        let keys = &self.keys;
+        vec![
+            keys.pool,
+            keys.lp_mint,
+            keys.lock_escrow,
+            keys.escrow_vault,
+            keys.a_token_vault,
+            keys.b_token_vault,
+            keys.a_vault,
+            keys.b_vault,
+            keys.a_vault_lp,
+            keys.b_vault_lp,
+            keys.a_vault_lp_mint,
+            keys.b_vault_lp_mint,
+            DYNAMIC_AMM_PROGRAM_ID,
+            DYNAMIC_VAULT_PROGRAM_ID,
+            TOKEN_PROGRAM_ID,
+            derive_m3m3_event_authority_key(),
+            m3m3::ID,
+        ]
+    }
+
+    /// The replaceable top stakers, smallest first.
+    fn replaceable_top_stakers(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.escrow(self.top_stakers[1]), false),
+            AccountMeta::new(self.escrow(self.top_stakers[0]), false),
+        ]
+    }
+}
+
+/// The stake-for-fee instruction of a transaction, with its accounts split into the ones named
+/// by the instruction and the remaining ones.
+fn m3m3_instruction(
+    transaction: impl Into<VersionedTransaction>,
+    named_account_count: usize,
+) -> (Vec<Pubkey>, Vec<AccountMeta>) {
+    let instructions: Vec<Instruction> = decompile(&transaction.into())
+        .into_iter()
+        .filter(|instruction| instruction.program_id == m3m3::ID)
+        .collect();
+    assert_eq!(instructions.len(), 1);
+
+    let mut accounts = instructions[0].accounts.clone();
+    let remaining_accounts = accounts.split_off(named_account_count);
+    (
+        accounts.into_iter().map(|account| account.pubkey).collect(),
+        remaining_accounts,
+    )
+}
+
+#[tokio::test]
+async fn account_states_are_fetched_in_batched_rounds() {
+    let mut sim = simulator(2, 10);
+    staker(&mut sim, 100, START);
+    let vault = sim.vault();
+
+    let rpc = Arc::new(InMemoryRpc::new());
+    let keys = write_vault(&rpc, &sim, START);
+
+    let client = StakeForFee::create(rpc.clone(), keys.pool).await.unwrap();
+    assert_eq!(client.account_states.fee_vault.pool, keys.pool);
+    let requests = rpc.multiple_accounts_requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(
+        requests[0],
+        vec![
+            vault,
+            derive_top_staker_list_key(vault),
+            derive_full_balance_list_key(vault),
+            sysvar::clock::ID,
+            keys.pool,
+            derive_lock_escrow_key(keys.pool, vault),
+        ]
+    );
+    assert_eq!(
+        requests[2],
+        vec![keys.a_vault_lp_mint, keys.b_vault_lp_mint]
+    );
+
+    // Without the pool, it is read from the fee vault before its accounts are fetched.
+    let rpc = Arc::new(InMemoryRpc::new());
+    write_vault(&rpc, &sim, START);
+    let from_vault = StakeForFee::from_vault(rpc.clone(), vault).await.unwrap();
+    let requests = rpc.multiple_accounts_requests();
+    assert_eq!(requests.len(), 4);
+    assert_eq!(requests[0].len(), 4);
+    assert_eq!(
+        requests[1],
+        vec![
+            from_vault.account_states.fee_vault.pool,
+            from_vault.account_states.fee_vault.lock_escrow,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn stake_accounts_are_in_program_order() {
+    let setup = Setup::new().await;
+    let fee_vault = &setup.client.account_states.fee_vault;
+    let owner = setup.owner;
+
+    let transaction = setup.client.stake(100, owner, 2).await.unwrap();
+    let (accounts, remaining_accounts) = m3m3_instruction(transaction, 26);
+    let mut expected = vec![
+        setup.sim.vault(),
+        fee_vault.stake_token_vault,
+        fee_vault.quote_token_vault,
+        fee_vault.top_staker_list,
+        fee_vault.full_balance_list,
+        setup.escrow(owner),
+        setup.escrow(setup.smallest),
+        derive_associated_token_key(owner, fee_vault.stake_mint),
+        owner,
+    ];
+    expected.extend(setup.pool_accounts());
+    assert_eq!(accounts, expected);
+    assert_eq!(remaining_accounts, setup.replaceable_top_stakers());
+
+    let transaction = setup.client.stake(100, owner, 0).await.unwrap();
+    assert!(m3m3_instruction(transaction, 26).1.is_empty());
+
+    assert!(matches!(
+        setup.client.stake(100, owner, 3).await,
+        Err(ClientError::InvalidReplaceableTopStakerCount(3))
+    ));
+}
+
+#[tokio::test]
+async fn claim_fee_accounts_are_in_program_order() {
+    let setup = Setup::new().await;
+    let fee_vault = &setup.client.account_states.fee_vault;
+    let owner = setup.owner;
+
+    let transaction = setup.client.claim_fee(owner, u64::MAX).await.unwrap();
+    let (accounts, remaining_accounts) = m3m3_instruction(transaction, 26);
+    let mut expected = vec![
+        setup.sim.vault(),
+        fee_vault.top_staker_list,
+        fee_vault.full_balance_list,
+        setup.escrow(owner),
+        setup.escrow(setup.smallest),
+        derive_associated_token_key(owner, fee_vault.quote_mint),
+        fee_vault.stake_token_vault,
+        fee_vault.quote_token_vault,
+        owner,
+    ];
+    expected.extend(setup.pool_accounts());
+    assert_eq!(accounts, expected);
+    assert_eq!(remaining_accounts, setup.replaceable_top_stakers());
+}
+
+#[tokio::test]
+async fn cancel_unstake_accounts_are_in_program_order() {
+    let setup = Setup::new().await;
+    let fee_vault = &setup.client.account_states.fee_vault;
+    let owner = setup.owner;
+    let unstake = Pubkey::new_unique();
+
+    let transaction = setup.client.cancel_unstake(unstake, owner).await.unwrap();
+    let (accounts, remaining_accounts) = m3m3_instruction(transaction, 26);
+    let mut expected = vec![
+        unstake,
+        setup.escrow(owner),
+        setup.escrow(setup.smallest),
+        fee_vault.top_staker_list,
+        fee_vault.full_balance_list,
+        setup.sim.vault(),
+        fee_vault.stake_token_vault,
+        fee_vault.quote_token_vault,
+        owner,
+    ];
+    expected.extend(setup.pool_accounts());
+    assert_eq!(accounts, expected);
+    // Up to three, of which the top list only has two.
+    assert_eq!(remaining_accounts, setup.replaceable_top_stakers());
+
+    // A top staker needs no replaceable top staker.
+    let transaction = setup
+        .client
+        .cancel_unstake(unstake, setup.top_stakers[0])
+        .await
+        .unwrap();
+    assert!(m3m3_instruction(transaction, 26).1.is_empty());
+}
+
+#[tokio::test]
+async fn withdraw_accounts_are_in_program_order() {
+    let setup = Setup::new().await;
+    let fee_vault = &setup.client.account_states.fee_vault;
+    let owner = setup.owner;
+    let unstake = Pubkey::new_unique();
+
+    let transaction = setup.client.withdraw(unstake, owner).await.unwrap();
+    let (accounts, remaining_accounts) = m3m3_instruction(transaction, 9);
+    assert_eq!(
+        accounts,
+        vec![
+            unstake,
+            setup.escrow(owner),
+            fee_vault.stake_token_vault,
+            setup.sim.vault(),
+            derive_associated_token_key(owner, fee_vault.stake_mint),
+            owner,
+            TOKEN_PROGRAM_ID,
+            derive_m3m3_event_authority_key(),
+            m3m3::ID,
+        ]
+    );
+    assert!(remaining_accounts.is_empty());
+}
+
+#[tokio::test]
+async fn unstake_builds_the_prepared_transaction() {
+    let setup = Setup::new().await;
+    let owner = setup.top_stakers[0];
+    let unstake = Keypair::new();
+
+    let transaction = setup.client.unstake(100, &unstake, owner).await.unwrap();
+    let prepared = setup
+        .client
+        .prepare_unstake(100, owner, Some(unstake.insecure_clone()))
+        .await
+        .unwrap();
+    assert_eq!(decompile(&transaction), decompile(&prepared.transaction));
+    assert_eq!(prepared.signers, vec![owner, unstake.pubkey()]);
+
+    // A top staker passes the largest stakers outside the top list as promotion candidates.
+    let (_, remaining_accounts) = m3m3_instruction(transaction, 26);
+    assert_eq!(
+        remaining_accounts,
+        vec![
+            AccountMeta::new(setup.escrow(setup.owner), false),
+            AccountMeta::new(setup.escrow(setup.smallest), false),
+        ]
+    );
+}
@@ -0,0 +1,79 @@
+mod utils;
+
+use solana_sdk::pubkey::Pubkey;
+use stake_for_fee_client::{fetch_portfolio, InMemoryRpc};
+use utils::{simulator, staker, write_stake_escrow, write_unstake, write_vault, START};
+
+#[tokio::test]
+async fn portfolio_values_every_stake_escrow_of_owner() {
+    let rpc = InMemoryRpc::new();
+    let current_time = START + 700;
+
+    // In the top list of the first vault, with fees released to it.
+    let mut top_sim = simulator(2, 10);
+    let owner = staker(&mut top_sim, 1_234_567, START);
+    staker(&mut top_sim, 7_654_321, START);
+    top_sim.accrue_lock_escrow_fee(1_000_003, 77_777).unwrap();
+    top_sim.claim_fee_crank(START + 400).unwrap();
+    let unstake = Pubkey::new_unique();
+    top_sim
+        .request_unstake(owner, unstake, 1_000, START + 500)
+        .unwrap();
+
+    // Pushed out of the top list of the second vault.
+    let mut out_sim = simulator(1, 10);
+    out_sim.initialize_stake_escrow(owner, START).unwrap();
+    out_sim.stake(owner, 100, START).unwrap();
+    staker(&mut out_sim, 1_000, START);
+
+    let mut keys = vec![];
+    for sim in [&top_sim, &out_sim] {
+        keys.push(write_vault(&rpc, sim, current_time));
+        write_stake_escrow(&rpc, sim, owner);
+    }
+    write_unstake(&rpc, &top_sim, owner, unstake);
+    // Someone else's escrow in the same vault.
+    let other = top_sim.top_staker_list().stakers[0].owner;
+    let other = if other == owner {
+        top_sim.top_staker_list().stakers[1].owner
+    } else {
+        other
+    };
+    write_stake_escrow(&rpc, &top_sim, other);
+
+    let portfolio = fetch_portfolio(&rpc, owner).await.unwrap();
+    assert_eq!(portfolio.current_time, current_time);
+    assert_eq!(portfolio.positions.len(), 2);
+
+    let position = portfolio.position(top_sim.vault()).unwrap();
+    assert!(position.in_top_list);
+    assert_eq!(position.stake_amount, 1_234_567 - 1_000);
+    assert_eq!(position.pool.a_vault, keys[0].a_vault);
+    assert_eq!(position.configuration().unstake_lock_duration, 3_600);
+    assert_eq!(position.unstakes.locked.len(), 1);
+    assert_eq!(position.unstakes.locked[0].unstake, unstake);
+    assert_eq!(position.unstakes.locked[0].release_at, START + 500 + 3_600);
+    assert!(position.unstakes.withdrawable.is_empty());
+
+    // The pending fees are what `claim_fee` pays out.
+    let (pending_fee_a, pending_fee_b) = (position.pending_fee_a, position.pending_fee_b);
+    assert!(pending_fee_a > 0 && pending_fee_b > 0);
+    let stake_amount = top_sim.stake_escrow(owner).unwrap().stake_amount;
+    let claimed_b = top_sim.claim_fee(owner, u64::MAX, current_time).unwrap();
+    let claimed_a = top_sim.stake_escrow(owner).unwrap().stake_amount - stake_amount;
+    assert_eq!((pending_fee_a, pending_fee_b), (claimed_a, claimed_b));
+    assert_eq!(position.pending_quote_fee(), claimed_b);
+    assert_eq!(
+        top_sim.stake_escrow(owner).unwrap().fee_b_claimed_amount,
+        position.fee_b_claimed_amount + u128::from(claimed_b)
+    );
+
+    let position = portfolio.position(out_sim.vault()).unwrap();
+    assert!(!position.in_top_list);
+    assert_eq!(position.stake_amount, 100);
+    assert_eq!((position.pending_fee_a, position.pending_fee_b), (0, 0));
+    assert_eq!(position.unstakes.total_pending_amount, 0);
+
+    let empty = fetch_portfolio(&rpc, Pubkey::new_unique()).await.unwrap();
+    assert!(empty.positions.is_empty());
+}
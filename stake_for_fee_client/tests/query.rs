@@ -0,0 +1,226 @@
+use m3m3::{
+    FeeVault, FeeVaultAccount, StakeEscrow, StakeEscrowAccount, Unstake, UnstakeAccount,
+    FEE_VAULT_ACCOUNT_DISCM, STAKE_ESCROW_ACCOUNT_DISCM, UNSTAKE_ACCOUNT_DISCM,
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use stake_for_fee_client::{AccountQuery, InMemoryRpc, ProgramAccount};
+
+fn zeroed<T: ProgramAccount>() -> T {
+    let mut data = vec![0u8; T::LEN];
+    data[..8].copy_from_slice(&T::DISCRIMINATOR);
+    T::decode(&data).unwrap()
+}
+
+fn program_account(data: Vec<u8>) -> Account {
+    Account {
+        lamports: 1_000_000,
+        data,
+        owner: m3m3::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn sorted_keys<T>(accounts: &[(Pubkey, T)]) -> Vec<Pubkey> {
+    let mut keys: Vec<Pubkey> = accounts.iter().map(|(key, _)| *key).collect();
+    keys.sort();
+    keys
+}
+
+fn sorted(mut keys: Vec<Pubkey>) -> Vec<Pubkey> {
+    keys.sort();
+    keys
+}
+
+#[test]
+fn layout_sizes_match_serialized_accounts() {
+    assert_eq!(
+        FeeVaultAccount(zeroed()).try_to_vec().unwrap().len(),
+        FeeVault::LEN
+    );
+    assert_eq!(
+        StakeEscrowAccount(zeroed()).try_to_vec().unwrap().len(),
+        StakeEscrow::LEN
+    );
+    assert_eq!(
+        UnstakeAccount(zeroed()).try_to_vec().unwrap().len(),
+        Unstake::LEN
+    );
+    assert_eq!(FeeVault::DISCRIMINATOR, FEE_VAULT_ACCOUNT_DISCM);
+    assert_eq!(StakeEscrow::DISCRIMINATOR, STAKE_ESCROW_ACCOUNT_DISCM);
+    assert_eq!(Unstake::DISCRIMINATOR, UNSTAKE_ACCOUNT_DISCM);
+}
+
+#[tokio::test]
+async fn queries_filter_on_each_field() {
+    let rpc = InMemoryRpc::new();
+    let (pool, stake_mint, quote_mint, creator) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+
+    let mut fee_vaults = vec![];
+    for idx in 0..4 {
+        let mut fee_vault: FeeVault = zeroed();
+        fee_vault.pool = if idx == 0 { pool } else { Pubkey::new_unique() };
+        fee_vault.stake_mint = if idx <= 1 {
+            stake_mint
+        } else {
+            Pubkey::new_unique()
+        };
+        fee_vault.quote_mint = if idx <= 2 {
+            quote_mint
+        } else {
+            Pubkey::new_unique()
+        };
+        fee_vault.creator = if idx == 3 {
+            creator
+        } else {
+            Pubkey::new_unique()
+        };
+        // Fields around the filtered ones must not shift them.
+        fee_vault.top_staker_info.effective_stake_amount = u64::MAX;
+        fee_vault.created_at = -1;
+
+        let key = Pubkey::new_unique();
+        rpc.set_account(
+            key,
+            program_account(FeeVaultAccount(fee_vault).try_to_vec().unwrap()),
+        );
+        fee_vaults.push(key);
+    }
+
+    let by_pool = AccountQuery::<FeeVault>::new()
+        .pool(pool)
+        .fetch(&rpc)
+        .await
+        .unwrap();
+    assert_eq!(sorted_keys(&by_pool), vec![fee_vaults[0]]);
+    assert_eq!(by_pool[0].1.pool, pool);
+
+    let by_stake_mint = AccountQuery::<FeeVault>::new()
+        .stake_mint(stake_mint)
+        .fetch(&rpc)
+        .await
+        .unwrap();
+    assert_eq!(
+        sorted_keys(&by_stake_mint),
+        sorted(fee_vaults[..2].to_vec())
+    );
+
+    let by_quote_mint = AccountQuery::<FeeVault>::new()
+        .quote_mint(quote_mint)
+        .stake_mint(stake_mint)
+        .fetch(&rpc)
+        .await
+        .unwrap();
+    assert_eq!(
+        sorted_keys(&by_quote_mint),
+        sorted(fee_vaults[..2].to_vec())
+    );
+
+    let by_creator = AccountQuery::<FeeVault>::new()
+        .creator(creator)
+        .fetch(&rpc)
+        .await
+        .unwrap();
+    assert_eq!(sorted_keys(&by_creator), vec![fee_vaults[3]]);
+
+    let (owner, vault) = (Pubkey::new_unique(), fee_vaults[0]);
+    let mut stake_escrows = vec![];
+    for (escrow_owner, escrow_vault, in_top_list) in [
+        (owner, vault, 1),
+        (owner, fee_vaults[1], 0),
+        (Pubkey::new_unique(), vault, 0),
+    ] {
+        let mut stake_escrow: StakeEscrow = zeroed();
+        stake_escrow.owner = escrow_owner;
+        stake_escrow.vault = escrow_vault;
+        stake_escrow.full_balance_index = u64::MAX;
+        stake_escrow.stake_amount = u64::MAX;
+        stake_escrow.in_top_list = in_top_list;
+
+        let key = Pubkey::new_unique();
+        rpc.set_account(
+            key,
+            program_account(StakeEscrowAccount(stake_escrow).try_to_vec().unwrap()),
+        );
+        stake_escrows.push(key);
+    }
+
+    let by_owner = AccountQuery::<StakeEscrow>::new()
+        .owner(owner)
+        .fetch(&rpc)
+        .await
+        .unwrap();
+    assert_eq!(sorted_keys(&by_owner), sorted(stake_escrows[..2].to_vec()));
+
+    let by_vault = AccountQuery::<StakeEscrow>::new()
+        .vault(vault)
+        .fetch(&rpc)
+        .await
+        .unwrap();
+    assert_eq!(
+        sorted_keys(&by_vault),
+        sorted(vec![stake_escrows[0], stake_escrows[2]])
+    );
+
+    let out_of_top_list = AccountQuery::<StakeEscrow>::new()
+        .vault(vault)
+        .in_top_list(false)
+        .fetch(&rpc)
+        .await
+        .unwrap();
+    assert_eq!(sorted_keys(&out_of_top_list), vec![stake_escrows[2]]);
+
+    let in_top_list = AccountQuery::<StakeEscrow>::new()
+        .in_top_list(true)
+        .fetch(&rpc)
+        .await
+        .unwrap();
+    assert_eq!(sorted_keys(&in_top_list), vec![stake_escrows[0]]);
+
+    let mut unstakes = vec![];
+    for stake_escrow in [stake_escrows[0], stake_escrows[0], stake_escrows[1]] {
+        let mut unstake: Unstake = zeroed();
+        unstake.stake_escrow = stake_escrow;
+        unstake.unstake_amount = 1;
+
+        let key = Pubkey::new_unique();
+        rpc.set_account(
+            key,
+            program_account(UnstakeAccount(unstake).try_to_vec().unwrap()),
+        );
+        unstakes.push(key);
+    }
+
+    let by_stake_escrow = AccountQuery::<Unstake>::new()
+        .stake_escrow(stake_escrows[0])
+        .fetch(&rpc)
+        .await
+        .unwrap();
+    assert_eq!(
+        sorted_keys(&by_stake_escrow),
+        sorted(unstakes[..2].to_vec())
+    );
+
+    // The discriminator and size keep other account types out.
+    assert_eq!(
+        AccountQuery::<StakeEscrow>::new()
+            .fetch(&rpc)
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+    assert_eq!(
+        AccountQuery::<Unstake>::new()
+            .fetch(&rpc)
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+}
@@ -0,0 +1,154 @@
+use m3m3::STAKE_ESCROW_ACCOUNT_DISCM;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    rpc_filter::{Memcmp, RpcFilterType},
+    rpc_response::RpcKeyedAccount,
+};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    hash::Hash,
+    message::Message,
+    pubkey::Pubkey,
+    system_instruction,
+    transaction::{Transaction, VersionedTransaction},
+};
+use stake_for_fee_client::{ClientError, InMemoryRpc, RpcBackend};
+
+const STAKE_ESCROW_LEN: usize = 8 + 32 + 32 + 64;
+
+fn stake_escrow_account(owner: Pubkey, vault: Pubkey) -> Account {
+    let mut data = Vec::with_capacity(STAKE_ESCROW_LEN);
+    data.extend_from_slice(&STAKE_ESCROW_ACCOUNT_DISCM);
+    data.extend_from_slice(owner.as_ref());
+    data.extend_from_slice(vault.as_ref());
+    data.resize(STAKE_ESCROW_LEN, 0);
+
+    Account {
+        lamports: 1_000_000,
+        data,
+        owner: m3m3::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn keyed_account_json(key: Pubkey, account: &Account, encoding: UiAccountEncoding) -> String {
+    serde_json::to_string(&RpcKeyedAccount {
+        pubkey: key.to_string(),
+        account: UiAccount::encode(&key, account, encoding, None, None),
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn fixtures_are_served_and_program_accounts_filtered() {
+    let vault = Pubkey::new_unique();
+    let other_vault = Pubkey::new_unique();
+    let escrows: Vec<(Pubkey, Account)> = [vault, vault, other_vault]
+        .into_iter()
+        .map(|vault| {
+            (
+                Pubkey::new_unique(),
+                stake_escrow_account(Pubkey::new_unique(), vault),
+            )
+        })
+        .collect();
+
+    let rpc = InMemoryRpc::new();
+    // A `getProgramAccounts` dump of the first two, and a single account dump of the third.
+    let list = escrows[..2]
+        .iter()
+        .map(|(key, account)| keyed_account_json(*key, account, UiAccountEncoding::Base64))
+        .collect::<Vec<_>>()
+        .join(",");
+    rpc.load_fixture(&format!("[{}]", list)).unwrap();
+    rpc.load_fixture(&keyed_account_json(
+        escrows[2].0,
+        &escrows[2].1,
+        UiAccountEncoding::Base64,
+    ))
+    .unwrap();
+    let foreign = Pubkey::new_unique();
+    rpc.set_account(
+        foreign,
+        Account {
+            owner: Pubkey::new_unique(),
+            ..escrows[0].1.clone()
+        },
+    );
+
+    let accounts = rpc
+        .get_multiple_accounts(&[escrows[2].0, Pubkey::new_unique()])
+        .await
+        .unwrap();
+    assert_eq!(accounts, vec![Some(escrows[2].1.clone()), None]);
+
+    let all = rpc.get_program_accounts(&m3m3::ID, vec![]).await.unwrap();
+    assert_eq!(all.len(), 3);
+    assert!(all.iter().all(|(key, _)| *key != foreign));
+
+    let mut of_vault = rpc
+        .get_program_accounts(
+            &m3m3::ID,
+            vec![
+                RpcFilterType::DataSize(STAKE_ESCROW_LEN as u64),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(8 + 32, vault.to_bytes().to_vec())),
+            ],
+        )
+        .await
+        .unwrap();
+    let mut expected = escrows[..2].to_vec();
+    of_vault.sort_by_key(|(key, _)| *key);
+    expected.sort_by_key(|(key, _)| *key);
+    assert_eq!(of_vault, expected);
+
+    let wrong_size = rpc
+        .get_program_accounts(&m3m3::ID, vec![RpcFilterType::DataSize(8)])
+        .await
+        .unwrap();
+    assert!(wrong_size.is_empty());
+
+    assert!(matches!(
+        rpc.load_fixture("{\"pubkey\": \"x\"}"),
+        Err(ClientError::InvalidFixture(_))
+    ));
+}
+
+#[tokio::test]
+async fn transactions_are_recorded_and_clock_is_served() {
+    let blockhash = Hash::new_unique();
+    let rpc = InMemoryRpc::new().with_latest_blockhash(blockhash);
+    assert!(matches!(
+        rpc.get_clock().await,
+        Err(ClientError::AccountNotFound(_))
+    ));
+
+    let clock = Clock {
+        slot: 100,
+        unix_timestamp: 1_700_000_000,
+        ..Clock::default()
+    };
+    rpc.set_clock(&clock);
+    assert_eq!(rpc.get_clock().await.unwrap(), clock);
+
+    let payer = Pubkey::new_unique();
+    let message = Message::new_with_blockhash(
+        &[system_instruction::transfer(
+            &payer,
+            &Pubkey::new_unique(),
+            1,
+        )],
+        Some(&payer),
+        &rpc.get_latest_blockhash().await.unwrap(),
+    );
+    let transaction = VersionedTransaction::from(Transaction::new_unsigned(message));
+
+    let simulation = rpc.simulate_transaction(&transaction).await.unwrap();
+    assert!(simulation.err.is_none());
+    rpc.send_transaction(&transaction).await.unwrap();
+
+    assert_eq!(rpc.simulated_transactions(), vec![transaction.clone()]);
+    assert_eq!(rpc.sent_transactions(), vec![transaction.clone()]);
+    assert_eq!(*transaction.message.recent_blockhash(), blockhash);
+}
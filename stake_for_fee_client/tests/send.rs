@@ -0,0 +1,136 @@
+use m3m3::StakeForFeeError;
+use solana_sdk::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use stake_for_fee_client::{
+    compute::unsigned_transaction, send_and_confirm, ClientError, InMemoryRpc, SendConfig,
+};
+use std::time::Duration;
+
+fn transaction(payer: &Keypair, program_ids: &[Pubkey]) -> VersionedTransaction {
+    let instructions: Vec<Instruction> = program_ids
+        .iter()
+        .map(|program_id| {
+            Instruction::new_with_bytes(
+                *program_id,
+                &[],
+                vec![AccountMeta::new(payer.pubkey(), true)],
+            )
+        })
+        .collect();
+    unsigned_transaction(&instructions, payer.pubkey(), &[], Hash::default()).unwrap()
+}
+
+fn config() -> SendConfig {
+    SendConfig {
+        poll_interval: Duration::ZERO,
+        ..SendConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn transaction_is_signed_and_resent_after_blockhash_expiry() {
+    let payer = Keypair::new();
+    let blockhash = Hash::new_unique();
+    let rpc = InMemoryRpc::new().with_latest_blockhash(blockhash);
+    let transaction = transaction(&payer, &[m3m3::ID]);
+
+    let signature = send_and_confirm(&rpc, &transaction, &[&payer], &config())
+        .await
+        .unwrap();
+    let sent = rpc.sent_transactions();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].signatures, vec![signature]);
+    assert_eq!(*sent[0].message.recent_blockhash(), blockhash);
+    assert!(sent[0].verify_with_results().iter().all(|valid| *valid));
+
+    // The first send never lands, so the second one confirms.
+    let payer = Keypair::new();
+    let transaction = self::transaction(&payer, &[m3m3::ID]);
+    rpc.drop_next_transactions(1);
+    let signature = send_and_confirm(&rpc, &transaction, &[&payer], &config())
+        .await
+        .unwrap();
+    let sent = rpc.sent_transactions();
+    assert_eq!(sent.len(), 3);
+    assert_eq!(sent[2].signatures, vec![signature]);
+
+    let payer = Keypair::new();
+    let transaction = self::transaction(&payer, &[m3m3::ID]);
+    rpc.drop_next_transactions(2);
+    let config = SendConfig {
+        max_retries: 1,
+        ..config()
+    };
+    let err = send_and_confirm(&rpc, &transaction, &[&payer], &config)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::BlockhashExpired(_)));
+    assert_eq!(rpc.sent_transactions().len(), 5);
+
+    let err = send_and_confirm(&rpc, &transaction, &[&Keypair::new()], &config)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Signer(_)));
+}
+
+#[tokio::test]
+async fn transaction_landing_at_expiry_is_not_sent_again() {
+    let payer = Keypair::new();
+    let rpc = InMemoryRpc::new();
+    let transaction = transaction(&payer, &[m3m3::ID]);
+
+    // Lands at the first block past the 150 blocks its blockhash is valid for.
+    rpc.delay_next_transactions(1, 151);
+    let signature = send_and_confirm(&rpc, &transaction, &[&payer], &config())
+        .await
+        .unwrap();
+    let sent = rpc.sent_transactions();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].signatures, vec![signature]);
+}
+
+#[tokio::test]
+async fn failed_instruction_is_decoded() {
+    let payer = Keypair::new();
+    let other_program = Pubkey::new_unique();
+    let logs = vec![
+        format!("Program {} invoke [1]", other_program),
+        format!("Program {} success", other_program),
+        format!("Program {} invoke [1]", m3m3::ID),
+        "Program log: Instruction: Stake".to_string(),
+        "Program log: Error Code: InsufficientStakeAmount".to_string(),
+        format!("Program {} failed: custom program error: 0x1778", m3m3::ID),
+    ];
+    let err = TransactionError::InstructionError(1, InstructionError::Custom(6008));
+    let rpc = InMemoryRpc::new().with_transaction_error(err.clone(), logs.clone());
+
+    let transaction = transaction(&payer, &[other_program, m3m3::ID]);
+    let Err(ClientError::TransactionFailed(failure)) =
+        send_and_confirm(&rpc, &transaction, &[&payer], &config()).await
+    else {
+        panic!("transaction should fail");
+    };
+    assert_eq!(failure.signature, None);
+    assert_eq!(failure.err, err);
+    assert_eq!(failure.instruction_index, Some(1));
+    assert_eq!(
+        failure.program_error,
+        Some(StakeForFeeError::InsufficientStakeAmount)
+    );
+    assert_eq!(failure.logs, logs[2..]);
+
+    // The same code from another program is not a stake-for-fee error.
+    let transaction = self::transaction(&payer, &[m3m3::ID, other_program]);
+    let Err(ClientError::TransactionFailed(failure)) =
+        send_and_confirm(&rpc, &transaction, &[&payer], &config()).await
+    else {
+        panic!("transaction should fail");
+    };
+    assert_eq!(failure.program_error, None);
+}
@@ -0,0 +1,126 @@
+mod utils;
+
+use common::pda::{derive_associated_token_key, derive_stake_escrow_key};
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, pubkey::Pubkey};
+use stake_for_fee_client::{compute::MAX_COMPUTE_UNITS, InMemoryRpc, StakeForFee};
+use std::sync::Arc;
+use utils::{
+    decompile, simulator, staker, write_stake_escrow, write_token_account, write_vault, START,
+};
+
+#[tokio::test]
+async fn prepare_stake_creates_accounts_and_displaces_smallest_top_staker() {
+    let mut sim = simulator(2, 10);
+    let smallest = staker(&mut sim, 100, START);
+    staker(&mut sim, 1_000, START);
+    let owner = Pubkey::new_unique();
+
+    let rpc = Arc::new(InMemoryRpc::new().with_units_consumed(100_000));
+    write_vault(&rpc, &sim, START);
+    let client = StakeForFee::from_vault(rpc.clone(), sim.vault())
+        .await
+        .unwrap();
+
+    let prepared = client.prepare_stake(500, owner).await.unwrap();
+    let preview = &prepared.preview;
+    assert!(preview.initialize_stake_escrow);
+    assert!(preview.in_top_list);
+    assert_eq!(preview.displaced_owner, Some(smallest));
+    assert_eq!(
+        preview.stake_escrow,
+        derive_stake_escrow_key(sim.vault(), owner)
+    );
+
+    let transaction = &prepared.transaction;
+    assert_eq!(transaction.signatures.len(), 1);
+    assert_eq!(transaction.message.static_account_keys()[0], owner);
+    let instructions = decompile(transaction);
+    assert_eq!(instructions.len(), 4);
+    assert_eq!(
+        instructions[0].data,
+        ComputeBudgetInstruction::set_compute_unit_limit(110_000).data
+    );
+    assert_eq!(instructions[1].program_id, spl_associated_token_account::ID);
+    assert_eq!(instructions[2].program_id, m3m3::ID);
+    let stake = &instructions[3];
+    assert_eq!(stake.program_id, m3m3::ID);
+    assert_eq!(
+        stake.accounts[7].pubkey,
+        derive_associated_token_key(owner, sim.fee_vault().stake_mint)
+    );
+    assert!(stake.accounts.ends_with(&preview.remaining_accounts));
+
+    // Simulated once, without the compute unit limit it sizes.
+    let simulated = rpc.simulated_transactions();
+    assert_eq!(simulated.len(), 1);
+    assert_eq!(
+        decompile(&simulated[0])[0].data,
+        ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNITS).data
+    );
+    assert_eq!(decompile(&simulated[0])[1..], instructions[1..]);
+
+    // The stake applies as previewed.
+    sim.initialize_stake_escrow(owner, START).unwrap();
+    sim.stake(owner, 500, START).unwrap();
+    assert_eq!(sim.stake_escrow(owner).unwrap().in_top_list, 1);
+    assert_eq!(sim.stake_escrow(smallest).unwrap().in_top_list, 0);
+}
+
+#[tokio::test]
+async fn prepare_stake_skips_existing_accounts() {
+    let mut sim = simulator(2, 10);
+    let owner = staker(&mut sim, 100, START);
+
+    // No units reported, so the limit falls back to the maximum.
+    let rpc = Arc::new(InMemoryRpc::new());
+    write_vault(&rpc, &sim, START);
+    write_stake_escrow(&rpc, &sim, owner);
+    let stake_mint = sim.fee_vault().stake_mint;
+    write_token_account(
+        &rpc,
+        derive_associated_token_key(owner, stake_mint),
+        stake_mint,
+        owner,
+        1_000,
+    );
+    let client = StakeForFee::from_vault(rpc.clone(), sim.vault())
+        .await
+        .unwrap();
+
+    let prepared = client.prepare_stake(50, owner).await.unwrap();
+    assert!(!prepared.preview.initialize_stake_escrow);
+    assert!(prepared.preview.was_in_top_list);
+    assert_eq!(prepared.preview.stake_amount, 150);
+
+    let instructions = decompile(&prepared.transaction);
+    assert_eq!(instructions.len(), 2);
+    assert_eq!(
+        instructions[0].data,
+        ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNITS).data
+    );
+
+    assert!(client.prepare_stake(0, owner).await.is_err());
+}
+
+#[tokio::test]
+async fn stake_builds_the_prepared_transaction() {
+    let mut sim = simulator(2, 10);
+    staker(&mut sim, 100, START);
+    let owner = Pubkey::new_unique();
+
+    let rpc = Arc::new(InMemoryRpc::new().with_units_consumed(100_000));
+    write_vault(&rpc, &sim, START);
+    let client = StakeForFee::from_vault(rpc.clone(), sim.vault())
+        .await
+        .unwrap();
+
+    let transaction = client.stake(500, owner, 2).await.unwrap();
+    assert_eq!(
+        transaction,
+        client.prepare_stake(500, owner).await.unwrap().transaction
+    );
+    // Compute unit limit, stake token ATA, stake escrow and stake.
+    let instructions = decompile(&transaction);
+    assert_eq!(instructions.len(), 4);
+    assert_eq!(instructions[1].program_id, spl_associated_token_account::ID);
+}
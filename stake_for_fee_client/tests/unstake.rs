@@ -0,0 +1,116 @@
+mod utils;
+
+use common::pda::{derive_associated_token_key, derive_stake_escrow_key};
+use solana_sdk::{
+    instruction::AccountMeta,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use stake_for_fee_client::{InMemoryRpc, StakeForFee};
+use std::sync::Arc;
+use utils::{decompile, simulator, staker, write_stake_escrow, write_unstake, write_vault, START};
+
+#[tokio::test]
+async fn prepare_unstake_passes_promotion_candidates() {
+    let mut sim = simulator(1, 10);
+    let owner = staker(&mut sim, 1_000, START);
+    let promoted = staker(&mut sim, 500, START);
+    let other = staker(&mut sim, 300, START);
+
+    let rpc = Arc::new(InMemoryRpc::new().with_units_consumed(50_000));
+    write_vault(&rpc, &sim, START);
+    write_stake_escrow(&rpc, &sim, owner);
+    let client = StakeForFee::from_vault(rpc.clone(), sim.vault())
+        .await
+        .unwrap();
+
+    let unstake = Keypair::new();
+    let unstake_key = unstake.pubkey();
+    let prepared = client
+        .prepare_unstake(900, owner, Some(unstake))
+        .await
+        .unwrap();
+    assert_eq!(prepared.unstake.pubkey(), unstake_key);
+    assert_eq!(prepared.signers, vec![owner, unstake_key]);
+    assert_eq!(prepared.transaction.signatures.len(), 2);
+
+    let preview = &prepared.preview;
+    assert!(preview.was_in_top_list);
+    assert!(!preview.in_top_list);
+    assert_eq!(preview.promoted_owner, Some(promoted));
+    assert_eq!(
+        preview.remaining_accounts,
+        [promoted, other]
+            .map(|owner| AccountMeta::new(derive_stake_escrow_key(sim.vault(), owner), false))
+            .to_vec()
+    );
+
+    let instructions = decompile(&prepared.transaction);
+    assert_eq!(instructions.len(), 2);
+    let request_unstake = &instructions[1];
+    assert_eq!(request_unstake.accounts[0].pubkey, unstake_key);
+    assert!(request_unstake
+        .accounts
+        .ends_with(&preview.remaining_accounts));
+
+    sim.request_unstake(owner, unstake_key, 900, START).unwrap();
+    assert_eq!(sim.stake_escrow(promoted).unwrap().in_top_list, 1);
+
+    // Without a keypair one is generated, and no candidates are needed outside the top list.
+    write_stake_escrow(&rpc, &sim, other);
+    let prepared = client.prepare_unstake(100, other, None).await.unwrap();
+    assert_eq!(prepared.signers, vec![other, prepared.unstake.pubkey()]);
+    assert!(prepared.preview.remaining_accounts.is_empty());
+}
+
+#[tokio::test]
+async fn existing_unstakes_are_withdrawn_or_cancelled() {
+    let mut sim = simulator(2, 10);
+    let owner = staker(&mut sim, 1_000, START);
+    let released = Pubkey::new_unique();
+    let locked = Pubkey::new_unique();
+    sim.request_unstake(owner, released, 100, START).unwrap();
+    sim.request_unstake(owner, locked, 200, START + 3_000)
+        .unwrap();
+
+    let rpc = Arc::new(InMemoryRpc::new());
+    write_vault(&rpc, &sim, START + 4_000);
+    write_stake_escrow(&rpc, &sim, owner);
+    for unstake in [released, locked] {
+        write_unstake(&rpc, &sim, owner, unstake);
+    }
+    let client = StakeForFee::from_vault(rpc.clone(), sim.vault())
+        .await
+        .unwrap();
+
+    let timeline = client.fetch_unstake_timeline(owner).await.unwrap();
+    assert_eq!(timeline.total_pending_amount, 300);
+    assert_eq!(timeline.next_release_at, Some(START + 3_000 + 3_600));
+
+    let withdrawals = client.prepare_withdraw_unstakes(owner).await.unwrap();
+    assert_eq!(withdrawals.len(), 1);
+    assert_eq!(withdrawals[0].unstake.unstake, released);
+    let instructions = decompile(&withdrawals[0].transaction);
+    // Compute unit limit, stake token ATA and withdraw.
+    assert_eq!(instructions.len(), 3);
+    assert_eq!(instructions[1].program_id, spl_associated_token_account::ID);
+    assert_eq!(instructions[2].accounts[0].pubkey, released);
+    assert_eq!(
+        instructions[2].accounts[4].pubkey,
+        derive_associated_token_key(owner, sim.fee_vault().stake_mint)
+    );
+
+    let cancellations = client.prepare_cancel_unstakes(owner).await.unwrap();
+    assert_eq!(cancellations.len(), 1);
+    assert_eq!(cancellations[0].unstake.unstake, locked);
+    let instructions = decompile(&cancellations[0].transaction);
+    assert_eq!(instructions.len(), 2);
+    assert_eq!(instructions[1].accounts[0].pubkey, locked);
+    assert_eq!(instructions[1].accounts.len(), 26);
+
+    assert!(client
+        .prepare_withdraw_unstakes(Pubkey::new_unique())
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,316 @@
+#![allow(dead_code)]
+
+use borsh::BorshSerialize;
+use common::{
+    dynamic_amm::{LOCK_ESCROW_ACCOUNT_DISCM, POOL_ACCOUNT_DISCM},
+    dynamic_vault::VAULT_ACCOUNT_DISCM,
+    pda::{derive_lock_escrow_key, derive_stake_escrow_key},
+};
+use m3m3::{
+    FeeVaultAccount, FullBalanceListMetadataAccount, StakeEscrowAccount, StakerMetadata,
+    TopListMetadataAccount, UnstakeAccount,
+};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    transaction::VersionedTransaction,
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use stake_for_fee_client::InMemoryRpc;
+use stake_for_fee_simulator::{Simulator, SimulatorConfig};
+
+pub const START: i64 = 1_700_000_000;
+
+pub fn simulator(top_list_length: u16, full_balance_list_capacity: u64) -> Simulator {
+    simulator_with_mints(
+        top_list_length,
+        full_balance_list_capacity,
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    )
+}
+
+pub fn simulator_with_mints(
+    top_list_length: u16,
+    full_balance_list_capacity: u64,
+    stake_mint: Pubkey,
+    quote_mint: Pubkey,
+) -> Simulator {
+    let mut config = SimulatorConfig::new(Pubkey::new_unique(), stake_mint, quote_mint);
+    config.top_list_length = top_list_length;
+    config.seconds_to_full_unlock = 1_000;
+    config.unstake_lock_duration = 3_600;
+    config.full_balance_list_capacity = full_balance_list_capacity;
+
+    Simulator::new(config, Pubkey::new_unique(), START).unwrap()
+}
+
+pub fn staker(sim: &mut Simulator, amount: u64, current_time: i64) -> Pubkey {
+    let owner = Pubkey::new_unique();
+    sim.initialize_stake_escrow(owner, current_time).unwrap();
+    sim.stake(owner, amount, current_time).unwrap();
+    owner
+}
+
+/// Accounts around a simulated fee vault. The lock escrow never has fees to claim, so the
+/// simulator's lock escrow fees must be cranked in before writing.
+#[derive(Clone, Copy, Debug)]
+pub struct PoolKeys {
+    pub pool: Pubkey,
+    pub lock_escrow: Pubkey,
+    pub escrow_vault: Pubkey,
+    pub lp_mint: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub a_vault: Pubkey,
+    pub b_vault: Pubkey,
+    pub a_vault_lp: Pubkey,
+    pub b_vault_lp: Pubkey,
+    pub a_vault_lp_mint: Pubkey,
+    pub b_vault_lp_mint: Pubkey,
+    pub a_token_vault: Pubkey,
+    pub b_token_vault: Pubkey,
+}
+
+pub fn program_account(owner: Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: 1_000_000,
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Write the fee vault of `sim`, its lists, pool accounts and the clock at `current_time`.
+pub fn write_vault(rpc: &InMemoryRpc, sim: &Simulator, current_time: i64) -> PoolKeys {
+    let mut fee_vault = sim.fee_vault().clone();
+    let vault = sim.vault();
+    let keys = PoolKeys {
+        pool: fee_vault.pool,
+        lock_escrow: derive_lock_escrow_key(fee_vault.pool, vault),
+        escrow_vault: Pubkey::new_unique(),
+        lp_mint: Pubkey::new_unique(),
+        token_a_mint: fee_vault.stake_mint,
+        token_b_mint: fee_vault.quote_mint,
+        a_vault: Pubkey::new_unique(),
+        b_vault: Pubkey::new_unique(),
+        a_vault_lp: Pubkey::new_unique(),
+        b_vault_lp: Pubkey::new_unique(),
+        a_vault_lp_mint: Pubkey::new_unique(),
+        b_vault_lp_mint: Pubkey::new_unique(),
+        a_token_vault: Pubkey::new_unique(),
+        b_token_vault: Pubkey::new_unique(),
+    };
+    fee_vault.lock_escrow = keys.lock_escrow;
+    if fee_vault.stake_token_vault == Pubkey::default() {
+        fee_vault.stake_token_vault = Pubkey::new_unique();
+        fee_vault.quote_token_vault = Pubkey::new_unique();
+    }
+
+    let top_list_length = fee_vault.top_staker_info.top_list_length as usize;
+    let mut data = TopListMetadataAccount(sim.top_staker_list().metadata.clone())
+        .try_to_vec()
+        .unwrap();
+    let mut stakers = sim.top_staker_list().stakers.clone();
+    stakers.resize(
+        top_list_length,
+        StakerMetadata {
+            stake_amount: 0,
+            full_balance_index: -1,
+            owner: Pubkey::default(),
+        },
+    );
+    for staker in &stakers {
+        data.extend(staker.try_to_vec().unwrap());
+    }
+    rpc.set_account(fee_vault.top_staker_list, program_account(m3m3::ID, data));
+
+    let mut data = FullBalanceListMetadataAccount(sim.full_balance_list().metadata.clone())
+        .try_to_vec()
+        .unwrap();
+    for staker in &sim.full_balance_list().stakers {
+        data.extend(staker.try_to_vec().unwrap());
+    }
+    rpc.set_account(fee_vault.full_balance_list, program_account(m3m3::ID, data));
+
+    rpc.set_account(
+        vault,
+        program_account(m3m3::ID, FeeVaultAccount(fee_vault).try_to_vec().unwrap()),
+    );
+
+    let mut data = POOL_ACCOUNT_DISCM.to_vec();
+    for key in [
+        keys.lp_mint,
+        keys.token_a_mint,
+        keys.token_b_mint,
+        keys.a_vault,
+        keys.b_vault,
+        keys.a_vault_lp,
+        keys.b_vault_lp,
+    ] {
+        data.extend_from_slice(key.as_ref());
+    }
+    data.extend([0, 1]);
+    rpc.set_account(keys.pool, program_account(Pubkey::new_unique(), data));
+
+    let mut data = LOCK_ESCROW_ACCOUNT_DISCM.to_vec();
+    for key in [keys.pool, vault, keys.escrow_vault] {
+        data.extend_from_slice(key.as_ref());
+    }
+    data.push(0);
+    data.extend(1_000_000u64.to_le_bytes());
+    // No LP price ever exceeds this, so no fee is claimable.
+    data.extend(u128::MAX.to_le_bytes());
+    data.extend([0u8; 24]);
+    rpc.set_account(
+        keys.lock_escrow,
+        program_account(Pubkey::new_unique(), data),
+    );
+
+    for (vault_key, token_vault, token_mint, lp_mint) in [
+        (
+            keys.a_vault,
+            keys.a_token_vault,
+            keys.token_a_mint,
+            keys.a_vault_lp_mint,
+        ),
+        (
+            keys.b_vault,
+            keys.b_token_vault,
+            keys.token_b_mint,
+            keys.b_vault_lp_mint,
+        ),
+    ] {
+        rpc.set_account(
+            vault_key,
+            program_account(
+                Pubkey::new_unique(),
+                dynamic_vault_data(token_vault, token_mint, lp_mint),
+            ),
+        );
+    }
+
+    for (token_account, mint) in [
+        (keys.a_vault_lp, keys.a_vault_lp_mint),
+        (keys.b_vault_lp, keys.b_vault_lp_mint),
+    ] {
+        write_token_account(rpc, token_account, mint, Pubkey::new_unique(), 1_000_000);
+    }
+    for mint in [
+        keys.lp_mint,
+        keys.token_a_mint,
+        keys.token_b_mint,
+        keys.a_vault_lp_mint,
+        keys.b_vault_lp_mint,
+    ] {
+        write_mint(rpc, mint, 6);
+    }
+
+    rpc.set_clock(&Clock {
+        unix_timestamp: current_time,
+        ..Clock::default()
+    });
+
+    keys
+}
+
+pub fn write_stake_escrow(rpc: &InMemoryRpc, sim: &Simulator, owner: Pubkey) -> Pubkey {
+    let key = derive_stake_escrow_key(sim.vault(), owner);
+    let stake_escrow = sim.stake_escrow(owner).unwrap().clone();
+    rpc.set_account(
+        key,
+        program_account(
+            m3m3::ID,
+            StakeEscrowAccount(stake_escrow).try_to_vec().unwrap(),
+        ),
+    );
+    key
+}
+
+/// Write `unstake` of `sim` with the owner and vault the program stores for indexing.
+pub fn write_unstake(rpc: &InMemoryRpc, sim: &Simulator, owner: Pubkey, unstake: Pubkey) {
+    let mut data = UnstakeAccount(sim.unstake(unstake).unwrap().clone())
+        .try_to_vec()
+        .unwrap();
+    data[64..96].copy_from_slice(owner.as_ref());
+    data[96..128].copy_from_slice(sim.vault().as_ref());
+    rpc.set_account(unstake, program_account(m3m3::ID, data));
+}
+
+pub fn write_mint(rpc: &InMemoryRpc, mint: Pubkey, decimals: u8) {
+    let mut data = vec![0u8; Mint::LEN];
+    Mint {
+        mint_authority: COption::None,
+        supply: 1_000_000_000,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    rpc.set_account(mint, program_account(spl_token::ID, data));
+}
+
+pub fn write_token_account(
+    rpc: &InMemoryRpc,
+    key: Pubkey,
+    mint: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) {
+    let mut data = vec![0u8; TokenAccount::LEN];
+    TokenAccount {
+        mint,
+        owner,
+        amount,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    rpc.set_account(key, program_account(spl_token::ID, data));
+}
+
+/// Instructions of a transaction without lookup tables.
+pub fn decompile(transaction: &VersionedTransaction) -> Vec<Instruction> {
+    let keys = transaction.message.static_account_keys();
+    transaction
+        .message
+        .instructions()
+        .iter()
+        .map(|instruction| Instruction {
+            program_id: keys[usize::from(instruction.program_id_index)],
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|idx| AccountMeta {
+                    pubkey: keys[usize::from(*idx)],
+                    is_signer: transaction.message.is_signer(usize::from(*idx)),
+                    is_writable: transaction.message.is_maybe_writable(usize::from(*idx)),
+                })
+                .collect(),
+            data: instruction.data.clone(),
+        })
+        .collect()
+}
+
+fn dynamic_vault_data(token_vault: Pubkey, token_mint: Pubkey, lp_mint: Pubkey) -> Vec<u8> {
+    let mut data = VAULT_ACCOUNT_DISCM.to_vec();
+    // enabled, bumps, total_amount
+    data.extend([1, 0, 0]);
+    data.extend(1_000_000u64.to_le_bytes());
+    for key in [token_vault, Pubkey::new_unique(), token_mint, lp_mint] {
+        data.extend_from_slice(key.as_ref());
+    }
+    // strategies, base, admin, operator
+    data.extend([0u8; 33 * 32]);
+    // locked_profit_tracker
+    data.extend([0u8; 24]);
+    data
+}
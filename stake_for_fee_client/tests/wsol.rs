@@ -0,0 +1,88 @@
+mod utils;
+
+use common::pda::derive_associated_token_key;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_program};
+use spl_token::native_mint;
+use stake_for_fee_client::{wsol::unwrap_sol_instruction, InMemoryRpc, StakeForFee};
+use std::sync::Arc;
+use utils::{
+    decompile, simulator_with_mints, staker, write_stake_escrow, write_unstake, write_vault, START,
+};
+
+/// Decompiled instructions carry the signer and writable flags of the whole message.
+fn is_unwrap_sol(instruction: &Instruction, owner: Pubkey) -> bool {
+    let unwrap_sol = unwrap_sol_instruction(owner).unwrap();
+    instruction.program_id == unwrap_sol.program_id
+        && instruction.data == unwrap_sol.data
+        && instruction
+            .accounts
+            .iter()
+            .map(|account| account.pubkey)
+            .eq(unwrap_sol.accounts.iter().map(|account| account.pubkey))
+}
+
+#[tokio::test]
+async fn claim_fee_unwraps_sol_quote() {
+    let mut sim = simulator_with_mints(2, 10, Pubkey::new_unique(), native_mint::ID);
+    let owner = staker(&mut sim, 1_000, START);
+
+    let rpc = Arc::new(InMemoryRpc::new());
+    write_vault(&rpc, &sim, START);
+    write_stake_escrow(&rpc, &sim, owner);
+    let mut client = StakeForFee::from_vault(rpc, sim.vault()).await.unwrap();
+
+    let instructions = decompile(&client.claim_fee(owner, u64::MAX).await.unwrap());
+    // Compute unit limit, create the wSOL ATA, claim, close the wSOL ATA.
+    assert_eq!(instructions.len(), 4);
+    assert_eq!(instructions[1].program_id, spl_associated_token_account::ID);
+    assert_eq!(instructions[2].program_id, m3m3::ID);
+    assert!(is_unwrap_sol(&instructions[3], owner));
+
+    client.unwrap_sol = false;
+    let instructions = decompile(&client.claim_fee(owner, u64::MAX).await.unwrap());
+    assert_eq!(instructions.len(), 3);
+    assert_eq!(instructions[2].program_id, m3m3::ID);
+}
+
+#[tokio::test]
+async fn sol_stake_is_wrapped_and_withdrawn_as_sol() {
+    let mut sim = simulator_with_mints(2, 10, native_mint::ID, Pubkey::new_unique());
+    let owner = staker(&mut sim, 1_000, START);
+    let unstake = Pubkey::new_unique();
+    sim.request_unstake(owner, unstake, 100, START).unwrap();
+
+    let rpc = Arc::new(InMemoryRpc::new());
+    write_vault(&rpc, &sim, START + 4_000);
+    write_stake_escrow(&rpc, &sim, owner);
+    write_unstake(&rpc, &sim, owner, unstake);
+    let mut client = StakeForFee::from_vault(rpc, sim.vault()).await.unwrap();
+    let wsol_ata = derive_associated_token_key(owner, native_mint::ID);
+
+    let prepared = client.prepare_stake(500, owner).await.unwrap();
+    let instructions = decompile(&prepared.transaction);
+    // Compute unit limit, wrap, stake and unwrap.
+    assert_eq!(instructions.len(), 6);
+    assert_eq!(instructions[1].program_id, spl_associated_token_account::ID);
+    assert_eq!(instructions[2].program_id, system_program::ID);
+    assert_eq!(instructions[2].accounts[1].pubkey, wsol_ata);
+    assert_eq!(instructions[3].program_id, spl_token::ID);
+    assert_eq!(instructions[4].program_id, m3m3::ID);
+    assert!(is_unwrap_sol(&instructions[5], owner));
+
+    let withdrawals = client.prepare_withdraw_unstakes(owner).await.unwrap();
+    let instructions = decompile(&withdrawals[0].transaction);
+    assert_eq!(instructions.len(), 4);
+    assert_eq!(instructions[1].program_id, spl_associated_token_account::ID);
+    assert_eq!(instructions[2].accounts[4].pubkey, wsol_ata);
+    assert!(is_unwrap_sol(&instructions[3], owner));
+
+    // Kept as wSOL, already in the ATA being created.
+    client.unwrap_sol = false;
+    let instructions = decompile(&client.prepare_stake(500, owner).await.unwrap().transaction);
+    assert_eq!(instructions.len(), 3);
+    assert_eq!(instructions[1].program_id, spl_associated_token_account::ID);
+    let instructions =
+        decompile(&client.prepare_withdraw_unstakes(owner).await.unwrap()[0].transaction);
+    assert_eq!(instructions.len(), 3);
+    assert_eq!(instructions[2].program_id, m3m3::ID);
+}
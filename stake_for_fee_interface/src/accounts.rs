@@ -330,3 +330,46 @@ impl FeeVaultAccount {
         Ok(data)
     }
 }
+pub const CONFIG_ACCOUNT_DISCM: [u8; 8] = [155, 12, 170, 224, 30, 250, 204, 130];
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    pub index: u64,
+    pub seconds_to_full_unlock: u64,
+    pub unstake_lock_duration: u64,
+    pub join_window_duration: u64,
+    pub top_list_length: u16,
+    pub padding0: [u8; 6],
+    pub padding: [u128; 10],
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigAccount(pub Config);
+impl ConfigAccount {
+    pub fn deserialize(buf: &[u8]) -> std::io::Result<Self> {
+        use std::io::Read;
+        let mut reader = buf;
+        let mut maybe_discm = [0u8; 8];
+        reader.read_exact(&mut maybe_discm)?;
+        if maybe_discm != CONFIG_ACCOUNT_DISCM {
+            return Err(
+                std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!(
+                        "discm does not match. Expected: {:?}. Received: {:?}",
+                        CONFIG_ACCOUNT_DISCM, maybe_discm
+                    ),
+                ),
+            );
+        }
+        Ok(Self(Config::deserialize(&mut reader)?))
+    }
+    pub fn serialize<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(&CONFIG_ACCOUNT_DISCM)?;
+        self.0.serialize(&mut writer)
+    }
+    pub fn try_to_vec(&self) -> std::io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.serialize(&mut data)?;
+        Ok(data)
+    }
+}
@@ -10,6 +10,7 @@ use crate::*;
 #[derive(Clone, Debug, PartialEq)]
 pub enum StakeForFeeProgramIx {
     InitializeVault(InitializeVaultIxArgs),
+    InitializeVaultWithConfig(InitializeVaultWithConfigIxArgs),
     InitializeStakeEscrow,
     Stake(StakeIxArgs),
     ClaimFee(ClaimFeeIxArgs),
@@ -34,6 +35,13 @@ impl StakeForFeeProgramIx {
                     ),
                 )
             }
+            INITIALIZE_VAULT_WITH_CONFIG_IX_DISCM => {
+                Ok(
+                    Self::InitializeVaultWithConfig(
+                        InitializeVaultWithConfigIxArgs::deserialize(&mut reader)?,
+                    ),
+                )
+            }
             INITIALIZE_STAKE_ESCROW_IX_DISCM => Ok(Self::InitializeStakeEscrow),
             STAKE_IX_DISCM => Ok(Self::Stake(StakeIxArgs::deserialize(&mut reader)?)),
             CLAIM_FEE_IX_DISCM => {
@@ -76,6 +84,10 @@ impl StakeForFeeProgramIx {
                 writer.write_all(&INITIALIZE_VAULT_IX_DISCM)?;
                 args.serialize(&mut writer)
             }
+            Self::InitializeVaultWithConfig(args) => {
+                writer.write_all(&INITIALIZE_VAULT_WITH_CONFIG_IX_DISCM)?;
+                args.serialize(&mut writer)
+            }
             Self::InitializeStakeEscrow => {
                 writer.write_all(&INITIALIZE_STAKE_ESCROW_IX_DISCM)
             }
@@ -483,6 +495,396 @@ pub fn initialize_vault_verify_account_privileges<'me, 'info>(
     initialize_vault_verify_signer_privileges(accounts)?;
     Ok(())
 }
+pub const INITIALIZE_VAULT_WITH_CONFIG_IX_ACCOUNTS_LEN: usize = 16;
+#[derive(Copy, Clone, Debug)]
+pub struct InitializeVaultWithConfigAccounts<'me, 'info> {
+    pub vault: &'me AccountInfo<'info>,
+    pub stake_token_vault: &'me AccountInfo<'info>,
+    pub quote_token_vault: &'me AccountInfo<'info>,
+    pub top_staker_list: &'me AccountInfo<'info>,
+    pub full_balance_list: &'me AccountInfo<'info>,
+    pub pool: &'me AccountInfo<'info>,
+    pub stake_mint: &'me AccountInfo<'info>,
+    pub quote_mint: &'me AccountInfo<'info>,
+    pub lock_escrow: &'me AccountInfo<'info>,
+    pub config: &'me AccountInfo<'info>,
+    pub payer: &'me AccountInfo<'info>,
+    pub system_program: &'me AccountInfo<'info>,
+    pub token_program: &'me AccountInfo<'info>,
+    pub associated_token_program: &'me AccountInfo<'info>,
+    pub event_authority: &'me AccountInfo<'info>,
+    pub program: &'me AccountInfo<'info>,
+}
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InitializeVaultWithConfigKeys {
+    pub vault: Pubkey,
+    pub stake_token_vault: Pubkey,
+    pub quote_token_vault: Pubkey,
+    pub top_staker_list: Pubkey,
+    pub full_balance_list: Pubkey,
+    pub pool: Pubkey,
+    pub stake_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub lock_escrow: Pubkey,
+    pub config: Pubkey,
+    pub payer: Pubkey,
+    pub system_program: Pubkey,
+    pub token_program: Pubkey,
+    pub associated_token_program: Pubkey,
+    pub event_authority: Pubkey,
+    pub program: Pubkey,
+}
+impl From<InitializeVaultWithConfigAccounts<'_, '_>> for InitializeVaultWithConfigKeys {
+    fn from(accounts: InitializeVaultWithConfigAccounts) -> Self {
+        Self {
+            vault: *accounts.vault.key,
+            stake_token_vault: *accounts.stake_token_vault.key,
+            quote_token_vault: *accounts.quote_token_vault.key,
+            top_staker_list: *accounts.top_staker_list.key,
+            full_balance_list: *accounts.full_balance_list.key,
+            pool: *accounts.pool.key,
+            stake_mint: *accounts.stake_mint.key,
+            quote_mint: *accounts.quote_mint.key,
+            lock_escrow: *accounts.lock_escrow.key,
+            config: *accounts.config.key,
+            payer: *accounts.payer.key,
+            system_program: *accounts.system_program.key,
+            token_program: *accounts.token_program.key,
+            associated_token_program: *accounts.associated_token_program.key,
+            event_authority: *accounts.event_authority.key,
+            program: *accounts.program.key,
+        }
+    }
+}
+impl From<InitializeVaultWithConfigKeys>
+for [AccountMeta; INITIALIZE_VAULT_WITH_CONFIG_IX_ACCOUNTS_LEN] {
+    fn from(keys: InitializeVaultWithConfigKeys) -> Self {
+        [
+            AccountMeta {
+                pubkey: keys.vault,
+                is_signer: false,
+                is_writable: true,
+            },
+            AccountMeta {
+                pubkey: keys.stake_token_vault,
+                is_signer: false,
+                is_writable: true,
+            },
+            AccountMeta {
+                pubkey: keys.quote_token_vault,
+                is_signer: false,
+                is_writable: true,
+            },
+            AccountMeta {
+                pubkey: keys.top_staker_list,
+                is_signer: false,
+                is_writable: true,
+            },
+            AccountMeta {
+                pubkey: keys.full_balance_list,
+                is_signer: false,
+                is_writable: true,
+            },
+            AccountMeta {
+                pubkey: keys.pool,
+                is_signer: false,
+                is_writable: false,
+            },
+            AccountMeta {
+                pubkey: keys.stake_mint,
+                is_signer: false,
+                is_writable: false,
+            },
+            AccountMeta {
+                pubkey: keys.quote_mint,
+                is_signer: false,
+                is_writable: false,
+            },
+            AccountMeta {
+                pubkey: keys.lock_escrow,
+                is_signer: false,
+                is_writable: false,
+            },
+            AccountMeta {
+                pubkey: keys.config,
+                is_signer: false,
+                is_writable: false,
+            },
+            AccountMeta {
+                pubkey: keys.payer,
+                is_signer: true,
+                is_writable: true,
+            },
+            AccountMeta {
+                pubkey: keys.system_program,
+                is_signer: false,
+                is_writable: false,
+            },
+            AccountMeta {
+                pubkey: keys.token_program,
+                is_signer: false,
+                is_writable: false,
+            },
+            AccountMeta {
+                pubkey: keys.associated_token_program,
+                is_signer: false,
+                is_writable: false,
+            },
+            AccountMeta {
+                pubkey: keys.event_authority,
+                is_signer: false,
+                is_writable: false,
+            },
+            AccountMeta {
+                pubkey: keys.program,
+                is_signer: false,
+                is_writable: false,
+            },
+        ]
+    }
+}
+impl From<[Pubkey; INITIALIZE_VAULT_WITH_CONFIG_IX_ACCOUNTS_LEN]>
+for InitializeVaultWithConfigKeys {
+    fn from(pubkeys: [Pubkey; INITIALIZE_VAULT_WITH_CONFIG_IX_ACCOUNTS_LEN]) -> Self {
+        Self {
+            vault: pubkeys[0],
+            stake_token_vault: pubkeys[1],
+            quote_token_vault: pubkeys[2],
+            top_staker_list: pubkeys[3],
+            full_balance_list: pubkeys[4],
+            pool: pubkeys[5],
+            stake_mint: pubkeys[6],
+            quote_mint: pubkeys[7],
+            lock_escrow: pubkeys[8],
+            config: pubkeys[9],
+            payer: pubkeys[10],
+            system_program: pubkeys[11],
+            token_program: pubkeys[12],
+            associated_token_program: pubkeys[13],
+            event_authority: pubkeys[14],
+            program: pubkeys[15],
+        }
+    }
+}
+impl<'info> From<InitializeVaultWithConfigAccounts<'_, 'info>>
+for [AccountInfo<'info>; INITIALIZE_VAULT_WITH_CONFIG_IX_ACCOUNTS_LEN] {
+    fn from(accounts: InitializeVaultWithConfigAccounts<'_, 'info>) -> Self {
+        [
+            accounts.vault.clone(),
+            accounts.stake_token_vault.clone(),
+            accounts.quote_token_vault.clone(),
+            accounts.top_staker_list.clone(),
+            accounts.full_balance_list.clone(),
+            accounts.pool.clone(),
+            accounts.stake_mint.clone(),
+            accounts.quote_mint.clone(),
+            accounts.lock_escrow.clone(),
+            accounts.config.clone(),
+            accounts.payer.clone(),
+            accounts.system_program.clone(),
+            accounts.token_program.clone(),
+            accounts.associated_token_program.clone(),
+            accounts.event_authority.clone(),
+            accounts.program.clone(),
+        ]
+    }
+}
+impl<
+    'me,
+    'info,
+> From<&'me [AccountInfo<'info>; INITIALIZE_VAULT_WITH_CONFIG_IX_ACCOUNTS_LEN]>
+for InitializeVaultWithConfigAccounts<'me, 'info> {
+    fn from(
+        arr: &'me [AccountInfo<'info>; INITIALIZE_VAULT_WITH_CONFIG_IX_ACCOUNTS_LEN],
+    ) -> Self {
+        Self {
+            vault: &arr[0],
+            stake_token_vault: &arr[1],
+            quote_token_vault: &arr[2],
+            top_staker_list: &arr[3],
+            full_balance_list: &arr[4],
+            pool: &arr[5],
+            stake_mint: &arr[6],
+            quote_mint: &arr[7],
+            lock_escrow: &arr[8],
+            config: &arr[9],
+            payer: &arr[10],
+            system_program: &arr[11],
+            token_program: &arr[12],
+            associated_token_program: &arr[13],
+            event_authority: &arr[14],
+            program: &arr[15],
+        }
+    }
+}
+pub const INITIALIZE_VAULT_WITH_CONFIG_IX_DISCM: [u8; 8] = [
+    155,
+    133,
+    106,
+    51,
+    200,
+    234,
+    255,
+    134,
+];
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InitializeVaultWithConfigIxArgs {
+    pub params: InitializeVaultWithConfigParams,
+}
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitializeVaultWithConfigIxData(pub InitializeVaultWithConfigIxArgs);
+impl From<InitializeVaultWithConfigIxArgs> for InitializeVaultWithConfigIxData {
+    fn from(args: InitializeVaultWithConfigIxArgs) -> Self {
+        Self(args)
+    }
+}
+impl InitializeVaultWithConfigIxData {
+    pub fn deserialize(buf: &[u8]) -> std::io::Result<Self> {
+        let mut reader = buf;
+        let mut maybe_discm = [0u8; 8];
+        reader.read_exact(&mut maybe_discm)?;
+        if maybe_discm != INITIALIZE_VAULT_WITH_CONFIG_IX_DISCM {
+            return Err(
+                std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!(
+                        "discm does not match. Expected: {:?}. Received: {:?}",
+                        INITIALIZE_VAULT_WITH_CONFIG_IX_DISCM, maybe_discm
+                    ),
+                ),
+            );
+        }
+        Ok(Self(InitializeVaultWithConfigIxArgs::deserialize(&mut reader)?))
+    }
+    pub fn serialize<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(&INITIALIZE_VAULT_WITH_CONFIG_IX_DISCM)?;
+        self.0.serialize(&mut writer)
+    }
+    pub fn try_to_vec(&self) -> std::io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.serialize(&mut data)?;
+        Ok(data)
+    }
+}
+pub fn initialize_vault_with_config_ix_with_program_id(
+    program_id: Pubkey,
+    keys: InitializeVaultWithConfigKeys,
+    args: InitializeVaultWithConfigIxArgs,
+) -> std::io::Result<Instruction> {
+    let metas: [AccountMeta; INITIALIZE_VAULT_WITH_CONFIG_IX_ACCOUNTS_LEN] = keys.into();
+    let data: InitializeVaultWithConfigIxData = args.into();
+    Ok(Instruction {
+        program_id,
+        accounts: Vec::from(metas),
+        data: data.try_to_vec()?,
+    })
+}
+pub fn initialize_vault_with_config_ix(
+    keys: InitializeVaultWithConfigKeys,
+    args: InitializeVaultWithConfigIxArgs,
+) -> std::io::Result<Instruction> {
+    initialize_vault_with_config_ix_with_program_id(crate::ID, keys, args)
+}
+pub fn initialize_vault_with_config_invoke_with_program_id(
+    program_id: Pubkey,
+    accounts: InitializeVaultWithConfigAccounts<'_, '_>,
+    args: InitializeVaultWithConfigIxArgs,
+) -> ProgramResult {
+    let keys: InitializeVaultWithConfigKeys = accounts.into();
+    let ix = initialize_vault_with_config_ix_with_program_id(program_id, keys, args)?;
+    invoke_instruction(&ix, accounts)
+}
+pub fn initialize_vault_with_config_invoke(
+    accounts: InitializeVaultWithConfigAccounts<'_, '_>,
+    args: InitializeVaultWithConfigIxArgs,
+) -> ProgramResult {
+    initialize_vault_with_config_invoke_with_program_id(crate::ID, accounts, args)
+}
+pub fn initialize_vault_with_config_invoke_signed_with_program_id(
+    program_id: Pubkey,
+    accounts: InitializeVaultWithConfigAccounts<'_, '_>,
+    args: InitializeVaultWithConfigIxArgs,
+    seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let keys: InitializeVaultWithConfigKeys = accounts.into();
+    let ix = initialize_vault_with_config_ix_with_program_id(program_id, keys, args)?;
+    invoke_instruction_signed(&ix, accounts, seeds)
+}
+pub fn initialize_vault_with_config_invoke_signed(
+    accounts: InitializeVaultWithConfigAccounts<'_, '_>,
+    args: InitializeVaultWithConfigIxArgs,
+    seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    initialize_vault_with_config_invoke_signed_with_program_id(
+        crate::ID,
+        accounts,
+        args,
+        seeds,
+    )
+}
+pub fn initialize_vault_with_config_verify_account_keys(
+    accounts: InitializeVaultWithConfigAccounts<'_, '_>,
+    keys: InitializeVaultWithConfigKeys,
+) -> Result<(), (Pubkey, Pubkey)> {
+    for (actual, expected) in [
+        (*accounts.vault.key, keys.vault),
+        (*accounts.stake_token_vault.key, keys.stake_token_vault),
+        (*accounts.quote_token_vault.key, keys.quote_token_vault),
+        (*accounts.top_staker_list.key, keys.top_staker_list),
+        (*accounts.full_balance_list.key, keys.full_balance_list),
+        (*accounts.pool.key, keys.pool),
+        (*accounts.stake_mint.key, keys.stake_mint),
+        (*accounts.quote_mint.key, keys.quote_mint),
+        (*accounts.lock_escrow.key, keys.lock_escrow),
+        (*accounts.config.key, keys.config),
+        (*accounts.payer.key, keys.payer),
+        (*accounts.system_program.key, keys.system_program),
+        (*accounts.token_program.key, keys.token_program),
+        (*accounts.associated_token_program.key, keys.associated_token_program),
+        (*accounts.event_authority.key, keys.event_authority),
+        (*accounts.program.key, keys.program),
+    ] {
+        if actual != expected {
+            return Err((actual, expected));
+        }
+    }
+    Ok(())
+}
+pub fn initialize_vault_with_config_verify_writable_privileges<'me, 'info>(
+    accounts: InitializeVaultWithConfigAccounts<'me, 'info>,
+) -> Result<(), (&'me AccountInfo<'info>, ProgramError)> {
+    for should_be_writable in [
+        accounts.vault,
+        accounts.stake_token_vault,
+        accounts.quote_token_vault,
+        accounts.top_staker_list,
+        accounts.full_balance_list,
+        accounts.payer,
+    ] {
+        if !should_be_writable.is_writable {
+            return Err((should_be_writable, ProgramError::InvalidAccountData));
+        }
+    }
+    Ok(())
+}
+pub fn initialize_vault_with_config_verify_signer_privileges<'me, 'info>(
+    accounts: InitializeVaultWithConfigAccounts<'me, 'info>,
+) -> Result<(), (&'me AccountInfo<'info>, ProgramError)> {
+    for should_be_signer in [accounts.payer] {
+        if !should_be_signer.is_signer {
+            return Err((should_be_signer, ProgramError::MissingRequiredSignature));
+        }
+    }
+    Ok(())
+}
+pub fn initialize_vault_with_config_verify_account_privileges<'me, 'info>(
+    accounts: InitializeVaultWithConfigAccounts<'me, 'info>,
+) -> Result<(), (&'me AccountInfo<'info>, ProgramError)> {
+    initialize_vault_with_config_verify_writable_privileges(accounts)?;
+    initialize_vault_with_config_verify_signer_privileges(accounts)?;
+    Ok(())
+}
 pub const INITIALIZE_STAKE_ESCROW_IX_ACCOUNTS_LEN: usize = 9;
 #[derive(Copy, Clone, Debug)]
 pub struct InitializeStakeEscrowAccounts<'me, 'info> {
@@ -11,6 +11,12 @@ pub struct InitializeVaultParams {
 }
 #[derive(Clone, Debug, BorshDeserialize, BorshSerialize, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InitializeVaultWithConfigParams {
+    pub start_fee_distribute_timestamp: Option<u64>,
+    pub padding: [u8; 64],
+}
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StakerBalance {
     pub balance: u64,
     pub owner: Pubkey,
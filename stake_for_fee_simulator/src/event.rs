@@ -0,0 +1,20 @@
+pub use common::event::*;
+use m3m3::{
+    AddNewUserToTopHolder, CancelUnstakeSucceed, ClaimFeeSucceed, FeeEmission, ReclaimIndex,
+    RemoveUserFromTopHolder, StakeEscrowCreated, UnstakeCreated, UserStake, WithdrawSucceed,
+};
+
+/// Events emitted by the simulated instructions, using the program's event structs.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    StakeEscrowCreated(StakeEscrowCreated),
+    UserStake(UserStake),
+    UnstakeCreated(UnstakeCreated),
+    CancelUnstakeSucceed(CancelUnstakeSucceed),
+    WithdrawSucceed(WithdrawSucceed),
+    ClaimFeeSucceed(ClaimFeeSucceed),
+    FeeEmission(FeeEmission),
+    AddNewUserToTopHolder(AddNewUserToTopHolder),
+    RemoveUserFromTopHolder(RemoveUserFromTopHolder),
+    ReclaimIndex(ReclaimIndex),
+}
@@ -0,0 +1,7 @@
+//! In-memory model of the stake-for-fee program, to answer what-if questions and test bots
+//! without a validator.
+pub mod event;
+pub mod simulator;
+
+pub use event::Event;
+pub use simulator::{Simulator, SimulatorConfig};
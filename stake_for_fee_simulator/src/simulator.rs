@@ -0,0 +1,1116 @@
+use crate::event::{
+    AddNewUserToTopHolderFields, CancelUnstakeSucceedFields, ClaimFeeSucceedFields, Event,
+    FeeEmissionFields, ReclaimIndexFields, RemoveUserFromTopHolderFields, StakeEscrowCreatedFields,
+    UnstakeCreatedFields, UserStakeFields, WithdrawSucceedFields,
+};
+use common::{
+    constants::{
+        FULL_BALANCE_LIST_HARD_LIMIT, MIN_LOCK_ESCROW_CLAIM_FEE_DURATION, NO_FULL_BALANCE_INDEX,
+    },
+    decoder::{FullBalanceListState, TopStakerListState},
+    fee_release::get_released_fee,
+    math::fee::{get_fee_per_liquidity, get_new_fee},
+    pda::{
+        derive_full_balance_list_key, derive_m3m3_vault_key, derive_stake_escrow_key,
+        derive_top_staker_list_key,
+    },
+    top_list::find_smallest_full_balance_index,
+    unstake::get_release_at,
+};
+use m3m3::{
+    Configuration, FeeVault, FullBalanceListMetadata, Metrics, StakeEscrow, StakeForFeeError,
+    StakerBalance, StakerMetadata, TopListMetadata, TopStakerInfo, Unstake,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::{cmp::Reverse, collections::HashMap};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimulatorConfig {
+    pub pool: Pubkey,
+    pub stake_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    /// Whether the stake mint is token A of the pool. Fees in the stake token are restaked on
+    /// claim instead of being transferred.
+    pub is_stake_token_a: bool,
+    pub top_list_length: u16,
+    pub seconds_to_full_unlock: u64,
+    pub unstake_lock_duration: u64,
+    pub start_fee_distribute_timestamp: i64,
+    pub full_balance_list_capacity: u64,
+}
+
+impl SimulatorConfig {
+    pub fn new(pool: Pubkey, stake_mint: Pubkey, quote_mint: Pubkey) -> Self {
+        Self {
+            pool,
+            stake_mint,
+            quote_mint,
+            is_stake_token_a: true,
+            top_list_length: 10,
+            seconds_to_full_unlock: 86_400,
+            unstake_lock_duration: 86_400,
+            start_fee_distribute_timestamp: 0,
+            full_balance_list_capacity: FULL_BALANCE_LIST_HARD_LIMIT,
+        }
+    }
+}
+
+/// Fee vault with its top staker list, full balance list, stake escrows and unstakes held in
+/// memory.
+///
+/// Instructions take the current unix timestamp and either apply fully or leave the state
+/// untouched and return the program's error. Fees accrue in the lock escrow through
+/// [`Simulator::accrue_lock_escrow_fee`] and are claimed into the vault by every fee-updating
+/// instruction, at most once per `MIN_LOCK_ESCROW_CLAIM_FEE_DURATION`.
+#[derive(Clone, Debug)]
+pub struct Simulator {
+    vault: Pubkey,
+    is_stake_token_a: bool,
+    full_balance_list_capacity: u64,
+    fee_vault: FeeVault,
+    top_staker_list: TopStakerListState,
+    full_balance_list: FullBalanceListState,
+    stake_escrows: HashMap<Pubkey, StakeEscrow>,
+    escrow_by_owner: HashMap<Pubkey, Pubkey>,
+    unstakes: HashMap<Pubkey, Unstake>,
+    lock_escrow_fee_a: u64,
+    lock_escrow_fee_b: u64,
+    events: Vec<Event>,
+    undo_log: UndoLog,
+}
+
+/// Entries of the large collections an instruction overwrote, with their previous value, to
+/// restore if it fails. The rest of the state is small enough to copy up front.
+#[derive(Clone, Debug, Default)]
+struct UndoLog {
+    stake_escrows: Vec<(Pubkey, Option<StakeEscrow>)>,
+    owners: Vec<Pubkey>,
+    unstakes: Vec<(Pubkey, Option<Unstake>)>,
+    full_balance_entries: Vec<(usize, StakerBalance)>,
+}
+
+type Result<T> = std::result::Result<T, StakeForFeeError>;
+
+/// Top list ordering: larger stake ranks higher, and on equal stake the earlier full balance
+/// index does.
+fn rank(stake_amount: u64, full_balance_index: u64) -> (u64, Reverse<u64>) {
+    (stake_amount, Reverse(full_balance_index))
+}
+
+impl Simulator {
+    pub fn new(config: SimulatorConfig, creator: Pubkey, current_time: i64) -> Result<Self> {
+        if config.top_list_length == 0 {
+            return Err(StakeForFeeError::InvalidTopListLength);
+        }
+        if config.seconds_to_full_unlock == 0 {
+            return Err(StakeForFeeError::InvalidSecondsToFullUnlock);
+        }
+        if config.unstake_lock_duration == 0 {
+            return Err(StakeForFeeError::InvalidUnstakeLockDuration);
+        }
+
+        let vault = derive_m3m3_vault_key(config.pool);
+        let start_fee_distribute_timestamp =
+            config.start_fee_distribute_timestamp.max(current_time);
+
+        let fee_vault = FeeVault {
+            lock_escrow: Pubkey::default(),
+            stake_mint: config.stake_mint,
+            quote_mint: config.quote_mint,
+            pool: config.pool,
+            stake_token_vault: Pubkey::default(),
+            quote_token_vault: Pubkey::default(),
+            top_staker_list: derive_top_staker_list_key(vault),
+            full_balance_list: derive_full_balance_list_key(vault),
+            metrics: Metrics {
+                total_staked_amount: 0,
+                total_stake_escrow_count: 0,
+                ongoing_total_partial_unstake_amount: 0,
+                padding0: 0,
+                total_fee_a_amount: 0,
+                total_fee_b_amount: 0,
+                user_total_claimed_fee_a: 0,
+                user_total_claimed_fee_b: 0,
+                padding: [0; 4],
+            },
+            configuration: Configuration {
+                seconds_to_full_unlock: config.seconds_to_full_unlock,
+                unstake_lock_duration: config.unstake_lock_duration,
+                start_fee_distribute_timestamp,
+                padding0: 0,
+                padding: [0; 4],
+            },
+            top_staker_info: TopStakerInfo {
+                top_list_length: config.top_list_length.into(),
+                current_length: 0,
+                effective_stake_amount: 0,
+                last_claim_fee_at: start_fee_distribute_timestamp,
+                last_updated_at: start_fee_distribute_timestamp,
+                locked_fee_a: 0,
+                locked_fee_b: 0,
+                padding0: 0,
+                cumulative_fee_a_per_liquidity: 0,
+                cumulative_fee_b_per_liquidity: 0,
+                padding: [0; 4],
+            },
+            creator,
+            created_at: current_time,
+            bump: 0,
+            padding0: [0; 7],
+            padding: [0; 20],
+        };
+
+        Ok(Self {
+            vault,
+            is_stake_token_a: config.is_stake_token_a,
+            full_balance_list_capacity: config.full_balance_list_capacity,
+            fee_vault,
+            top_staker_list: TopStakerListState {
+                metadata: TopListMetadata { vault },
+                stakers: Vec::new(),
+            },
+            full_balance_list: FullBalanceListState {
+                metadata: FullBalanceListMetadata { vault, length: 0 },
+                stakers: Vec::new(),
+            },
+            stake_escrows: HashMap::new(),
+            escrow_by_owner: HashMap::new(),
+            unstakes: HashMap::new(),
+            lock_escrow_fee_a: 0,
+            lock_escrow_fee_b: 0,
+            events: Vec::new(),
+            undo_log: UndoLog::default(),
+        })
+    }
+
+    pub fn vault(&self) -> Pubkey {
+        self.vault
+    }
+
+    pub fn fee_vault(&self) -> &FeeVault {
+        &self.fee_vault
+    }
+
+    pub fn top_staker_list(&self) -> &TopStakerListState {
+        &self.top_staker_list
+    }
+
+    pub fn full_balance_list(&self) -> &FullBalanceListState {
+        &self.full_balance_list
+    }
+
+    pub fn stake_escrow(&self, owner: Pubkey) -> Option<&StakeEscrow> {
+        self.escrow_by_owner
+            .get(&owner)
+            .and_then(|escrow| self.stake_escrows.get(escrow))
+    }
+
+    pub fn unstake(&self, unstake: Pubkey) -> Option<&Unstake> {
+        self.unstakes.get(&unstake)
+    }
+
+    /// Fees not yet claimed from the lock escrow.
+    pub fn lock_escrow_fees(&self) -> (u64, u64) {
+        (self.lock_escrow_fee_a, self.lock_escrow_fee_b)
+    }
+
+    /// Take the events emitted since the last call.
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Trading fees earned by the vault's locked LP, claimable by the next fee update.
+    pub fn accrue_lock_escrow_fee(&mut self, fee_a: u64, fee_b: u64) -> Result<()> {
+        self.lock_escrow_fee_a = self
+            .lock_escrow_fee_a
+            .checked_add(fee_a)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        self.lock_escrow_fee_b = self
+            .lock_escrow_fee_b
+            .checked_add(fee_b)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn initialize_stake_escrow(&mut self, owner: Pubkey, current_time: i64) -> Result<Pubkey> {
+        self.transact(|sim| sim.process_initialize_stake_escrow(owner, current_time))
+    }
+
+    pub fn stake(&mut self, owner: Pubkey, amount: u64, current_time: i64) -> Result<()> {
+        self.transact(|sim| sim.process_stake(owner, amount, current_time))
+    }
+
+    /// Claim up to `max_fee` of the quote token fee and restake the stake token fee. Returns the
+    /// claimed quote token amount.
+    pub fn claim_fee(&mut self, owner: Pubkey, max_fee: u64, current_time: i64) -> Result<u64> {
+        self.transact(|sim| sim.process_claim_fee(owner, max_fee, current_time))
+    }
+
+    pub fn request_unstake(
+        &mut self,
+        owner: Pubkey,
+        unstake: Pubkey,
+        unstake_amount: u64,
+        current_time: i64,
+    ) -> Result<()> {
+        self.transact(|sim| {
+            sim.process_request_unstake(owner, unstake, unstake_amount, current_time)
+        })
+    }
+
+    pub fn cancel_unstake(
+        &mut self,
+        owner: Pubkey,
+        unstake: Pubkey,
+        current_time: i64,
+    ) -> Result<()> {
+        self.transact(|sim| sim.process_cancel_unstake(owner, unstake, current_time))
+    }
+
+    /// Returns the withdrawn stake token amount.
+    pub fn withdraw(&mut self, owner: Pubkey, unstake: Pubkey, current_time: i64) -> Result<u64> {
+        self.transact(|sim| sim.process_withdraw(owner, unstake, current_time))
+    }
+
+    pub fn claim_fee_crank(&mut self, current_time: i64) -> Result<()> {
+        self.transact(|sim| sim.update_fee_vault(current_time))
+    }
+
+    /// Run `process` on the state and roll back on error, like a failed transaction.
+    fn transact<T>(&mut self, process: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let fee_vault = self.fee_vault.clone();
+        let top_staker_list = self.top_staker_list.clone();
+        let full_balance_list_metadata = self.full_balance_list.metadata.clone();
+        let full_balance_list_len = self.full_balance_list.stakers.len();
+        let lock_escrow_fees = self.lock_escrow_fees();
+        let event_count = self.events.len();
+
+        let result = process(self);
+        let undo_log = std::mem::take(&mut self.undo_log);
+        if result.is_err() {
+            for (escrow, stake_escrow) in undo_log.stake_escrows.into_iter().rev() {
+                match stake_escrow {
+                    Some(stake_escrow) => self.stake_escrows.insert(escrow, stake_escrow),
+                    None => self.stake_escrows.remove(&escrow),
+                };
+            }
+            for owner in undo_log.owners {
+                self.escrow_by_owner.remove(&owner);
+            }
+            for (unstake_key, unstake) in undo_log.unstakes.into_iter().rev() {
+                match unstake {
+                    Some(unstake) => self.unstakes.insert(unstake_key, unstake),
+                    None => self.unstakes.remove(&unstake_key),
+                };
+            }
+            for (idx, staker) in undo_log.full_balance_entries.into_iter().rev() {
+                self.full_balance_list.stakers[idx] = staker;
+            }
+            self.full_balance_list
+                .stakers
+                .truncate(full_balance_list_len);
+            self.full_balance_list.metadata = full_balance_list_metadata;
+
+            self.fee_vault = fee_vault;
+            self.top_staker_list = top_staker_list;
+            (self.lock_escrow_fee_a, self.lock_escrow_fee_b) = lock_escrow_fees;
+            self.events.truncate(event_count);
+        }
+        result
+    }
+
+    fn process_initialize_stake_escrow(
+        &mut self,
+        owner: Pubkey,
+        current_time: i64,
+    ) -> Result<Pubkey> {
+        if self.escrow_by_owner.contains_key(&owner) {
+            return Err(StakeForFeeError::InvalidStakeEscrow);
+        }
+
+        let escrow = derive_stake_escrow_key(self.vault, owner);
+
+        let full_balance_index =
+            if self.full_balance_list.metadata.length < self.full_balance_list_capacity {
+                self.full_balance_list.stakers.push(StakerBalance {
+                    balance: 0,
+                    owner,
+                    is_in_top_list: 0,
+                    padding: [0; 7],
+                });
+                self.full_balance_list.metadata.length += 1;
+                self.full_balance_list.metadata.length - 1
+            } else {
+                NO_FULL_BALANCE_INDEX
+            };
+
+        let top_staker_info = &self.fee_vault.top_staker_info;
+        let previous = self.stake_escrows.insert(
+            escrow,
+            StakeEscrow {
+                owner,
+                vault: self.vault,
+                full_balance_index,
+                stake_amount: 0,
+                in_top_list: 0,
+                padding0: [0; 15],
+                ongoing_total_partial_unstake_amount: 0,
+                created_at: current_time,
+                fee_a_claimed_amount: 0,
+                fee_b_claimed_amount: 0,
+                fee_a_per_liquidity_checkpoint: top_staker_info.cumulative_fee_a_per_liquidity,
+                fee_b_per_liquidity_checkpoint: top_staker_info.cumulative_fee_b_per_liquidity,
+                fee_a_pending: 0,
+                fee_b_pending: 0,
+                padding: [0; 20],
+            },
+        );
+        self.undo_log.stake_escrows.push((escrow, previous));
+        self.escrow_by_owner.insert(owner, escrow);
+        self.undo_log.owners.push(owner);
+        self.fee_vault.metrics.total_stake_escrow_count += 1;
+
+        self.events.push(Event::StakeEscrowCreated(
+            StakeEscrowCreatedFields {
+                pool: self.fee_vault.pool,
+                vault: self.vault,
+                escrow,
+                owner,
+                full_balance_index,
+            }
+            .into(),
+        ));
+
+        Ok(escrow)
+    }
+
+    fn process_stake(&mut self, owner: Pubkey, amount: u64, current_time: i64) -> Result<()> {
+        if amount == 0 {
+            return Err(StakeForFeeError::InsufficientStakeAmount);
+        }
+        let escrow = self.escrow_key(owner)?;
+
+        self.update_fee_vault(current_time)?;
+        self.checkpoint_stake_escrow(escrow)?;
+        self.increase_stake(escrow, amount)?;
+
+        let stake_escrow = &self.stake_escrows[&escrow];
+        self.events.push(Event::UserStake(
+            UserStakeFields {
+                pool: self.fee_vault.pool,
+                vault: self.vault,
+                owner,
+                stake_amount: amount,
+                total_stake_amount: stake_escrow.stake_amount,
+                fee_a_pending: stake_escrow.fee_a_pending,
+                fee_b_pending: stake_escrow.fee_b_pending,
+                fee_a_per_liquidity_checkpoint: stake_escrow.fee_a_per_liquidity_checkpoint,
+                fee_b_per_liquidity_checkpoint: stake_escrow.fee_b_per_liquidity_checkpoint,
+            }
+            .into(),
+        ));
+
+        Ok(())
+    }
+
+    fn process_claim_fee(&mut self, owner: Pubkey, max_fee: u64, current_time: i64) -> Result<u64> {
+        let escrow = self.escrow_key(owner)?;
+
+        self.update_fee_vault(current_time)?;
+        self.checkpoint_stake_escrow(escrow)?;
+
+        let is_stake_token_a = self.is_stake_token_a;
+        let stake_escrow = self.stake_escrow_mut(escrow)?;
+        let (quote_fee_pending, stake_fee_pending) = if is_stake_token_a {
+            (
+                &mut stake_escrow.fee_b_pending,
+                &mut stake_escrow.fee_a_pending,
+            )
+        } else {
+            (
+                &mut stake_escrow.fee_a_pending,
+                &mut stake_escrow.fee_b_pending,
+            )
+        };
+
+        let quote_fee = (*quote_fee_pending).min(max_fee);
+        *quote_fee_pending -= quote_fee;
+        let restake_amount = std::mem::take(stake_fee_pending);
+
+        let (fee_a_amount, fee_b_amount) = if is_stake_token_a {
+            (restake_amount, quote_fee)
+        } else {
+            (quote_fee, restake_amount)
+        };
+
+        stake_escrow.fee_a_claimed_amount = stake_escrow
+            .fee_a_claimed_amount
+            .checked_add(fee_a_amount.into())
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        stake_escrow.fee_b_claimed_amount = stake_escrow
+            .fee_b_claimed_amount
+            .checked_add(fee_b_amount.into())
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        let metrics = &mut self.fee_vault.metrics;
+        metrics.user_total_claimed_fee_a = metrics
+            .user_total_claimed_fee_a
+            .checked_add(fee_a_amount.into())
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        metrics.user_total_claimed_fee_b = metrics
+            .user_total_claimed_fee_b
+            .checked_add(fee_b_amount.into())
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        if restake_amount > 0 {
+            self.increase_stake(escrow, restake_amount)?;
+        }
+
+        let stake_escrow = &self.stake_escrows[&escrow];
+        self.events.push(Event::ClaimFeeSucceed(
+            ClaimFeeSucceedFields {
+                stake_escrow: escrow,
+                pool: self.fee_vault.pool,
+                vault: self.vault,
+                owner,
+                fee_a_amount,
+                fee_b_amount,
+                total_fee_a_amount: stake_escrow.fee_a_claimed_amount,
+                total_fee_b_amount: stake_escrow.fee_b_claimed_amount,
+            }
+            .into(),
+        ));
+
+        Ok(quote_fee)
+    }
+
+    fn process_request_unstake(
+        &mut self,
+        owner: Pubkey,
+        unstake: Pubkey,
+        unstake_amount: u64,
+        current_time: i64,
+    ) -> Result<()> {
+        let escrow = self.escrow_key(owner)?;
+        let stake_amount = self.stake_escrows[&escrow].stake_amount;
+        if unstake_amount == 0 || unstake_amount > stake_amount {
+            return Err(StakeForFeeError::InsufficientStakeAmount);
+        }
+        if self.unstakes.contains_key(&unstake) {
+            return Err(StakeForFeeError::UndeterminedError);
+        }
+
+        self.update_fee_vault(current_time)?;
+        self.checkpoint_stake_escrow(escrow)?;
+
+        let stake_escrow = self.stake_escrow_mut(escrow)?;
+        stake_escrow.stake_amount -= unstake_amount;
+        stake_escrow.ongoing_total_partial_unstake_amount = stake_escrow
+            .ongoing_total_partial_unstake_amount
+            .checked_add(unstake_amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        let in_top_list = stake_escrow.in_top_list != 0;
+
+        let metrics = &mut self.fee_vault.metrics;
+        metrics.total_staked_amount = metrics
+            .total_staked_amount
+            .checked_sub(unstake_amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        metrics.ongoing_total_partial_unstake_amount = metrics
+            .ongoing_total_partial_unstake_amount
+            .checked_add(unstake_amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        self.sync_full_balance(escrow);
+
+        if in_top_list {
+            let top_staker_info = &mut self.fee_vault.top_staker_info;
+            top_staker_info.effective_stake_amount = top_staker_info
+                .effective_stake_amount
+                .checked_sub(unstake_amount)
+                .ok_or(StakeForFeeError::MathOverflow)?;
+            self.sync_top_list_slot(escrow);
+            self.promote_largest_outside_top_list(escrow)?;
+        }
+
+        let release_at = get_release_at(
+            self.fee_vault.configuration.unstake_lock_duration,
+            current_time,
+        )?;
+
+        let previous = self.unstakes.insert(
+            unstake,
+            Unstake {
+                stake_escrow: escrow,
+                unstake_amount,
+                created_at: current_time,
+                release_at,
+                padding: [0; 30],
+            },
+        );
+        self.undo_log.unstakes.push((unstake, previous));
+
+        let stake_escrow = &self.stake_escrows[&escrow];
+        self.events.push(Event::UnstakeCreated(
+            UnstakeCreatedFields {
+                unstake,
+                pool: self.fee_vault.pool,
+                vault: self.vault,
+                owner,
+                amount: unstake_amount,
+                new_stake_escrow_amount: stake_escrow.stake_amount,
+                new_stake_escrow_ongoing_total_unstake_amount: stake_escrow
+                    .ongoing_total_partial_unstake_amount,
+                fee_a_pending: stake_escrow.fee_a_pending,
+                fee_b_pending: stake_escrow.fee_b_pending,
+                fee_a_per_liquidity_checkpoint: stake_escrow.fee_a_per_liquidity_checkpoint,
+                fee_b_per_liquidity_checkpoint: stake_escrow.fee_b_per_liquidity_checkpoint,
+                start_at: current_time,
+                end_at: release_at,
+            }
+            .into(),
+        ));
+
+        Ok(())
+    }
+
+    fn process_cancel_unstake(
+        &mut self,
+        owner: Pubkey,
+        unstake: Pubkey,
+        current_time: i64,
+    ) -> Result<()> {
+        let escrow = self.escrow_key(owner)?;
+        let unstake_amount = self.owned_unstake(escrow, unstake)?.unstake_amount;
+
+        self.update_fee_vault(current_time)?;
+        self.checkpoint_stake_escrow(escrow)?;
+
+        let stake_escrow = self.stake_escrow_mut(escrow)?;
+        stake_escrow.ongoing_total_partial_unstake_amount = stake_escrow
+            .ongoing_total_partial_unstake_amount
+            .checked_sub(unstake_amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        let metrics = &mut self.fee_vault.metrics;
+        metrics.ongoing_total_partial_unstake_amount = metrics
+            .ongoing_total_partial_unstake_amount
+            .checked_sub(unstake_amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        self.increase_stake(escrow, unstake_amount)?;
+        self.remove_unstake(unstake);
+
+        let stake_escrow = &self.stake_escrows[&escrow];
+        self.events.push(Event::CancelUnstakeSucceed(
+            CancelUnstakeSucceedFields {
+                unstake,
+                pool: self.fee_vault.pool,
+                vault: self.vault,
+                owner,
+                amount: unstake_amount,
+                new_stake_escrow_amount: stake_escrow.stake_amount,
+                new_stake_escrow_ongoing_total_unstake_amount: stake_escrow
+                    .ongoing_total_partial_unstake_amount,
+                fee_a_pending: stake_escrow.fee_a_pending,
+                fee_b_pending: stake_escrow.fee_b_pending,
+                fee_a_per_liquidity_checkpoint: stake_escrow.fee_a_per_liquidity_checkpoint,
+                fee_b_per_liquidity_checkpoint: stake_escrow.fee_b_per_liquidity_checkpoint,
+            }
+            .into(),
+        ));
+
+        Ok(())
+    }
+
+    fn process_withdraw(
+        &mut self,
+        owner: Pubkey,
+        unstake: Pubkey,
+        current_time: i64,
+    ) -> Result<u64> {
+        let escrow = self.escrow_key(owner)?;
+        let Unstake {
+            unstake_amount,
+            release_at,
+            ..
+        } = *self.owned_unstake(escrow, unstake)?;
+
+        if current_time < release_at {
+            return Err(StakeForFeeError::CannotWithdrawUnstakeAmount);
+        }
+
+        let stake_escrow = self.stake_escrow_mut(escrow)?;
+        stake_escrow.ongoing_total_partial_unstake_amount = stake_escrow
+            .ongoing_total_partial_unstake_amount
+            .checked_sub(unstake_amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        let ongoing_total_partial_unstake_amount =
+            stake_escrow.ongoing_total_partial_unstake_amount;
+
+        let metrics = &mut self.fee_vault.metrics;
+        metrics.ongoing_total_partial_unstake_amount = metrics
+            .ongoing_total_partial_unstake_amount
+            .checked_sub(unstake_amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        self.remove_unstake(unstake);
+
+        self.events.push(Event::WithdrawSucceed(
+            WithdrawSucceedFields {
+                unstake,
+                pool: self.fee_vault.pool,
+                vault: self.vault,
+                owner,
+                amount: unstake_amount,
+                new_stake_escrow_ongoing_total_unstake_amount: ongoing_total_partial_unstake_amount,
+            }
+            .into(),
+        ));
+
+        Ok(unstake_amount)
+    }
+
+    /// Claim the lock escrow fees into the locked fees, then drip the locked fees to the top list
+    /// linearly over `seconds_to_full_unlock`. Nothing drips while the top list is empty.
+    fn update_fee_vault(&mut self, current_time: i64) -> Result<()> {
+        let start_fee_distribute_timestamp =
+            self.fee_vault.configuration.start_fee_distribute_timestamp;
+        let seconds_to_full_unlock = self.fee_vault.configuration.seconds_to_full_unlock;
+
+        if current_time < start_fee_distribute_timestamp {
+            return Ok(());
+        }
+
+        let top_staker_info = &mut self.fee_vault.top_staker_info;
+
+        let (token_a_claimed, token_b_claimed) = if current_time
+            .saturating_sub(top_staker_info.last_claim_fee_at)
+            >= MIN_LOCK_ESCROW_CLAIM_FEE_DURATION
+        {
+            top_staker_info.last_claim_fee_at = current_time;
+            (
+                std::mem::take(&mut self.lock_escrow_fee_a),
+                std::mem::take(&mut self.lock_escrow_fee_b),
+            )
+        } else {
+            (0, 0)
+        };
+
+        top_staker_info.locked_fee_a = top_staker_info
+            .locked_fee_a
+            .checked_add(token_a_claimed)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        top_staker_info.locked_fee_b = top_staker_info
+            .locked_fee_b
+            .checked_add(token_b_claimed)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        let effective_stake_amount = top_staker_info.effective_stake_amount;
+        let (token_a_released, token_b_released) = if effective_stake_amount == 0 {
+            (0, 0)
+        } else {
+            (
+                get_released_fee(
+                    top_staker_info.locked_fee_a,
+                    top_staker_info.last_updated_at,
+                    seconds_to_full_unlock,
+                    current_time,
+                )?,
+                get_released_fee(
+                    top_staker_info.locked_fee_b,
+                    top_staker_info.last_updated_at,
+                    seconds_to_full_unlock,
+                    current_time,
+                )?,
+            )
+        };
+
+        top_staker_info.locked_fee_a -= token_a_released;
+        top_staker_info.locked_fee_b -= token_b_released;
+        top_staker_info.cumulative_fee_a_per_liquidity = top_staker_info
+            .cumulative_fee_a_per_liquidity
+            .checked_add(get_fee_per_liquidity(
+                token_a_released,
+                effective_stake_amount,
+            )?)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        top_staker_info.cumulative_fee_b_per_liquidity = top_staker_info
+            .cumulative_fee_b_per_liquidity
+            .checked_add(get_fee_per_liquidity(
+                token_b_released,
+                effective_stake_amount,
+            )?)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        top_staker_info.last_updated_at = top_staker_info.last_updated_at.max(current_time);
+
+        let metrics = &mut self.fee_vault.metrics;
+        metrics.total_fee_a_amount = metrics
+            .total_fee_a_amount
+            .checked_add(token_a_claimed.into())
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        metrics.total_fee_b_amount = metrics
+            .total_fee_b_amount
+            .checked_add(token_b_claimed.into())
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        if token_a_claimed > 0
+            || token_b_claimed > 0
+            || token_a_released > 0
+            || token_b_released > 0
+        {
+            let top_staker_info = &self.fee_vault.top_staker_info;
+            self.events.push(Event::FeeEmission(
+                FeeEmissionFields {
+                    pool: self.fee_vault.pool,
+                    vault: self.vault,
+                    token_a_claimed,
+                    token_b_claimed,
+                    token_a_released,
+                    token_b_released,
+                    cumulative_fee_a_per_liquidity: top_staker_info.cumulative_fee_a_per_liquidity,
+                    cumulative_fee_b_per_liquidity: top_staker_info.cumulative_fee_b_per_liquidity,
+                    effective_stake_amount,
+                }
+                .into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Move the fee earned since the last checkpoint into the escrow's pending fee. Escrows
+    /// outside the top list earn nothing.
+    fn checkpoint_stake_escrow(&mut self, escrow: Pubkey) -> Result<()> {
+        let top_staker_info = self.fee_vault.top_staker_info.clone();
+        let stake_escrow = self.stake_escrow_mut(escrow)?;
+
+        if stake_escrow.in_top_list != 0 {
+            let new_fee_a = get_new_fee(
+                top_staker_info.cumulative_fee_a_per_liquidity,
+                stake_escrow.fee_a_per_liquidity_checkpoint,
+                stake_escrow.stake_amount,
+            )?;
+            let new_fee_b = get_new_fee(
+                top_staker_info.cumulative_fee_b_per_liquidity,
+                stake_escrow.fee_b_per_liquidity_checkpoint,
+                stake_escrow.stake_amount,
+            )?;
+
+            stake_escrow.fee_a_pending = stake_escrow
+                .fee_a_pending
+                .checked_add(new_fee_a)
+                .ok_or(StakeForFeeError::MathOverflow)?;
+            stake_escrow.fee_b_pending = stake_escrow
+                .fee_b_pending
+                .checked_add(new_fee_b)
+                .ok_or(StakeForFeeError::MathOverflow)?;
+        }
+
+        stake_escrow.fee_a_per_liquidity_checkpoint =
+            top_staker_info.cumulative_fee_a_per_liquidity;
+        stake_escrow.fee_b_per_liquidity_checkpoint =
+            top_staker_info.cumulative_fee_b_per_liquidity;
+
+        Ok(())
+    }
+
+    /// Add `amount` to the escrow's stake and update its full balance and top list standing. The
+    /// escrow must be checkpointed.
+    fn increase_stake(&mut self, escrow: Pubkey, amount: u64) -> Result<()> {
+        let stake_escrow = self.stake_escrow_mut(escrow)?;
+        stake_escrow.stake_amount = stake_escrow
+            .stake_amount
+            .checked_add(amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+        let in_top_list = stake_escrow.in_top_list != 0;
+
+        let metrics = &mut self.fee_vault.metrics;
+        metrics.total_staked_amount = metrics
+            .total_staked_amount
+            .checked_add(amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        if self.stake_escrows[&escrow].full_balance_index == NO_FULL_BALANCE_INDEX {
+            self.reclaim_full_balance_index(escrow)?;
+        }
+        self.sync_full_balance(escrow);
+
+        if in_top_list {
+            let top_staker_info = &mut self.fee_vault.top_staker_info;
+            top_staker_info.effective_stake_amount = top_staker_info
+                .effective_stake_amount
+                .checked_add(amount)
+                .ok_or(StakeForFeeError::MathOverflow)?;
+            self.sync_top_list_slot(escrow);
+        } else {
+            self.try_enter_top_list(escrow)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the escrow's stake to its full balance list slot, if it has one.
+    fn sync_full_balance(&mut self, escrow: Pubkey) {
+        let stake_escrow = &self.stake_escrows[&escrow];
+        if stake_escrow.full_balance_index != NO_FULL_BALANCE_INDEX {
+            let (balance, in_top_list) = (stake_escrow.stake_amount, stake_escrow.in_top_list);
+            let staker = self.full_balance_entry_mut(stake_escrow.full_balance_index as usize);
+            staker.balance = balance;
+            staker.is_in_top_list = in_top_list;
+        }
+    }
+
+    /// Give an escrow without a full balance list slot the one of the `smallest_stake_escrow` the
+    /// client passes, if the escrow now holds more.
+    fn reclaim_full_balance_index(&mut self, escrow: Pubkey) -> Result<()> {
+        let stake_escrow = &self.stake_escrows[&escrow];
+        let (owner, stake_amount) = (stake_escrow.owner, stake_escrow.stake_amount);
+
+        let reclaim_index = find_smallest_full_balance_index(owner, &self.full_balance_list)
+            .filter(|idx| self.full_balance_list.stakers[*idx].balance < stake_amount)
+            .ok_or(StakeForFeeError::FullBalanceListFull)?;
+        let out_staker = &self.full_balance_list.stakers[reclaim_index];
+        let (out_owner, out_owner_balance) = (out_staker.owner, out_staker.balance);
+        let out_escrow = self.escrow_key(out_owner)?;
+
+        // Only picked when every entry is a top staker. It leaves the top list along with its
+        // slot, which the reclaiming escrow may then take.
+        if out_staker.is_in_top_list != 0 {
+            self.remove_from_top_list(out_escrow)?;
+            self.top_staker_list
+                .stakers
+                .retain(|staker| staker.owner != out_owner);
+            self.fee_vault.top_staker_info.current_length =
+                self.top_staker_list.stakers.len() as u64;
+        }
+
+        self.stake_escrow_mut(out_escrow)?.full_balance_index = NO_FULL_BALANCE_INDEX;
+        self.stake_escrow_mut(escrow)?.full_balance_index = reclaim_index as u64;
+
+        *self.full_balance_entry_mut(reclaim_index) = StakerBalance {
+            balance: stake_amount,
+            owner,
+            is_in_top_list: 0,
+            padding: [0; 7],
+        };
+
+        self.events.push(Event::ReclaimIndex(
+            ReclaimIndexFields {
+                vault: self.vault,
+                in_owner: owner,
+                in_owner_balance: stake_amount,
+                out_owner,
+                out_owner_balance,
+                reclaim_index: reclaim_index as u64,
+            }
+            .into(),
+        ));
+
+        Ok(())
+    }
+
+    fn sync_top_list_slot(&mut self, escrow: Pubkey) {
+        let stake_escrow = &self.stake_escrows[&escrow];
+        if let Some(staker) = self
+            .top_staker_list
+            .stakers
+            .iter_mut()
+            .find(|staker| staker.owner == stake_escrow.owner)
+        {
+            staker.stake_amount = stake_escrow.stake_amount;
+        }
+    }
+
+    /// Add the escrow to the top list if there is a free slot, or if it ranks above the smallest
+    /// top staker, which is then removed.
+    fn try_enter_top_list(&mut self, escrow: Pubkey) -> Result<()> {
+        let stake_escrow = &self.stake_escrows[&escrow];
+        if stake_escrow.stake_amount == 0
+            || stake_escrow.full_balance_index == NO_FULL_BALANCE_INDEX
+        {
+            return Ok(());
+        }
+
+        let top_list_length = self.fee_vault.top_staker_info.top_list_length as usize;
+        if self.top_staker_list.stakers.len() < top_list_length {
+            return self.add_to_top_list(escrow, None);
+        }
+
+        let smallest = self
+            .top_staker_list
+            .stakers
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, staker)| rank(staker.stake_amount, staker.full_balance_index as u64))
+            .map(|(slot, staker)| {
+                (
+                    slot,
+                    staker.owner,
+                    staker.stake_amount,
+                    staker.full_balance_index,
+                )
+            });
+
+        if let Some((slot, smallest_owner, smallest_stake_amount, smallest_index)) = smallest {
+            if rank(stake_escrow.stake_amount, stake_escrow.full_balance_index)
+                > rank(smallest_stake_amount, smallest_index as u64)
+            {
+                let smallest_escrow = self.escrow_key(smallest_owner)?;
+                self.remove_from_top_list(smallest_escrow)?;
+                self.add_to_top_list(escrow, Some(slot))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// After a top staker's stake dropped, swap it with the largest staker outside the top list
+    /// if that one now ranks higher.
+    fn promote_largest_outside_top_list(&mut self, escrow: Pubkey) -> Result<()> {
+        let largest = self
+            .full_balance_list
+            .stakers
+            .iter()
+            .enumerate()
+            .filter(|(_, staker)| staker.is_in_top_list == 0 && staker.balance > 0)
+            .max_by_key(|(idx, staker)| rank(staker.balance, *idx as u64))
+            .map(|(idx, staker)| (idx, staker.owner, staker.balance));
+
+        let stake_escrow = &self.stake_escrows[&escrow];
+        if let Some((idx, candidate_owner, candidate_balance)) = largest {
+            if rank(candidate_balance, idx as u64)
+                > rank(stake_escrow.stake_amount, stake_escrow.full_balance_index)
+            {
+                let slot = self
+                    .top_staker_list
+                    .stakers
+                    .iter()
+                    .position(|staker| staker.owner == stake_escrow.owner);
+                let candidate_escrow = self.escrow_key(candidate_owner)?;
+
+                self.remove_from_top_list(escrow)?;
+                self.checkpoint_stake_escrow(candidate_escrow)?;
+                self.add_to_top_list(candidate_escrow, slot)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Put the checkpointed escrow into `slot`, or a new slot.
+    fn add_to_top_list(&mut self, escrow: Pubkey, slot: Option<usize>) -> Result<()> {
+        let stake_escrow = self.stake_escrow_mut(escrow)?;
+        stake_escrow.in_top_list = 1;
+        let stake_escrow = stake_escrow.clone();
+
+        let staker = StakerMetadata {
+            stake_amount: stake_escrow.stake_amount,
+            full_balance_index: stake_escrow.full_balance_index as i64,
+            owner: stake_escrow.owner,
+        };
+        match slot {
+            Some(slot) => self.top_staker_list.stakers[slot] = staker,
+            None => self.top_staker_list.stakers.push(staker),
+        }
+
+        let top_staker_info = &mut self.fee_vault.top_staker_info;
+        top_staker_info.current_length = self.top_staker_list.stakers.len() as u64;
+        top_staker_info.effective_stake_amount = top_staker_info
+            .effective_stake_amount
+            .checked_add(stake_escrow.stake_amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        self.full_balance_entry_mut(stake_escrow.full_balance_index as usize)
+            .is_in_top_list = 1;
+
+        self.events.push(Event::AddNewUserToTopHolder(
+            AddNewUserToTopHolderFields {
+                pool: self.fee_vault.pool,
+                vault: self.vault,
+                owner: stake_escrow.owner,
+                stake_amount: stake_escrow.stake_amount,
+                fee_a_pending: stake_escrow.fee_a_pending,
+                fee_b_pending: stake_escrow.fee_b_pending,
+                fee_a_per_liquidity_checkpoint: stake_escrow.fee_a_per_liquidity_checkpoint,
+                fee_b_per_liquidity_checkpoint: stake_escrow.fee_b_per_liquidity_checkpoint,
+            }
+            .into(),
+        ));
+
+        Ok(())
+    }
+
+    /// Take the escrow out of the top list, keeping its slot for the caller to refill. Its fee is
+    /// checkpointed first so it keeps what it earned.
+    fn remove_from_top_list(&mut self, escrow: Pubkey) -> Result<()> {
+        self.checkpoint_stake_escrow(escrow)?;
+
+        let stake_escrow = self.stake_escrow_mut(escrow)?;
+        stake_escrow.in_top_list = 0;
+        let stake_escrow = stake_escrow.clone();
+
+        let top_staker_info = &mut self.fee_vault.top_staker_info;
+        top_staker_info.effective_stake_amount = top_staker_info
+            .effective_stake_amount
+            .checked_sub(stake_escrow.stake_amount)
+            .ok_or(StakeForFeeError::MathOverflow)?;
+
+        self.full_balance_entry_mut(stake_escrow.full_balance_index as usize)
+            .is_in_top_list = 0;
+
+        self.events.push(Event::RemoveUserFromTopHolder(
+            RemoveUserFromTopHolderFields {
+                pool: self.fee_vault.pool,
+                vault: self.vault,
+                owner: stake_escrow.owner,
+                stake_amount: stake_escrow.stake_amount,
+                fee_a_pending: stake_escrow.fee_a_pending,
+                fee_b_pending: stake_escrow.fee_b_pending,
+                fee_a_per_liquidity_checkpoint: stake_escrow.fee_a_per_liquidity_checkpoint,
+                fee_b_per_liquidity_checkpoint: stake_escrow.fee_b_per_liquidity_checkpoint,
+            }
+            .into(),
+        ));
+
+        Ok(())
+    }
+
+    fn escrow_key(&self, owner: Pubkey) -> Result<Pubkey> {
+        self.escrow_by_owner
+            .get(&owner)
+            .copied()
+            .ok_or(StakeForFeeError::InvalidStakeEscrow)
+    }
+
+    fn stake_escrow_mut(&mut self, escrow: Pubkey) -> Result<&mut StakeEscrow> {
+        let stake_escrow = self
+            .stake_escrows
+            .get_mut(&escrow)
+            .ok_or(StakeForFeeError::InvalidStakeEscrow)?;
+        self.undo_log
+            .stake_escrows
+            .push((escrow, Some(stake_escrow.clone())));
+        Ok(stake_escrow)
+    }
+
+    fn full_balance_entry_mut(&mut self, idx: usize) -> &mut StakerBalance {
+        let staker = &mut self.full_balance_list.stakers[idx];
+        self.undo_log
+            .full_balance_entries
+            .push((idx, staker.clone()));
+        staker
+    }
+
+    fn remove_unstake(&mut self, unstake: Pubkey) {
+        let previous = self.unstakes.remove(&unstake);
+        self.undo_log.unstakes.push((unstake, previous));
+    }
+
+    fn owned_unstake(&self, escrow: Pubkey, unstake: Pubkey) -> Result<&Unstake> {
+        self.unstakes
+            .get(&unstake)
+            .filter(|unstake| unstake.stake_escrow == escrow)
+            .ok_or(StakeForFeeError::InvalidStakeEscrow)
+    }
+}
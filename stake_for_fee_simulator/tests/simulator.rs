@@ -0,0 +1,360 @@
+use common::{
+    constants::NO_FULL_BALANCE_INDEX,
+    fee_release::get_released_fees_with_pending_claim,
+    invariant::{check_vault_invariants, VaultAccounts},
+    math::fee::get_stake_escrow_pending_fees,
+    pda::derive_stake_escrow_key,
+    preview::PreviewState,
+};
+use m3m3::StakeForFeeError;
+use solana_sdk::pubkey::Pubkey;
+use stake_for_fee_simulator::{
+    event::{
+        AddNewUserToTopHolderFields, ClaimFeeSucceedFields, ReclaimIndexFields,
+        RemoveUserFromTopHolderFields,
+    },
+    Event, Simulator, SimulatorConfig,
+};
+
+const START: i64 = 1_700_000_000;
+
+fn simulator(top_list_length: u16, full_balance_list_capacity: u64) -> Simulator {
+    let mut config = SimulatorConfig::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    config.top_list_length = top_list_length;
+    config.seconds_to_full_unlock = 1_000;
+    config.unstake_lock_duration = 3_600;
+    config.full_balance_list_capacity = full_balance_list_capacity;
+
+    Simulator::new(config, Pubkey::new_unique(), START).unwrap()
+}
+
+fn staker(sim: &mut Simulator, amount: u64, current_time: i64) -> Pubkey {
+    let owner = Pubkey::new_unique();
+    sim.initialize_stake_escrow(owner, current_time).unwrap();
+    sim.stake(owner, amount, current_time).unwrap();
+    owner
+}
+
+fn in_top_list(sim: &Simulator, owner: Pubkey) -> bool {
+    sim.stake_escrow(owner).unwrap().in_top_list != 0
+}
+
+fn preview_state(sim: &Simulator) -> PreviewState<'_> {
+    PreviewState {
+        fee_vault: sim.fee_vault(),
+        top_staker_list: sim.top_staker_list(),
+        full_balance_list: sim.full_balance_list(),
+    }
+}
+
+/// Check the vault with the escrows of `owners`, without unstakes and with ample token balances.
+fn assert_consistent(sim: &Simulator, owners: &[Pubkey]) {
+    let stake_escrows: Vec<_> = owners
+        .iter()
+        .map(|owner| sim.stake_escrow(*owner).unwrap().clone())
+        .collect();
+    let report = check_vault_invariants(&VaultAccounts {
+        fee_vault: sim.fee_vault(),
+        top_staker_list: sim.top_staker_list(),
+        full_balance_list: sim.full_balance_list(),
+        stake_escrows: &stake_escrows,
+        unstakes: &[],
+        stake_token_balance: u64::MAX,
+        quote_token_balance: u64::MAX,
+        is_stake_token_a: true,
+    })
+    .unwrap();
+    assert!(report.is_consistent(), "{:?}", report.violations);
+}
+
+#[test]
+fn fees_drip_linearly_to_top_stakers() {
+    let mut sim = simulator(2, 10);
+    let alice = staker(&mut sim, 300, START);
+    let bob = staker(&mut sim, 100, START);
+
+    sim.accrue_lock_escrow_fee(4_000, 8_000).unwrap();
+    // Too early to claim from the lock escrow.
+    sim.claim_fee_crank(START + 100).unwrap();
+    assert_eq!(sim.fee_vault().top_staker_info.locked_fee_a, 0);
+
+    // Claimed fee starts dripping right away, 200 seconds since the last update.
+    sim.claim_fee_crank(START + 300).unwrap();
+    assert_eq!(sim.fee_vault().top_staker_info.locked_fee_a, 3_200);
+    assert_eq!(sim.fee_vault().metrics.total_fee_b_amount, 8_000);
+
+    // Another half of the locked fee is released 500 seconds later, split 3:1.
+    let quote_fee = sim.claim_fee(alice, u64::MAX, START + 800).unwrap();
+    assert_eq!(quote_fee, 3_600);
+    let alice_escrow = sim.stake_escrow(alice).unwrap();
+    assert_eq!(alice_escrow.stake_amount, 300 + 1_800);
+    assert_eq!(
+        (alice_escrow.fee_a_pending, alice_escrow.fee_b_pending),
+        (0, 0)
+    );
+
+    let events = sim.drain_events();
+    assert!(events.iter().any(|event| matches!(
+        event,
+        Event::ClaimFeeSucceed(claim)
+            if ClaimFeeSucceedFields::from(claim).fee_a_amount == 1_800
+                && ClaimFeeSucceedFields::from(claim).fee_b_amount == 3_600
+    )));
+
+    // Restaked fee counts towards the stake from then on.
+    assert_eq!(
+        sim.fee_vault().top_staker_info.effective_stake_amount,
+        2_200
+    );
+    assert_eq!(sim.stake_escrow(bob).unwrap().stake_amount, 100);
+}
+
+#[test]
+fn pending_fees_match_off_chain_computation() {
+    let mut sim = simulator(3, 10);
+    let alice = staker(&mut sim, 1_234_567, START);
+    staker(&mut sim, 7_654_321, START);
+
+    sim.accrue_lock_escrow_fee(1_000_003, 77).unwrap();
+    sim.claim_fee_crank(START + 400).unwrap();
+    sim.accrue_lock_escrow_fee(999, 123_456).unwrap();
+
+    let current_time = START + 1_001;
+    let fee_vault = sim.fee_vault().clone();
+    let stake_escrow = sim.stake_escrow(alice).unwrap().clone();
+    let (pending_fee_a, pending_fee_b) = sim.lock_escrow_fees();
+
+    let release = get_released_fees_with_pending_claim(
+        &fee_vault,
+        current_time,
+        pending_fee_a,
+        pending_fee_b,
+    )
+    .unwrap();
+    let expected = get_stake_escrow_pending_fees(
+        &fee_vault.top_staker_info,
+        &stake_escrow,
+        release.released_fee_a,
+        release.released_fee_b,
+    )
+    .unwrap();
+
+    sim.claim_fee_crank(current_time).unwrap();
+    let claimed_b = sim.claim_fee(alice, u64::MAX, current_time).unwrap();
+    let claimed_a = sim.stake_escrow(alice).unwrap().stake_amount - 1_234_567;
+
+    assert_eq!((claimed_a, claimed_b), expected);
+}
+
+#[test]
+fn larger_stake_replaces_smallest_top_staker() {
+    let mut sim = simulator(2, 10);
+    let alice = staker(&mut sim, 100, START);
+    // Same stake as alice but a later full balance index, so it ranks lower.
+    let bob = staker(&mut sim, 100, START);
+    let carol = staker(&mut sim, 100, START);
+    assert!(in_top_list(&sim, alice) && in_top_list(&sim, bob));
+    assert!(!in_top_list(&sim, carol));
+    sim.drain_events();
+
+    sim.stake(carol, 1, START + 10).unwrap();
+    assert!(in_top_list(&sim, alice) && in_top_list(&sim, carol));
+    assert!(!in_top_list(&sim, bob));
+    assert_eq!(sim.fee_vault().top_staker_info.effective_stake_amount, 201);
+
+    let events = sim.drain_events();
+    assert!(matches!(
+        &events[..],
+        [
+            Event::RemoveUserFromTopHolder(removed),
+            Event::AddNewUserToTopHolder(added),
+            Event::UserStake(_),
+        ] if RemoveUserFromTopHolderFields::from(removed).owner == bob
+            && AddNewUserToTopHolderFields::from(added).owner == carol
+    ));
+}
+
+#[test]
+fn unstake_promotes_largest_staker_outside_top_list() {
+    let mut sim = simulator(1, 10);
+    let alice = staker(&mut sim, 500, START);
+    let bob = staker(&mut sim, 300, START);
+    let unstake = Pubkey::new_unique();
+
+    sim.request_unstake(alice, unstake, 250, START + 10)
+        .unwrap();
+    assert!(in_top_list(&sim, bob));
+    assert!(!in_top_list(&sim, alice));
+    assert_eq!(sim.fee_vault().top_staker_info.effective_stake_amount, 300);
+    assert_eq!(
+        sim.stake_escrow(alice)
+            .unwrap()
+            .ongoing_total_partial_unstake_amount,
+        250
+    );
+
+    assert_eq!(
+        sim.withdraw(alice, unstake, START + 10 + 3_599),
+        Err(StakeForFeeError::CannotWithdrawUnstakeAmount)
+    );
+
+    sim.cancel_unstake(alice, unstake, START + 20).unwrap();
+    assert!(in_top_list(&sim, alice));
+    assert!(!in_top_list(&sim, bob));
+    assert_eq!(
+        sim.fee_vault().metrics.ongoing_total_partial_unstake_amount,
+        0
+    );
+
+    let unstake = Pubkey::new_unique();
+    sim.request_unstake(bob, unstake, 300, START + 30).unwrap();
+    assert_eq!(sim.withdraw(bob, unstake, START + 30 + 3_600), Ok(300));
+    assert!(sim.unstake(unstake).is_none());
+    assert_eq!(sim.fee_vault().metrics.total_staked_amount, 500);
+}
+
+#[test]
+fn invalid_operation_leaves_state_untouched() {
+    let mut sim = simulator(2, 10);
+    let alice = staker(&mut sim, 100, START);
+    sim.accrue_lock_escrow_fee(1_000, 1_000).unwrap();
+    sim.drain_events();
+
+    let fee_vault = sim.fee_vault().clone();
+    assert_eq!(
+        sim.request_unstake(alice, Pubkey::new_unique(), 101, START + 500),
+        Err(StakeForFeeError::InsufficientStakeAmount)
+    );
+    assert_eq!(
+        sim.stake(Pubkey::new_unique(), 1, START + 500),
+        Err(StakeForFeeError::InvalidStakeEscrow)
+    );
+    assert_eq!(
+        sim.cancel_unstake(alice, Pubkey::new_unique(), START + 500),
+        Err(StakeForFeeError::InvalidStakeEscrow)
+    );
+
+    assert_eq!(sim.fee_vault(), &fee_vault);
+    assert_eq!(sim.lock_escrow_fees(), (1_000, 1_000));
+    assert!(sim.drain_events().is_empty());
+}
+
+#[test]
+fn full_balance_list_index_is_reclaimed_by_larger_staker() {
+    let mut sim = simulator(1, 2);
+    let alice = staker(&mut sim, 500, START);
+    let bob = staker(&mut sim, 100, START);
+
+    let carol = Pubkey::new_unique();
+    sim.initialize_stake_escrow(carol, START).unwrap();
+    assert_eq!(
+        sim.stake_escrow(carol).unwrap().full_balance_index,
+        NO_FULL_BALANCE_INDEX
+    );
+
+    // Fails after adding the stake, which is rolled back.
+    let fee_vault = sim.fee_vault().clone();
+    let full_balance_list = sim.full_balance_list().clone();
+    assert_eq!(
+        sim.stake(carol, 100, START + 1),
+        Err(StakeForFeeError::FullBalanceListFull)
+    );
+    assert_eq!(sim.stake_escrow(carol).unwrap().stake_amount, 0);
+    assert_eq!(sim.fee_vault(), &fee_vault);
+    assert_eq!(sim.full_balance_list(), &full_balance_list);
+
+    sim.drain_events();
+    sim.stake(carol, 101, START + 1).unwrap();
+    assert_eq!(sim.stake_escrow(carol).unwrap().full_balance_index, 1);
+    assert_eq!(
+        sim.stake_escrow(bob).unwrap().full_balance_index,
+        NO_FULL_BALANCE_INDEX
+    );
+    let Event::ReclaimIndex(reclaim) = &sim.drain_events()[0] else {
+        panic!("index should be reclaimed");
+    };
+    let reclaim = ReclaimIndexFields::from(reclaim);
+    assert_eq!(
+        (reclaim.in_owner, reclaim.out_owner, reclaim.reclaim_index),
+        (carol, bob, 1)
+    );
+    assert_consistent(&sim, &[alice, bob, carol]);
+}
+
+#[test]
+fn top_staker_index_is_reclaimed_when_every_staker_is_in_top_list() {
+    let mut sim = simulator(2, 2);
+    let alice = staker(&mut sim, 500, START);
+    let bob = staker(&mut sim, 100, START);
+
+    let carol = Pubkey::new_unique();
+    sim.initialize_stake_escrow(carol, START).unwrap();
+    let preview = preview_state(&sim)
+        .preview_stake(carol, sim.stake_escrow(carol), 101)
+        .unwrap();
+    sim.stake(carol, 101, START + 1).unwrap();
+    assert_eq!(preview.reclaimed_owner, Some(bob));
+    assert_eq!(preview.in_top_list, in_top_list(&sim, carol));
+    assert_eq!(
+        preview.effective_stake_amount,
+        sim.fee_vault().top_staker_info.effective_stake_amount
+    );
+
+    assert_eq!(sim.stake_escrow(carol).unwrap().full_balance_index, 1);
+    assert_eq!(
+        sim.stake_escrow(bob).unwrap().full_balance_index,
+        NO_FULL_BALANCE_INDEX
+    );
+    assert!(!in_top_list(&sim, bob));
+    assert!(in_top_list(&sim, alice) && in_top_list(&sim, carol));
+    let top_staker_info = &sim.fee_vault().top_staker_info;
+    assert_eq!(top_staker_info.current_length, 2);
+    assert_eq!(top_staker_info.effective_stake_amount, 601);
+    assert_consistent(&sim, &[alice, bob, carol]);
+}
+
+#[test]
+fn previews_match_simulated_outcome() {
+    let mut sim = simulator(2, 10);
+    let alice = staker(&mut sim, 300, START);
+    let bob = staker(&mut sim, 100, START);
+    staker(&mut sim, 50, START);
+    let dave = Pubkey::new_unique();
+
+    let preview = preview_state(&sim).preview_stake(dave, None, 200).unwrap();
+    sim.initialize_stake_escrow(dave, START + 10).unwrap();
+    sim.stake(dave, 200, START + 10).unwrap();
+    assert_eq!(
+        preview.stake_escrow,
+        derive_stake_escrow_key(sim.vault(), dave)
+    );
+    assert_eq!(preview.in_top_list, in_top_list(&sim, dave));
+    assert_eq!(preview.displaced_owner, Some(bob));
+    assert!(!in_top_list(&sim, bob));
+    assert_eq!(
+        preview.effective_stake_amount,
+        sim.fee_vault().top_staker_info.effective_stake_amount
+    );
+
+    // Alice drops below Bob, who takes her slot.
+    let preview = preview_state(&sim)
+        .preview_request_unstake(alice, sim.stake_escrow(alice), 250)
+        .unwrap();
+    sim.request_unstake(alice, Pubkey::new_unique(), 250, START + 20)
+        .unwrap();
+    assert_eq!(
+        preview.stake_amount,
+        sim.stake_escrow(alice).unwrap().stake_amount
+    );
+    assert_eq!(preview.in_top_list, in_top_list(&sim, alice));
+    assert_eq!(preview.promoted_owner, Some(bob));
+    assert!(in_top_list(&sim, bob));
+    assert_eq!(
+        preview.effective_stake_amount,
+        sim.fee_vault().top_staker_info.effective_stake_amount
+    );
+}